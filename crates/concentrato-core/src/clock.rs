@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A source of monotonic time for the [`Timer`](crate::Timer).
+pub trait Clock {
+    /// Time since an arbitrary origin fixed when the clock was created.
+    ///
+    /// Successive readings must never go backwards. Time the system spends
+    /// suspended is not included.
+    fn now(&self) -> Duration;
+
+    /// Total time the system has spent suspended since the clock's origin.
+    ///
+    /// Clocks that cannot tell keep the default, which never sees a suspend.
+    fn suspended(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// The system's monotonic clock.
+///
+/// On Linux, suspends are detected by how far `CLOCK_BOOTTIME`, which keeps
+/// counting while the machine sleeps, has run ahead of `CLOCK_MONOTONIC`,
+/// which does not.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    origin: Instant,
+    asleep_at_origin: Duration,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            asleep_at_origin: time_asleep(Bound::Upper),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn suspended(&self) -> Duration {
+        // The lower bound now less the upper one at the origin: a delay
+        // while reading either can only hide time asleep, never invent it.
+        time_asleep(Bound::Lower).saturating_sub(self.asleep_at_origin)
+    }
+}
+
+/// Which way an estimate of [`time_asleep`] may be off.
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    /// Never more than the true figure.
+    Lower,
+    /// Never less than the true figure.
+    Upper,
+}
+
+/// Time spent suspended since boot, off by however long it took to read
+/// the two clocks in the direction of `bound`.
+#[cfg(target_os = "linux")]
+fn time_asleep(bound: Bound) -> Duration {
+    fn read(clock: libc::clockid_t) -> Duration {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `ts` is valid for writes, and both clocks used below exist
+        // on every kernel since 2.6.39.
+        unsafe { libc::clock_gettime(clock, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+    // Whichever clock is read second also counts the delay between the two
+    // reads, which widens the gap if it is the boot clock and narrows it if
+    // it is the monotonic one.
+    match bound {
+        Bound::Lower => {
+            let boottime = read(libc::CLOCK_BOOTTIME);
+            boottime.saturating_sub(read(libc::CLOCK_MONOTONIC))
+        }
+        Bound::Upper => {
+            let monotonic = read(libc::CLOCK_MONOTONIC);
+            read(libc::CLOCK_BOOTTIME).saturating_sub(monotonic)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn time_asleep(_bound: Bound) -> Duration {
+    Duration::ZERO
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same reading, so a test can keep one handle while the
+/// timer owns another:
+///
+/// ```
+/// use std::time::Duration;
+/// use concentrato_core::{Cycle, FakeClock, Phase, Timer};
+///
+/// let clock = FakeClock::new();
+/// let mut timer = Timer::with_clock(Cycle::classic(), clock.clone());
+/// timer.start().unwrap();
+/// clock.advance(Duration::from_secs(25 * 60));
+/// timer.tick();
+/// assert_eq!(timer.phase(), Phase::ShortBreak);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeClock {
+    nanos: Arc<AtomicU64>,
+    asleep: Arc<AtomicU64>,
+}
+
+impl FakeClock {
+    /// Creates a clock reading zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(as_nanos(by), Ordering::SeqCst);
+    }
+
+    /// Moves the clock to `now`, which must not be earlier than its reading.
+    pub fn set(&self, now: Duration) {
+        let previous = self.nanos.swap(as_nanos(now), Ordering::SeqCst);
+        debug_assert!(previous <= as_nanos(now), "FakeClock moved backwards");
+    }
+
+    /// Simulates the system sleeping for `duration`, which leaves
+    /// [`now`](Clock::now) where it was.
+    pub fn suspend(&self, duration: Duration) {
+        self.asleep.fetch_add(as_nanos(duration), Ordering::SeqCst);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    fn suspended(&self) -> Duration {
+        Duration::from_nanos(self.asleep.load(Ordering::SeqCst))
+    }
+}
+
+fn as_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sees_no_suspend_while_awake() {
+        // However the reads are delayed, the estimate can only come out at
+        // nothing, short of the machine really going to sleep.
+        for _ in 0..10_000 {
+            let clock = MonotonicClock::new();
+            assert_eq!(clock.suspended(), Duration::ZERO);
+        }
+        let clock = MonotonicClock::new();
+        for _ in 0..10_000 {
+            assert_eq!(clock.suspended(), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn bounds_the_time_asleep() {
+        for _ in 0..10_000 {
+            assert!(time_asleep(Bound::Lower) <= time_asleep(Bound::Upper));
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{ParseDurationError, Phase, parse_duration};
+
+/// One phase of a [`Cycle`] and how long it lasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub phase: Phase,
+    pub duration: Duration,
+}
+
+impl Step {
+    pub fn new(phase: Phase, duration: Duration) -> Self {
+        Self { phase, duration }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CycleError {
+    #[error("a cycle needs at least one phase")]
+    Empty,
+    #[error("a cycle needs at least one work session")]
+    NoWork,
+    #[error("{phase} at position {position} of the cycle has zero length")]
+    ZeroLength { position: usize, phase: Phase },
+    #[error("there must be at least one work session before a long break")]
+    ZeroInterval,
+    #[error("invalid cycle {spec:?}: {reason}")]
+    Syntax { spec: String, reason: &'static str },
+    #[error(transparent)]
+    Duration(#[from] ParseDurationError),
+}
+
+/// The sequence of phases a [`Timer`](crate::Timer) repeats.
+///
+/// Besides building one step by step, a cycle can be parsed from a compact
+/// spec of comma separated `work/break` rounds, each optionally repeated with
+/// `xN`, and optionally followed by `then LONG` to turn the last round's break
+/// into a long break. Lengths use the syntax of [`parse_duration`], so bare
+/// numbers are minutes:
+///
+/// * `25/5x4 then 15` is the classic cycle,
+/// * `50/10x3 then 30` is three rounds of fifty minutes, the last followed by
+///   a half hour break,
+/// * `15/3` alternates quarter hours of work with short breaks forever.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cycle {
+    steps: Vec<Step>,
+}
+
+impl Default for Cycle {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Cycle {
+    /// Creates a cycle from its steps, which must include a work session and
+    /// all have a length.
+    pub fn new(steps: Vec<Step>) -> Result<Self, CycleError> {
+        if steps.is_empty() {
+            return Err(CycleError::Empty);
+        }
+        if let Some((position, step)) = steps.iter().enumerate().find(|(_, s)| s.duration.is_zero())
+        {
+            return Err(CycleError::ZeroLength {
+                position,
+                phase: step.phase,
+            });
+        }
+        if !steps.iter().any(|s| s.phase == Phase::Work) {
+            return Err(CycleError::NoWork);
+        }
+        Ok(Self { steps })
+    }
+
+    /// The usual pomodoro rhythm: `long_break_interval` work sessions
+    /// separated by short breaks, followed by a long break.
+    pub fn standard(
+        work: Duration,
+        short_break: Duration,
+        long_break: Duration,
+        long_break_interval: usize,
+    ) -> Result<Self, CycleError> {
+        if long_break_interval == 0 {
+            return Err(CycleError::ZeroInterval);
+        }
+        let mut steps = Vec::with_capacity(2 * long_break_interval);
+        for round in 1..=long_break_interval {
+            steps.push(Step::new(Phase::Work, work));
+            if round < long_break_interval {
+                steps.push(Step::new(Phase::ShortBreak, short_break));
+            } else {
+                steps.push(Step::new(Phase::LongBreak, long_break));
+            }
+        }
+        Self::new(steps)
+    }
+
+    /// Four 25 minute work sessions with 5 minute breaks, then 15 minutes off.
+    pub fn classic() -> Self {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        Self::standard(minutes(25), minutes(5), minutes(15), 4).expect("the classic cycle is valid")
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Number of steps before the cycle repeats; never zero.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Always false, since a cycle has at least one step.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The step at `position`, wrapping around at the end of the cycle.
+    pub fn step(&self, position: usize) -> Step {
+        self.steps[position % self.steps.len()]
+    }
+
+    /// Number of work sessions in one pass through the cycle.
+    pub fn work_sessions(&self) -> usize {
+        self.steps.iter().filter(|s| s.phase == Phase::Work).count()
+    }
+}
+
+impl FromStr for Cycle {
+    type Err = CycleError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let syntax = |reason| CycleError::Syntax {
+            spec: spec.to_owned(),
+            reason,
+        };
+        let (rounds, long_break) = match spec.split_once("then") {
+            Some((rounds, long_break)) => (rounds, Some(parse_duration(long_break)?)),
+            None => (spec, None),
+        };
+
+        let mut steps = Vec::new();
+        for round in rounds.split(',') {
+            let (lengths, repeat) = match round.split_once(['x', '×', '*']) {
+                Some((lengths, repeat)) => {
+                    let repeat: usize = repeat
+                        .trim()
+                        .parse()
+                        .map_err(|_| syntax("repeat counts must be whole numbers"))?;
+                    if repeat == 0 {
+                        return Err(syntax("a round cannot be repeated zero times"));
+                    }
+                    (lengths, repeat)
+                }
+                None => (round, 1),
+            };
+            let (work, rest) = lengths
+                .split_once('/')
+                .ok_or_else(|| syntax("rounds are written as work/break"))?;
+            let (work, rest) = (parse_duration(work)?, parse_duration(rest)?);
+            for _ in 0..repeat {
+                steps.push(Step::new(Phase::Work, work));
+                steps.push(Step::new(Phase::ShortBreak, rest));
+            }
+        }
+        if let Some(duration) = long_break {
+            *steps.last_mut().expect("there is at least one round") =
+                Step::new(Phase::LongBreak, duration);
+        }
+        Self::new(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_duration;
+
+    fn minutes(m: u64) -> Duration {
+        Duration::from_secs(m * 60)
+    }
+
+    /// Writes `cycle` back as a spec, one round per work session.
+    fn spec(cycle: &Cycle) -> String {
+        let mut rounds = Vec::new();
+        let mut long_break = None;
+        for pair in cycle.steps().chunks(2) {
+            let [work, rest] = pair else {
+                panic!("{cycle:?} does not alternate work and breaks");
+            };
+            let rest = match rest.phase {
+                Phase::LongBreak => {
+                    long_break = Some(rest.duration);
+                    // Stands in for the break `then` replaces.
+                    Duration::from_secs(1)
+                }
+                _ => rest.duration,
+            };
+            rounds.push(format!(
+                "{}/{}",
+                format_duration(work.duration),
+                format_duration(rest)
+            ));
+        }
+        let mut spec = rounds.join(",");
+        if let Some(long_break) = long_break {
+            spec.push_str(" then ");
+            spec.push_str(&format_duration(long_break));
+        }
+        spec
+    }
+
+    #[test]
+    fn parses_the_classic_cycle() {
+        assert_eq!("25/5x4 then 15".parse(), Ok(Cycle::classic()));
+        assert_eq!("25/5 × 4 then 15m".parse(), Ok(Cycle::classic()));
+        assert_eq!("25m/5m*4then15".parse(), Ok(Cycle::classic()));
+    }
+
+    #[test]
+    fn parses_rounds_without_a_long_break() {
+        let cycle: Cycle = "15/3".parse().unwrap();
+        assert_eq!(
+            cycle.steps(),
+            [
+                Step::new(Phase::Work, minutes(15)),
+                Step::new(Phase::ShortBreak, minutes(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_mixed_rounds() {
+        let cycle: Cycle = "50/10x2, 25/5 then 30".parse().unwrap();
+        assert_eq!(
+            cycle.steps(),
+            [
+                Step::new(Phase::Work, minutes(50)),
+                Step::new(Phase::ShortBreak, minutes(10)),
+                Step::new(Phase::Work, minutes(50)),
+                Step::new(Phase::ShortBreak, minutes(10)),
+                Step::new(Phase::Work, minutes(25)),
+                Step::new(Phase::LongBreak, minutes(30)),
+            ]
+        );
+        assert_eq!(cycle.work_sessions(), 3);
+    }
+
+    #[test]
+    fn round_trips_through_a_spec() {
+        for input in [
+            "25/5x4 then 15",
+            "50/10x3 then 30",
+            "15/3",
+            "1h30m/20m, 45/5x2",
+            "90s/30s then 1h",
+        ] {
+            let cycle: Cycle = input.parse().unwrap();
+            let written = spec(&cycle);
+            assert_eq!(
+                written.parse(),
+                Ok(cycle),
+                "{input:?} written as {written:?}"
+            );
+        }
+        for cycle in [
+            Cycle::classic(),
+            Cycle::standard(minutes(50), minutes(10), minutes(30), 1).unwrap(),
+        ] {
+            assert_eq!(spec(&cycle).parse(), Ok(cycle));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        let syntax = |spec: &str, reason| {
+            Err(CycleError::Syntax {
+                spec: spec.to_owned(),
+                reason,
+            })
+        };
+        for (spec, reason) in [
+            ("", "rounds are written as work/break"),
+            ("25", "rounds are written as work/break"),
+            ("25/5,", "rounds are written as work/break"),
+            ("25/5x", "repeat counts must be whole numbers"),
+            ("25/5x-1", "repeat counts must be whole numbers"),
+            ("25/5x2.5", "repeat counts must be whole numbers"),
+            ("25/5x0", "a round cannot be repeated zero times"),
+        ] {
+            assert_eq!(spec.parse::<Cycle>(), syntax(spec, reason), "{spec:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        for spec in ["25/", "/5", "25/5 then", "25/5q", "abc/5", "25/5 then 15/3"] {
+            assert!(
+                matches!(spec.parse::<Cycle>(), Err(CycleError::Duration(_))),
+                "{spec:?} parsed as {:?}",
+                spec.parse::<Cycle>()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_zero_lengths() {
+        assert_eq!(
+            "25/0".parse::<Cycle>(),
+            Err(CycleError::ZeroLength {
+                position: 1,
+                phase: Phase::ShortBreak,
+            })
+        );
+        assert_eq!(
+            "25/5 then 0".parse::<Cycle>(),
+            Err(CycleError::ZeroLength {
+                position: 1,
+                phase: Phase::LongBreak,
+            })
+        );
+    }
+
+    #[test]
+    fn validates_built_cycles() {
+        assert_eq!(Cycle::new(Vec::new()), Err(CycleError::Empty));
+        assert_eq!(
+            Cycle::new(vec![Step::new(Phase::ShortBreak, minutes(5))]),
+            Err(CycleError::NoWork)
+        );
+        assert_eq!(
+            Cycle::standard(minutes(25), minutes(5), minutes(15), 0),
+            Err(CycleError::ZeroInterval)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid duration {input:?}: {reason}")]
+pub struct ParseDurationError {
+    input: String,
+    reason: &'static str,
+}
+
+/// Parses durations such as `25`, `90s`, `1h30m` or `1h 30m`.
+///
+/// A bare number counts minutes, since that is what pomodoro lengths are
+/// almost always given in. Otherwise every number needs one of the units
+/// `h`, `m` or `s`.
+pub fn parse_duration(input: &str) -> Result<Duration, ParseDurationError> {
+    let error = |reason| ParseDurationError {
+        input: input.to_owned(),
+        reason,
+    };
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(error("it is empty"));
+    }
+    if let Ok(minutes) = trimmed.parse::<u64>() {
+        return Ok(Duration::from_secs(minutes.saturating_mul(60)));
+    }
+
+    let mut total = 0u64;
+    let mut rest = trimmed;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(error("expected a number"));
+        }
+        let value: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| error("number is too large"))?;
+        rest = &rest[digits..];
+        let unit = match rest.chars().next() {
+            Some('h') => 3600,
+            Some('m') => 60,
+            Some('s') => 1,
+            Some(_) => return Err(error("units must be h, m or s")),
+            None => return Err(error("a number is missing its unit")),
+        };
+        total = total.saturating_add(value.saturating_mul(unit));
+        rest = rest[1..].trim_start();
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Formats a duration the way [`parse_duration`] reads it, in whole seconds.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return "0s".to_owned();
+    }
+    let mut out = String::new();
+    for (value, unit) in [(secs / 3600, 'h'), (secs / 60 % 60, 'm'), (secs % 60, 's')] {
+        if value != 0 {
+            let _ = write!(out, "{value}{unit}");
+        }
+    }
+    out
+}
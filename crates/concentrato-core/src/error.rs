@@ -0,0 +1,36 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{Phase, Status};
+
+/// A transition that can be refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    Complete,
+    Prolong,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Start => "start",
+            Action::Pause => "pause",
+            Action::Resume => "resume",
+            Action::Stop => "stop",
+            Action::Complete => "complete",
+            Action::Prolong => "prolong",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TransitionError {
+    #[error("cannot {action} while the timer is {status}")]
+    InvalidState { action: Action, status: Status },
+    #[error("cannot complete {phase} with {}s still remaining", remaining.as_secs())]
+    NotElapsed { phase: Phase, remaining: Duration },
+}
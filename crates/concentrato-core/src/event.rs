@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use crate::Phase;
+
+/// What a successful transition did to the timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Started {
+        phase: Phase,
+    },
+    Paused {
+        phase: Phase,
+    },
+    Resumed {
+        phase: Phase,
+    },
+    /// `phase` was made `by` longer.
+    Extended {
+        phase: Phase,
+        by: Duration,
+    },
+    /// `phase` was abandoned and is idle again with its full length ahead.
+    Stopped {
+        phase: Phase,
+    },
+    /// `phase` ran for its full length and the timer moved on to `next`.
+    Completed {
+        phase: Phase,
+        next: Phase,
+    },
+    /// `phase` was cut short and the timer moved on to `next`.
+    Skipped {
+        phase: Phase,
+        next: Phase,
+    },
+    /// The system was suspended while `phase` ran and the timer's
+    /// [`SuspendPolicy::Discard`](crate::SuspendPolicy::Discard) threw the
+    /// phase away; it is idle again with its full length ahead of it.
+    Discarded {
+        phase: Phase,
+    },
+    /// The cycle went back to its first work session, abandoning `phase`.
+    Reset {
+        phase: Phase,
+    },
+}
+
+impl Event {
+    /// The phase the event happened in.
+    pub fn phase(&self) -> Phase {
+        match *self {
+            Event::Started { phase }
+            | Event::Paused { phase }
+            | Event::Resumed { phase }
+            | Event::Extended { phase, .. }
+            | Event::Stopped { phase }
+            | Event::Completed { phase, .. }
+            | Event::Skipped { phase, .. }
+            | Event::Discarded { phase }
+            | Event::Reset { phase } => phase,
+        }
+    }
+
+    /// The phase the timer moved on to, for events that end a phase.
+    pub fn next(&self) -> Option<Phase> {
+        match *self {
+            Event::Completed { next, .. } | Event::Skipped { next, .. } => Some(next),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! The pomodoro engine behind concentrato.
+//!
+//! [`Timer`] is an explicit state machine over the [`Phase`]s of a
+//! [`Cycle`]. Every transition either returns the [`Event`] it caused or a
+//! [`TransitionError`] explaining why it is not allowed right now, so the
+//! frontends built on top of it never have to guess what the timer did.
+//!
+//! The timer never sleeps or reads the system time itself: it asks a
+//! [`Clock`], so tests can drive a whole cycle with a [`FakeClock`]. The clock
+//! also reports system suspends, which the timer resolves according to its
+//! [`SuspendPolicy`].
+
+mod clock;
+mod cycle;
+mod duration;
+mod error;
+mod event;
+mod phase;
+mod snapshot;
+mod suspend;
+mod timer;
+
+pub use clock::{Clock, FakeClock, MonotonicClock};
+pub use cycle::{Cycle, CycleError, Step};
+pub use duration::{ParseDurationError, format_duration, parse_duration};
+pub use error::{Action, TransitionError};
+pub use event::Event;
+pub use phase::{ParsePhaseError, Phase};
+pub use snapshot::Snapshot;
+pub use suspend::{ParseSuspendPolicyError, SuspendPolicy};
+pub use timer::{Status, Timer};
@@ -0,0 +1,62 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The kind of interval the timer is counting down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Work, Phase::ShortBreak, Phase::LongBreak];
+
+    /// The identifier used for this phase in files and on the wire.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Work => "work",
+            Phase::ShortBreak => "short_break",
+            Phase::LongBreak => "long_break",
+        }
+    }
+
+    /// A capitalised name suitable for showing to people.
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+
+    pub fn is_break(self) -> bool {
+        !matches!(self, Phase::Work)
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown phase {0:?}, expected one of work, short_break or long_break")]
+pub struct ParsePhaseError(String);
+
+impl FromStr for Phase {
+    type Err = ParsePhaseError;
+
+    /// Parses the [`as_str`](Phase::as_str) form, also accepting dashes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('-', "_").as_str() {
+            "work" => Ok(Phase::Work),
+            "short_break" => Ok(Phase::ShortBreak),
+            "long_break" => Ok(Phase::LongBreak),
+            _ => Err(ParsePhaseError(s.to_owned())),
+        }
+    }
+}
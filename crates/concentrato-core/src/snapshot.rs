@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use crate::{Phase, Status};
+
+/// A timer's progress, detached from its clock so that it can be stored and
+/// picked up by another timer, possibly in another process.
+///
+/// See [`Timer::snapshot`](crate::Timer::snapshot) and
+/// [`Timer::restore`](crate::Timer::restore).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub position: usize,
+    pub phase: Phase,
+    pub status: Status,
+    pub planned: Duration,
+    pub elapsed: Duration,
+    pub paused: Duration,
+    pub completed: u32,
+    /// Whether the phase before this one ran its course.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub previous_completed: bool,
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Suspend gaps shorter than this are treated as measurement noise.
+pub(crate) const SUSPEND_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// What to do with a running phase when the system was suspended under it.
+///
+/// A monotonic clock stops while the machine sleeps, so without a policy a
+/// 25 minute pomodoro interrupted by an hour-long suspend would simply carry
+/// on where it left off. Whatever the policy, waking up ends at most the one
+/// phase that was running: a long sleep never replays a backlog of phases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SuspendPolicy {
+    /// Count the time asleep towards the phase, as a wall clock would.
+    #[default]
+    Count,
+    /// Pause the phase, leaving the time asleep out of it.
+    Pause,
+    /// Abandon the interrupted phase, which starts over from idle.
+    Discard,
+}
+
+impl SuspendPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SuspendPolicy::Count => "count",
+            SuspendPolicy::Pause => "pause",
+            SuspendPolicy::Discard => "discard",
+        }
+    }
+}
+
+impl fmt::Display for SuspendPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown suspend policy {0:?}, expected one of count, pause or discard")]
+pub struct ParseSuspendPolicyError(String);
+
+impl FromStr for SuspendPolicy {
+    type Err = ParseSuspendPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "count" => Ok(SuspendPolicy::Count),
+            "pause" => Ok(SuspendPolicy::Pause),
+            "discard" => Ok(SuspendPolicy::Discard),
+            _ => Err(ParseSuspendPolicyError(s.to_owned())),
+        }
+    }
+}
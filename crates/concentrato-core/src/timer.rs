@@ -0,0 +1,848 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::suspend::SUSPEND_THRESHOLD;
+use crate::{
+    Action, Clock, Cycle, Event, MonotonicClock, Phase, Snapshot, SuspendPolicy, TransitionError,
+};
+
+/// Whether the clock of the current phase is ticking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Status {
+    /// The phase has not been started yet.
+    Idle,
+    Running,
+    Paused,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Idle => "idle",
+            Status::Running => "running",
+            Status::Paused => "paused",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Idle,
+    /// `since` is a [`Clock::now`] reading, as is the one below.
+    Running {
+        since: Duration,
+    },
+    Paused {
+        since: Duration,
+    },
+}
+
+/// A pomodoro timer going round a [`Cycle`] of work sessions and breaks.
+///
+/// Each phase starts out [`Idle`](Status::Idle) and only begins counting down
+/// once [`start`](Timer::start)ed. When a phase ends, by running out or by
+/// being skipped, the timer moves to the next phase of the cycle and waits to
+/// be started again.
+///
+/// All time is read from a [`Clock`], which tests can replace with a
+/// [`FakeClock`](crate::FakeClock). Suspends reported by the clock are
+/// resolved by the timer's [`SuspendPolicy`] on the next [`tick`](Timer::tick).
+#[derive(Debug, Clone)]
+pub struct Timer<C = MonotonicClock> {
+    clock: C,
+    cycle: Cycle,
+    /// Index into the cycle, counting work sessions and breaks alike.
+    position: usize,
+    phase: Phase,
+    state: State,
+    planned: Duration,
+    /// Time spent running in this phase, up to the last pause.
+    elapsed: Duration,
+    /// Time spent paused in this phase, up to the last resume.
+    paused: Duration,
+    /// Work sessions completed since the last reset.
+    completed: u32,
+    /// Whether the phase before the current one ran its course, so that
+    /// prolonging it takes the completion back.
+    previous_completed: bool,
+    suspend_policy: SuspendPolicy,
+    /// The clock's [`Clock::suspended`] reading when suspends were last
+    /// looked for.
+    asleep: Duration,
+}
+
+impl Default for Timer {
+    /// An idle timer at the start of the [classic](Cycle::classic) cycle.
+    fn default() -> Self {
+        Self::new(Cycle::classic())
+    }
+}
+
+impl Timer {
+    /// Creates an idle timer at the first step of `cycle`, measuring time
+    /// with the system's monotonic clock.
+    pub fn new(cycle: Cycle) -> Self {
+        Self::with_clock(cycle, MonotonicClock::new())
+    }
+}
+
+impl<C: Clock> Timer<C> {
+    /// Like [`Timer::new`], but reading time from `clock`.
+    pub fn with_clock(cycle: Cycle, clock: C) -> Self {
+        let first = cycle.step(0);
+        let asleep = clock.suspended();
+        Self {
+            clock,
+            cycle,
+            position: 0,
+            phase: first.phase,
+            state: State::Idle,
+            planned: first.duration,
+            elapsed: Duration::ZERO,
+            paused: Duration::ZERO,
+            completed: 0,
+            previous_completed: false,
+            suspend_policy: SuspendPolicy::default(),
+            asleep,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn suspend_policy(&self) -> SuspendPolicy {
+        self.suspend_policy
+    }
+
+    pub fn set_suspend_policy(&mut self, policy: SuspendPolicy) {
+        self.suspend_policy = policy;
+    }
+
+    pub fn cycle(&self) -> &Cycle {
+        &self.cycle
+    }
+
+    /// Switches to `cycle` from the next phase on.
+    ///
+    /// A phase that has started keeps its length; the timer carries on from
+    /// the same position in the new cycle, wrapping around if it is shorter.
+    /// An idle phase has not started yet, so it is replaced straight away.
+    pub fn set_cycle(&mut self, cycle: Cycle) {
+        if cycle == self.cycle {
+            return;
+        }
+        self.cycle = cycle;
+        self.position %= self.cycle.len();
+        if let State::Idle = self.state {
+            self.enter();
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn status(&self) -> Status {
+        match self.state {
+            State::Idle => Status::Idle,
+            State::Running { .. } => Status::Running,
+            State::Paused { .. } => Status::Paused,
+        }
+    }
+
+    /// Index of the current phase within the cycle.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Number of work sessions completed since the timer was created or reset.
+    pub fn completed(&self) -> u32 {
+        self.completed
+    }
+
+    /// How long the current phase is meant to last.
+    pub fn planned(&self) -> Duration {
+        self.planned
+    }
+
+    /// How long the current phase has been running, excluding pauses.
+    pub fn elapsed(&self) -> Duration {
+        match self.state {
+            State::Running { since } => {
+                let asleep = match self.suspend_policy {
+                    SuspendPolicy::Count => self.unseen_suspend(),
+                    SuspendPolicy::Pause | SuspendPolicy::Discard => Duration::ZERO,
+                };
+                self.elapsed + self.since(since) + asleep
+            }
+            State::Idle | State::Paused { .. } => self.elapsed,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.planned.saturating_sub(self.elapsed())
+    }
+
+    /// How long the current phase has spent paused.
+    pub fn paused_for(&self) -> Duration {
+        match self.state {
+            State::Paused { since } => self.paused + self.since(since),
+            State::Idle | State::Running { .. } => self.paused,
+        }
+    }
+
+    /// Starts counting down an idle phase.
+    pub fn start(&mut self) -> Result<Event, TransitionError> {
+        self.catch_up();
+        let State::Idle = self.state else {
+            return Err(self.refuse(Action::Start));
+        };
+        self.state = State::Running {
+            since: self.clock.now(),
+        };
+        Ok(Event::Started { phase: self.phase })
+    }
+
+    pub fn pause(&mut self) -> Result<Event, TransitionError> {
+        self.catch_up();
+        let State::Running { since } = self.state else {
+            return Err(self.refuse(Action::Pause));
+        };
+        self.elapsed += self.since(since);
+        self.state = State::Paused {
+            since: self.clock.now(),
+        };
+        Ok(Event::Paused { phase: self.phase })
+    }
+
+    pub fn resume(&mut self) -> Result<Event, TransitionError> {
+        self.catch_up();
+        let State::Paused { since } = self.state else {
+            return Err(self.refuse(Action::Resume));
+        };
+        self.paused += self.since(since);
+        self.state = State::Running {
+            since: self.clock.now(),
+        };
+        Ok(Event::Resumed { phase: self.phase })
+    }
+
+    /// Makes the current phase `by` longer, whatever its status.
+    pub fn extend(&mut self, by: Duration) -> Event {
+        self.catch_up();
+        self.planned = self.planned.saturating_add(by);
+        Event::Extended {
+            phase: self.phase,
+            by,
+        }
+    }
+
+    /// Abandons a started phase, which goes back to idle with its full
+    /// length ahead of it.
+    pub fn stop(&mut self) -> Result<Event, TransitionError> {
+        self.catch_up();
+        if let State::Idle = self.state {
+            return Err(self.refuse(Action::Stop));
+        }
+        self.enter();
+        Ok(Event::Stopped { phase: self.phase })
+    }
+
+    /// Ends the current phase early, whatever its status, and moves on to
+    /// the next one.
+    pub fn skip(&mut self) -> Event {
+        self.catch_up();
+        let phase = self.phase;
+        self.advance();
+        self.previous_completed = false;
+        Event::Skipped {
+            phase,
+            next: self.phase,
+        }
+    }
+
+    /// Goes back to the idle first step of the cycle, forgetting completed
+    /// sessions.
+    pub fn reset(&mut self) -> Event {
+        self.catch_up();
+        let phase = self.phase;
+        self.position = 0;
+        self.completed = 0;
+        self.previous_completed = false;
+        self.enter();
+        Event::Reset { phase }
+    }
+
+    /// Goes back to the phase before the idle current one and starts it
+    /// again for `by`, for when a phase ended before its work did.
+    ///
+    /// A completed work session taken back this way no longer counts as
+    /// completed, until the extra time completes it again; one that was
+    /// skipped was never counted.
+    pub fn prolong(&mut self, by: Duration) -> Result<Event, TransitionError> {
+        self.catch_up();
+        let State::Idle = self.state else {
+            return Err(self.refuse(Action::Prolong));
+        };
+        self.position = (self.position + self.cycle.len() - 1) % self.cycle.len();
+        self.enter();
+        if self.phase == Phase::Work && self.previous_completed {
+            self.completed = self.completed.saturating_sub(1);
+        }
+        // What became of the phase before this one is not known.
+        self.previous_completed = false;
+        self.planned = by;
+        self.start()
+    }
+
+    /// Ends a started phase whose time has run out.
+    pub fn complete(&mut self) -> Result<Event, TransitionError> {
+        self.catch_up();
+        if let State::Idle = self.state {
+            return Err(self.refuse(Action::Complete));
+        }
+        let remaining = self.remaining();
+        if !remaining.is_zero() {
+            return Err(TransitionError::NotElapsed {
+                phase: self.phase,
+                remaining,
+            });
+        }
+        let phase = self.phase;
+        if phase == Phase::Work {
+            self.completed += 1;
+        }
+        self.advance();
+        self.previous_completed = true;
+        Ok(Event::Completed {
+            phase,
+            next: self.phase,
+        })
+    }
+
+    /// Completes the current phase if it is running and its time is up, after
+    /// applying the suspend policy to any suspend since the last call.
+    ///
+    /// Frontends call this periodically; it is the only way transitions
+    /// happen without someone asking for them. Other transitions apply the
+    /// suspend policy too, but only report their own event, so a timer that
+    /// is not ticked can pause or discard a phase silently.
+    pub fn tick(&mut self) -> Option<Event> {
+        if let Some(event) = self.catch_up() {
+            return Some(event);
+        }
+        match self.state {
+            State::Running { .. } if self.remaining().is_zero() => self.complete().ok(),
+            _ => None,
+        }
+    }
+
+    /// Captures the timer's progress.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            position: self.position,
+            phase: self.phase,
+            status: self.status(),
+            planned: self.planned,
+            elapsed: self.elapsed(),
+            paused: self.paused_for(),
+            completed: self.completed,
+            previous_completed: self.previous_completed,
+        }
+    }
+
+    /// Picks up the progress captured in `snapshot`, `downtime` after it was
+    /// taken.
+    ///
+    /// A phase that was running through the downtime is treated as if the
+    /// system had been suspended for that long, so the suspend policy decides
+    /// what becomes of it; the event it caused, if any, is returned. A paused
+    /// phase counts the downtime as time paused. The snapshot's phase and
+    /// length are kept even if the timer's cycle has changed since.
+    pub fn restore(&mut self, snapshot: Snapshot, downtime: Duration) -> Option<Event> {
+        self.catch_up();
+        let now = self.clock.now();
+        self.position = snapshot.position % self.cycle.len();
+        self.phase = snapshot.phase;
+        self.planned = snapshot.planned;
+        self.elapsed = snapshot.elapsed;
+        self.paused = snapshot.paused;
+        self.completed = snapshot.completed;
+        self.previous_completed = snapshot.previous_completed;
+        match snapshot.status {
+            Status::Idle => {
+                self.state = State::Idle;
+                None
+            }
+            Status::Paused => {
+                self.paused += downtime;
+                self.state = State::Paused { since: now };
+                None
+            }
+            Status::Running => {
+                self.state = State::Running { since: now };
+                if downtime.is_zero() {
+                    None
+                } else {
+                    self.resolve_suspend(downtime)
+                }
+            }
+        }
+    }
+
+    /// Resolves a suspend the timer has not yet accounted for.
+    fn catch_up(&mut self) -> Option<Event> {
+        let asleep = self.unseen_suspend();
+        if asleep.is_zero() {
+            return None;
+        }
+        self.asleep += asleep;
+        self.resolve_suspend(asleep)
+    }
+
+    /// Applies the suspend policy to the current phase, which was interrupted
+    /// for `asleep`.
+    fn resolve_suspend(&mut self, asleep: Duration) -> Option<Event> {
+        let State::Running { since } = self.state else {
+            return None;
+        };
+        let phase = self.phase;
+        match self.suspend_policy {
+            SuspendPolicy::Count => {
+                self.elapsed += asleep;
+                None
+            }
+            SuspendPolicy::Pause => {
+                // The monotonic clock stood still through the interruption,
+                // so pausing now is as good as having paused when it began.
+                self.elapsed += self.since(since);
+                self.state = State::Paused {
+                    since: self.clock.now(),
+                };
+                Some(Event::Paused { phase })
+            }
+            SuspendPolicy::Discard => {
+                self.enter();
+                Some(Event::Discarded { phase })
+            }
+        }
+    }
+
+    /// Time suspended since the timer last looked, ignoring noise.
+    fn unseen_suspend(&self) -> Duration {
+        let asleep = self.clock.suspended().saturating_sub(self.asleep);
+        if asleep < SUSPEND_THRESHOLD {
+            Duration::ZERO
+        } else {
+            asleep
+        }
+    }
+
+    fn advance(&mut self) {
+        self.position = (self.position + 1) % self.cycle.len();
+        self.enter();
+    }
+
+    /// Makes the step at the current position the idle current phase.
+    fn enter(&mut self) {
+        let step = self.cycle.step(self.position);
+        self.phase = step.phase;
+        self.state = State::Idle;
+        self.planned = step.duration;
+        self.elapsed = Duration::ZERO;
+        self.paused = Duration::ZERO;
+    }
+
+    /// Time since the clock read `reading`.
+    fn since(&self, reading: Duration) -> Duration {
+        self.clock.now().saturating_sub(reading)
+    }
+
+    fn refuse(&self, action: Action) -> TransitionError {
+        TransitionError::InvalidState {
+            action,
+            status: self.status(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeClock;
+
+    fn minutes(m: u64) -> Duration {
+        Duration::from_secs(m * 60)
+    }
+
+    fn timer() -> (Timer<FakeClock>, FakeClock) {
+        let clock = FakeClock::new();
+        (Timer::with_clock(Cycle::classic(), clock.clone()), clock)
+    }
+
+    fn running(policy: SuspendPolicy) -> (Timer<FakeClock>, FakeClock) {
+        let (mut timer, clock) = timer();
+        timer.set_suspend_policy(policy);
+        timer.start().unwrap();
+        clock.advance(minutes(10));
+        (timer, clock)
+    }
+
+    #[test]
+    fn starts_pauses_and_resumes() {
+        let (mut timer, clock) = timer();
+        assert_eq!(timer.status(), Status::Idle);
+        assert_eq!(timer.start(), Ok(Event::Started { phase: Phase::Work }));
+        clock.advance(minutes(10));
+        assert_eq!(timer.pause(), Ok(Event::Paused { phase: Phase::Work }));
+        clock.advance(minutes(3));
+        assert_eq!(timer.elapsed(), minutes(10));
+        assert_eq!(timer.paused_for(), minutes(3));
+        assert_eq!(timer.resume(), Ok(Event::Resumed { phase: Phase::Work }));
+        clock.advance(minutes(5));
+        assert_eq!(timer.status(), Status::Running);
+        assert_eq!(timer.remaining(), minutes(10));
+        assert_eq!(timer.paused_for(), minutes(3));
+    }
+
+    #[test]
+    fn refuses_transitions_from_the_wrong_status() {
+        let (mut timer, _) = timer();
+        let refused = |action, status| Err(TransitionError::InvalidState { action, status });
+        assert_eq!(timer.pause(), refused(Action::Pause, Status::Idle));
+        assert_eq!(timer.resume(), refused(Action::Resume, Status::Idle));
+        assert_eq!(timer.stop(), refused(Action::Stop, Status::Idle));
+        timer.start().unwrap();
+        assert_eq!(timer.start(), refused(Action::Start, Status::Running));
+        assert_eq!(timer.resume(), refused(Action::Resume, Status::Running));
+        assert_eq!(
+            timer.prolong(minutes(5)),
+            refused(Action::Prolong, Status::Running)
+        );
+        assert_eq!(
+            timer.complete(),
+            Err(TransitionError::NotElapsed {
+                phase: Phase::Work,
+                remaining: minutes(25),
+            })
+        );
+    }
+
+    #[test]
+    fn completes_when_the_time_is_up() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(25) - Duration::from_secs(1));
+        assert_eq!(timer.tick(), None);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            timer.tick(),
+            Some(Event::Completed {
+                phase: Phase::Work,
+                next: Phase::ShortBreak,
+            })
+        );
+        assert_eq!(timer.status(), Status::Idle);
+        assert_eq!(timer.completed(), 1);
+        assert_eq!(timer.planned(), minutes(5));
+        assert_eq!(timer.tick(), None);
+    }
+
+    #[test]
+    fn skips_without_completing() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(10));
+        assert_eq!(
+            timer.skip(),
+            Event::Skipped {
+                phase: Phase::Work,
+                next: Phase::ShortBreak,
+            }
+        );
+        assert_eq!(timer.position(), 1);
+        assert_eq!(timer.completed(), 0);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn stops_back_to_idle() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(10));
+        assert_eq!(timer.stop(), Ok(Event::Stopped { phase: Phase::Work }));
+        assert_eq!(timer.status(), Status::Idle);
+        assert_eq!(timer.position(), 0);
+        assert_eq!(timer.remaining(), minutes(25));
+    }
+
+    #[test]
+    fn resets_to_the_first_step() {
+        let (mut timer, clock) = timer();
+        for _ in 0..3 {
+            timer.start().unwrap();
+            clock.advance(timer.planned());
+            timer.tick();
+        }
+        assert_eq!(timer.completed(), 2);
+        assert_eq!(
+            timer.reset(),
+            Event::Reset {
+                phase: Phase::ShortBreak
+            }
+        );
+        assert_eq!(timer.position(), 0);
+        assert_eq!(timer.completed(), 0);
+        assert_eq!(timer.status(), Status::Idle);
+    }
+
+    #[test]
+    fn places_the_long_break_after_the_last_work_session() {
+        let (mut timer, clock) = timer();
+        let mut phases = Vec::new();
+        for _ in 0..10 {
+            phases.push(timer.phase());
+            timer.start().unwrap();
+            clock.advance(timer.planned());
+            timer.tick();
+        }
+        use Phase::{LongBreak, ShortBreak, Work};
+        assert_eq!(
+            phases,
+            [
+                Work, ShortBreak, Work, ShortBreak, Work, ShortBreak, Work, LongBreak, Work,
+                ShortBreak,
+            ]
+        );
+        assert_eq!(timer.completed(), 5);
+    }
+
+    #[test]
+    fn extends_the_current_phase() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(24));
+        timer.extend(minutes(5));
+        clock.advance(minutes(5));
+        assert_eq!(timer.tick(), None);
+        assert_eq!(timer.remaining(), minutes(1));
+    }
+
+    #[test]
+    fn prolongs_the_previous_work_session() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(25));
+        timer.tick();
+        assert_eq!(timer.completed(), 1);
+        assert_eq!(
+            timer.prolong(minutes(5)),
+            Ok(Event::Started { phase: Phase::Work })
+        );
+        assert_eq!(timer.position(), 0);
+        assert_eq!(timer.completed(), 0);
+        assert_eq!(timer.planned(), minutes(5));
+        clock.advance(minutes(5));
+        timer.tick();
+        assert_eq!(timer.completed(), 1);
+        assert_eq!(timer.phase(), Phase::ShortBreak);
+    }
+
+    #[test]
+    fn prolongs_a_skipped_work_session_without_taking_back_a_completed_one() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(25));
+        timer.tick();
+        timer.skip();
+        timer.start().unwrap();
+        clock.advance(minutes(10));
+        timer.skip();
+        assert_eq!(timer.phase(), Phase::ShortBreak);
+        assert_eq!(timer.completed(), 1);
+
+        timer.prolong(minutes(5)).unwrap();
+        assert_eq!((timer.position(), timer.phase()), (2, Phase::Work));
+        assert_eq!(timer.completed(), 1);
+        clock.advance(minutes(5));
+        timer.tick();
+        assert_eq!(timer.completed(), 2);
+
+        // Taken back once it is prolonged, and not again once skipped.
+        timer.prolong(minutes(5)).unwrap();
+        assert_eq!(timer.completed(), 1);
+        timer.stop().unwrap();
+        timer.skip();
+        timer.prolong(minutes(5)).unwrap();
+        assert_eq!(timer.completed(), 1);
+    }
+
+    #[test]
+    fn keeps_what_became_of_the_previous_phase_in_a_snapshot() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(25));
+        timer.tick();
+        let snapshot = timer.snapshot();
+        assert!(snapshot.previous_completed);
+
+        let (mut restored, _) = self::timer();
+        restored.restore(snapshot, Duration::ZERO);
+        restored.prolong(minutes(5)).unwrap();
+        assert_eq!(restored.completed(), 0);
+        assert!(!restored.snapshot().previous_completed);
+    }
+
+    #[test]
+    fn prolongs_the_last_step_from_the_first() {
+        let (mut timer, _) = timer();
+        timer.prolong(minutes(5)).unwrap();
+        assert_eq!(timer.position(), 7);
+        assert_eq!(timer.phase(), Phase::LongBreak);
+        assert_eq!(timer.completed(), 0);
+    }
+
+    #[test]
+    fn counts_a_suspend() {
+        let (mut timer, clock) = running(SuspendPolicy::Count);
+        clock.suspend(minutes(5));
+        assert_eq!(timer.elapsed(), minutes(15));
+        assert_eq!(timer.tick(), None);
+        assert_eq!(timer.elapsed(), minutes(15));
+        clock.suspend(minutes(30));
+        assert_eq!(
+            timer.tick(),
+            Some(Event::Completed {
+                phase: Phase::Work,
+                next: Phase::ShortBreak,
+            })
+        );
+        assert_eq!(timer.position(), 1, "a long suspend ends a single phase");
+    }
+
+    #[test]
+    fn pauses_on_a_suspend() {
+        let (mut timer, clock) = running(SuspendPolicy::Pause);
+        clock.suspend(minutes(60));
+        assert_eq!(timer.elapsed(), minutes(10));
+        assert_eq!(timer.tick(), Some(Event::Paused { phase: Phase::Work }));
+        assert_eq!(timer.status(), Status::Paused);
+        assert_eq!(timer.elapsed(), minutes(10));
+    }
+
+    #[test]
+    fn discards_on_a_suspend() {
+        let (mut timer, clock) = running(SuspendPolicy::Discard);
+        clock.suspend(minutes(60));
+        assert_eq!(timer.tick(), Some(Event::Discarded { phase: Phase::Work }));
+        assert_eq!(timer.status(), Status::Idle);
+        assert_eq!(timer.position(), 0);
+        assert_eq!(timer.remaining(), minutes(25));
+    }
+
+    #[test]
+    fn resolves_a_suspend_before_other_transitions() {
+        let (mut timer, clock) = running(SuspendPolicy::Discard);
+        clock.suspend(minutes(60));
+        assert_eq!(timer.start(), Ok(Event::Started { phase: Phase::Work }));
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn ignores_suspends_below_the_threshold() {
+        let (mut timer, clock) = running(SuspendPolicy::Discard);
+        clock.suspend(Duration::ZERO);
+        assert_eq!(timer.tick(), None);
+        clock.suspend(SUSPEND_THRESHOLD / 2);
+        assert_eq!(timer.tick(), None);
+        assert_eq!(timer.status(), Status::Running);
+    }
+
+    #[test]
+    fn sees_no_suspend_on_the_system_clock_while_awake() {
+        let mut timer = Timer::with_clock(Cycle::classic(), MonotonicClock::new());
+        timer.set_suspend_policy(SuspendPolicy::Discard);
+        timer.start().unwrap();
+        for _ in 0..10_000 {
+            assert_eq!(timer.tick(), None);
+        }
+        assert_eq!(timer.status(), Status::Running);
+    }
+
+    #[test]
+    fn ignores_suspends_while_paused() {
+        let (mut timer, clock) = running(SuspendPolicy::Discard);
+        timer.pause().unwrap();
+        clock.suspend(minutes(60));
+        assert_eq!(timer.tick(), None);
+        assert_eq!(timer.status(), Status::Paused);
+        assert_eq!(timer.elapsed(), minutes(10));
+    }
+
+    #[test]
+    fn round_trips_through_a_snapshot() {
+        let (mut timer, clock) = timer();
+        timer.start().unwrap();
+        clock.advance(minutes(25));
+        timer.tick();
+        timer.start().unwrap();
+        clock.advance(minutes(2));
+        let snapshot = timer.snapshot();
+
+        let (mut restored, _) = self::timer();
+        assert_eq!(restored.restore(snapshot, Duration::ZERO), None);
+        assert_eq!(restored.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn restores_after_downtime() {
+        let (mut timer, _) = running(SuspendPolicy::Count);
+        let snapshot = timer.snapshot();
+        let downtime = minutes(5);
+
+        let (mut counting, clock) = self::timer();
+        assert_eq!(counting.restore(snapshot, downtime), None);
+        assert_eq!(counting.status(), Status::Running);
+        assert_eq!(counting.elapsed(), minutes(15));
+        clock.advance(minutes(10));
+        assert!(counting.tick().is_some());
+
+        let (mut pausing, _) = self::timer();
+        pausing.set_suspend_policy(SuspendPolicy::Pause);
+        assert_eq!(
+            pausing.restore(snapshot, downtime),
+            Some(Event::Paused { phase: Phase::Work })
+        );
+        assert_eq!(pausing.elapsed(), minutes(10));
+
+        let (mut discarding, _) = self::timer();
+        discarding.set_suspend_policy(SuspendPolicy::Discard);
+        assert_eq!(
+            discarding.restore(snapshot, downtime),
+            Some(Event::Discarded { phase: Phase::Work })
+        );
+        assert_eq!(discarding.elapsed(), Duration::ZERO);
+
+        timer.pause().unwrap();
+        let (mut paused, _) = self::timer();
+        assert_eq!(paused.restore(timer.snapshot(), downtime), None);
+        assert_eq!(paused.status(), Status::Paused);
+        assert_eq!(paused.paused_for(), downtime);
+        assert_eq!(paused.elapsed(), minutes(10));
+    }
+
+    #[test]
+    fn keeps_a_started_phase_when_the_cycle_changes() {
+        let (mut timer, _) = timer();
+        timer.start().unwrap();
+        timer.set_cycle("50/10".parse().unwrap());
+        assert_eq!(timer.planned(), minutes(25));
+        timer.skip();
+        assert_eq!(timer.planned(), minutes(10));
+        timer.set_cycle("15/3".parse().unwrap());
+        assert_eq!(timer.planned(), minutes(3));
+    }
+}
@@ -0,0 +1,401 @@
+//! Output for the status bars of tiling window managers.
+//!
+//! Each [`Bar`] gets the timer in the shape it reads: JSON objects for
+//! waybar and i3status-rust, the i3bar protocol, blocks for i3blocks and text
+//! with action tags for polybar. All of them carry the phase and state as a
+//! class or color, so the module can be styled, and the details as a tooltip
+//! where the bar shows one.
+//!
+//! Clicking the module toggles the timer and scrolling up extends it. Bars
+//! that report clicks to the module itself, i3bar and i3blocks, send
+//! [`ClickEvent`]s that [`Module::click`] turns into requests; the others run
+//! a command, which polybar's action tags carry and the other bars take from
+//! their own config.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use concentrato_core::{Phase, Status as State};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::display;
+use crate::protocol::{Request, Status};
+use crate::template::Template;
+
+/// The status bars concentrato can feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Waybar,
+    I3bar,
+    Polybar,
+    I3blocks,
+    I3statusRust,
+}
+
+impl Bar {
+    pub const ALL: [Bar; 5] = [
+        Bar::Waybar,
+        Bar::I3bar,
+        Bar::Polybar,
+        Bar::I3blocks,
+        Bar::I3statusRust,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Bar::Waybar => "waybar",
+            Bar::I3bar => "i3bar",
+            Bar::Polybar => "polybar",
+            Bar::I3blocks => "i3blocks",
+            Bar::I3statusRust => "i3status-rust",
+        }
+    }
+
+    /// Whether the bar tells the module about clicks on its standard input,
+    /// rather than running a command for them.
+    pub fn reports_clicks(self) -> bool {
+        matches!(self, Bar::I3bar | Bar::I3blocks)
+    }
+}
+
+impl fmt::Display for Bar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown status bar {0:?}; expected waybar, i3bar, polybar, i3blocks or i3status-rust")]
+pub struct ParseBarError(String);
+
+impl FromStr for Bar {
+    type Err = ParseBarError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Bar::ALL
+            .into_iter()
+            .find(|bar| bar.as_str() == s)
+            .ok_or_else(|| ParseBarError(s.to_owned()))
+    }
+}
+
+/// A click on the module, as i3bar and i3blocks report it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct ClickEvent {
+    /// 1 for the left button, 4 and 5 for scrolling up and down.
+    pub button: u32,
+}
+
+impl ClickEvent {
+    /// Reads one click from a line i3bar or i3blocks wrote, skipping the
+    /// brackets and commas that frame i3bar's stream of them.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim().trim_start_matches(['[', ',']).trim_start();
+        if line.is_empty() {
+            return None;
+        }
+        serde_json::from_str(line).ok()
+    }
+}
+
+/// The concentrato module of one bar.
+#[derive(Debug, Clone)]
+pub struct Module {
+    bar: Bar,
+    template: Template,
+    /// How much scrolling up extends the phase by.
+    step: Duration,
+    /// The command line polybar's action tags run, up to the subcommand.
+    command: String,
+    /// Whether an i3bar status line has been written yet, since the ones
+    /// after it need a leading comma.
+    started: bool,
+}
+
+impl Module {
+    /// The text bars usually show.
+    pub const DEFAULT_TEMPLATE: &str = "{label} {remaining}";
+
+    /// `command` is how polybar should run concentrato, as in
+    /// `/usr/bin/concentrato --socket /tmp/sock`, already quoted for the
+    /// shell.
+    pub fn new(bar: Bar, template: Template, step: Duration, command: String) -> Self {
+        Self {
+            bar,
+            template,
+            step,
+            command,
+            started: false,
+        }
+    }
+
+    /// What the bar expects before the first status, if anything.
+    pub fn header(&self) -> Option<String> {
+        match self.bar {
+            Bar::I3bar => Some(format!(
+                "{}\n[",
+                json!({"version": 1, "click_events": true})
+            )),
+            _ => None,
+        }
+    }
+
+    /// The line that shows `status` on the bar.
+    pub fn render(&mut self, status: &Status) -> String {
+        let text = self.template.render(status);
+        let tooltip = display::details(status);
+        let class = [status.phase.to_string(), status.state.to_string()];
+        match self.bar {
+            Bar::Waybar => json!({
+                "text": text,
+                "alt": status.phase.as_str(),
+                "tooltip": tooltip,
+                "class": class,
+                "percentage": status.percent(),
+            })
+            .to_string(),
+            Bar::I3bar => {
+                // i3bar ignores keys starting with an underscore, which
+                // leaves room for the class and tooltip other tools look for.
+                let block = json!([{
+                    "name": "concentrato",
+                    "instance": status.phase.as_str(),
+                    "full_text": text,
+                    "short_text": display::clock(status.remaining()),
+                    "color": color(status),
+                    "_class": class,
+                    "_tooltip": tooltip,
+                }]);
+                let comma = if self.started { "," } else { "" };
+                self.started = true;
+                format!("{comma}{block}")
+            }
+            Bar::I3blocks => json!({
+                "full_text": text,
+                "short_text": display::clock(status.remaining()),
+                "color": color(status),
+            })
+            .to_string(),
+            Bar::I3statusRust => json!({
+                "icon": "pomodoro",
+                "state": match (status.state, status.phase.is_break()) {
+                    (State::Idle, _) => "Idle",
+                    (State::Paused, _) => "Warning",
+                    (State::Running, false) => "Info",
+                    (State::Running, true) => "Good",
+                },
+                "text": text,
+                "short_text": display::clock(status.remaining()),
+            })
+            .to_string(),
+            Bar::Polybar => format!(
+                "%{{A1:{}:}}%{{A4:{}:}}{}%{{A}}%{{A}}",
+                polybar_escape(&format!("{} toggle", self.command)),
+                polybar_escape(&format!("{} extend {}s", self.command, self.step.as_secs())),
+                // Polybar would read a literal % as the start of a tag.
+                text.replace('%', "%%"),
+            ),
+        }
+    }
+
+    /// The request a click on the module stands for, if any.
+    pub fn click(&self, event: ClickEvent) -> Option<Request> {
+        match event.button {
+            1 => Some(Request::Toggle),
+            4 => Some(Request::extend(self.step)),
+            _ => None,
+        }
+    }
+}
+
+/// Escapes the colons polybar would take for the end of an action's command.
+fn polybar_escape(command: &str) -> String {
+    command.replace(':', "\\:")
+}
+
+/// The text color i3bar and i3blocks show the module in.
+fn color(status: &Status) -> &'static str {
+    match (status.state, status.phase) {
+        (State::Idle, _) => "#888888",
+        (State::Paused, _) => "#e5c07b",
+        (State::Running, Phase::Work) => "#e06c75",
+        (State::Running, Phase::ShortBreak | Phase::LongBreak) => "#98c379",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn status() -> Status {
+        Status {
+            phase: Phase::Work,
+            state: State::Running,
+            planned_ms: 25 * 60_000,
+            elapsed_ms: 20 * 60_000,
+            remaining_ms: 5 * 60_000,
+            position: 0,
+            cycle_length: 8,
+            completed: 1,
+            today: 1,
+            today_focus_ms: 45 * 60_000,
+            goal: None,
+            streak: 0,
+            profile: None,
+            task: Some("write".to_owned()),
+        }
+    }
+
+    fn module(bar: Bar, template: &str) -> Module {
+        let template = template.parse().expect("the template is valid");
+        let command = "/usr/bin/concentrato --socket /tmp/a:b".to_owned();
+        Module::new(bar, template, Duration::from_secs(60), command)
+    }
+
+    fn json(line: &str) -> Value {
+        serde_json::from_str(line).expect("the line is JSON")
+    }
+
+    const TOOLTIP: &str = "Work, running: 5:00 left\nTask: write\nCompleted: 1";
+
+    #[test]
+    fn parses_bar_names() {
+        for bar in Bar::ALL {
+            assert_eq!(bar.as_str().parse(), Ok(bar));
+        }
+        assert_eq!("dzen".parse::<Bar>(), Err(ParseBarError("dzen".to_owned())));
+    }
+
+    #[test]
+    fn renders_waybar_json() {
+        let mut module = module(Bar::Waybar, Module::DEFAULT_TEMPLATE);
+        assert_eq!(module.header(), None);
+        assert_eq!(
+            json(&module.render(&status())),
+            json!({
+                "text": "Work 5:00",
+                "alt": "work",
+                "tooltip": TOOLTIP,
+                "class": ["work", "running"],
+                "percentage": 80,
+            })
+        );
+    }
+
+    #[test]
+    fn renders_an_i3bar_stream() {
+        let mut module = module(Bar::I3bar, Module::DEFAULT_TEMPLATE);
+        assert_eq!(
+            module.header().as_deref(),
+            Some("{\"click_events\":true,\"version\":1}\n[")
+        );
+        let first = module.render(&status());
+        assert_eq!(
+            json(&first),
+            json!([{
+                "name": "concentrato",
+                "instance": "work",
+                "full_text": "Work 5:00",
+                "short_text": "5:00",
+                "color": "#e06c75",
+                "_class": ["work", "running"],
+                "_tooltip": TOOLTIP,
+            }])
+        );
+        // Every status line after the first continues the infinite array.
+        let paused = Status {
+            state: State::Paused,
+            ..status()
+        };
+        let second = module.render(&paused);
+        let rest = second.strip_prefix(',').expect("a comma comes first");
+        assert_eq!(json(rest)[0]["color"], "#e5c07b");
+    }
+
+    #[test]
+    fn renders_i3blocks_and_i3status_rust_lines() {
+        let mut i3blocks = module(Bar::I3blocks, "{remaining:mm:ss}");
+        let idle = Status {
+            state: State::Idle,
+            ..status()
+        };
+        assert_eq!(
+            json(&i3blocks.render(&idle)),
+            json!({"full_text": "05:00", "short_text": "5:00", "color": "#888888"})
+        );
+
+        let mut module = module(Bar::I3statusRust, Module::DEFAULT_TEMPLATE);
+        let cases = [
+            (State::Idle, Phase::Work, "Idle"),
+            (State::Paused, Phase::ShortBreak, "Warning"),
+            (State::Running, Phase::Work, "Info"),
+            (State::Running, Phase::LongBreak, "Good"),
+        ];
+        for (state, phase, expected) in cases {
+            let status = Status {
+                state,
+                phase,
+                ..status()
+            };
+            let line = json(&module.render(&status));
+            assert_eq!(line["state"], expected, "{state} {phase}");
+            assert_eq!(line["icon"], "pomodoro");
+            assert_eq!(line["short_text"], "5:00");
+        }
+        // Each line stands alone, with nothing joining it to the last.
+        assert!(!module.render(&status()).contains('\n'));
+    }
+
+    #[test]
+    fn escapes_polybar_text_and_actions() {
+        let mut module = module(Bar::Polybar, "{percent}% {task}");
+        let status = Status {
+            task: Some("50% of %{F#f00}{chapter}".to_owned()),
+            ..status()
+        };
+        assert_eq!(
+            module.render(&status),
+            "%{A1:/usr/bin/concentrato --socket /tmp/a\\:b toggle:}\
+             %{A4:/usr/bin/concentrato --socket /tmp/a\\:b extend 60s:}\
+             80%% 50%% of %%{F#f00}{chapter}%{A}%{A}"
+        );
+        assert_eq!(polybar_escape("a:b::c"), "a\\:b\\:\\:c");
+        assert_eq!(polybar_escape("{no colons}"), "{no colons}");
+    }
+
+    #[test]
+    fn parses_clicks_from_either_bar() {
+        assert_eq!(
+            ClickEvent::parse(r#"{"name":"concentrato","button":1,"x":10}"#),
+            Some(ClickEvent { button: 1 })
+        );
+        // i3bar frames its clicks as an infinite array.
+        assert_eq!(ClickEvent::parse("["), None);
+        assert_eq!(
+            ClickEvent::parse(r#"[{"button":4}"#),
+            Some(ClickEvent { button: 4 })
+        );
+        assert_eq!(
+            ClickEvent::parse(r#" ,{"button":5}"#),
+            Some(ClickEvent { button: 5 })
+        );
+        assert_eq!(ClickEvent::parse(""), None);
+        assert_eq!(ClickEvent::parse("nonsense"), None);
+    }
+
+    #[test]
+    fn turns_clicks_into_requests() {
+        let module = module(Bar::I3blocks, Module::DEFAULT_TEMPLATE);
+        let click = |button| module.click(ClickEvent { button });
+        assert_eq!(click(1), Some(Request::Toggle));
+        assert_eq!(click(4), Some(Request::Extend { by_ms: 60_000 }));
+        assert_eq!(click(3), None);
+        assert_eq!(click(5), None);
+        assert!(Bar::I3bar.reports_clicks());
+        assert!(!Bar::Polybar.reports_clicks());
+    }
+}
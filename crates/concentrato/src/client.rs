@@ -0,0 +1,95 @@
+//! The client side of the daemon's [`protocol`](crate::protocol).
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use crate::protocol::{Envelope, ErrorKind, Notification, Request, Response, Status};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("the daemon is not running (nothing is listening on {})", path.display())]
+    NotRunning { path: PathBuf, source: io::Error },
+    #[error("lost the connection to the daemon: {0}")]
+    Io(#[from] io::Error),
+    #[error("the daemon sent an unreadable reply: {0}")]
+    Protocol(#[from] serde_json::Error),
+    /// The daemon understood the request but turned it down.
+    #[error("{message}")]
+    Refused { kind: ErrorKind, message: String },
+}
+
+/// A connection to the daemon.
+#[derive(Debug)]
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    pub fn connect(path: &Path) -> Result<Self, ClientError> {
+        let stream = UnixStream::connect(path).map_err(|source| match source.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => ClientError::NotRunning {
+                path: path.to_owned(),
+                source,
+            },
+            _ => ClientError::Io(source),
+        })?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// Sends `request` and waits for the timer's status after it.
+    pub fn request(&mut self, request: &Request) -> Result<Status, ClientError> {
+        let mut line = serde_json::to_string(&Envelope::new(request))?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+
+        let reply = read::<Response>(&mut self.reader)?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        match reply {
+            Response::Ok { status } => Ok(status),
+            Response::Error { kind, message } => Err(ClientError::Refused { kind, message }),
+        }
+    }
+
+    /// Subscribes to the timer's changes, and to every tick if `ticks` is
+    /// set, returning its current status and the notifications to come.
+    pub fn subscribe(mut self, ticks: bool) -> Result<(Status, Subscription), ClientError> {
+        let status = self.request(&Request::Subscribe { ticks })?;
+        Ok((
+            status,
+            Subscription {
+                reader: self.reader,
+            },
+        ))
+    }
+}
+
+/// The notifications a subscribed connection receives, which end when the
+/// daemon goes away.
+#[derive(Debug)]
+pub struct Subscription {
+    reader: BufReader<UnixStream>,
+}
+
+impl Iterator for Subscription {
+    type Item = Result<Notification, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read(&mut self.reader).transpose()
+    }
+}
+
+/// Reads the next message from the daemon, or `None` if it hung up.
+fn read<T: DeserializeOwned>(reader: &mut impl BufRead) -> Result<Option<T>, ClientError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str::<Envelope<T>>(&line)?.message))
+}
@@ -0,0 +1,1057 @@
+//! Settings, layered from built-in defaults, the config file, environment
+//! variables and command line overrides, each overriding the ones before.
+//!
+//! The file lives at `$XDG_CONFIG_HOME/concentrato/config.toml` unless
+//! `$CONCENTRATO_CONFIG` points elsewhere, and may be missing altogether.
+//! Unknown keys and impossible values are errors rather than being ignored,
+//! and errors in the file say which line they are on.
+//!
+//! Any setting can be overridden as `section.key=value` on the command line,
+//! or with a `CONCENTRATO_SECTION__KEY` environment variable such as
+//! `CONCENTRATO_TIMER__SHORT_BREAK=10m`. Override values are read as TOML
+//! where possible and as plain strings otherwise.
+//!
+//! Named profiles are partial configs under `[profiles.NAME]`, such as
+//! `[profiles.deep-work.timer]`, layered over the rest of the file. The
+//! top-level `profile` key picks the one sessions use unless they ask for
+//! another.
+//!
+//! A [`ConfigWatcher`] tells a long-running process when to load the file
+//! again.
+
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fmt, fs, io};
+
+use chrono::Weekday;
+use concentrato_core::{Cycle, SuspendPolicy, parse_duration};
+use serde::Deserialize;
+use serde::de::{self, Deserializer};
+
+use crate::goal::{self, Goal};
+use crate::paths;
+use crate::template::Template;
+
+mod watch;
+
+pub use watch::ConfigWatcher;
+
+/// Points at a config file to use instead of the default one.
+pub const CONFIG_VAR: &str = "CONCENTRATO_CONFIG";
+const ENV_PREFIX: &str = "CONCENTRATO_";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("in {}: {message}", path.display())]
+    Invalid { path: PathBuf, message: String },
+    #[error("invalid override {key} from {origin}: {message}")]
+    InvalidOverride {
+        key: String,
+        origin: Origin,
+        message: String,
+    },
+    #[error("override {0:?} is not of the form section.key=value")]
+    OverrideSyntax(String),
+    #[error("there is no profile called {0:?}")]
+    UnknownProfile(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The profile sessions use unless they ask for another.
+    pub profile: Option<String>,
+    pub timer: TimerConfig,
+    pub notifications: NotificationConfig,
+    pub sounds: SoundConfig,
+    pub hooks: HookConfig,
+    pub goal: GoalConfig,
+    /// Each profile as a complete config of its own, with the rest of this
+    /// config as its defaults. Filled in by [`Config::load`].
+    #[serde(deserialize_with = "unresolved")]
+    pub profiles: BTreeMap<String, Config>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerConfig {
+    #[serde(deserialize_with = "length")]
+    pub work: Duration,
+    #[serde(deserialize_with = "length")]
+    pub short_break: Duration,
+    #[serde(deserialize_with = "length")]
+    pub long_break: Duration,
+    /// Work sessions before each long break.
+    pub long_break_interval: NonZeroUsize,
+    /// A cycle spec such as `50/10x3 then 30`, replacing the four settings
+    /// above when present.
+    #[serde(deserialize_with = "some_parsed")]
+    pub sequence: Option<Cycle>,
+    /// What happens to a running phase when the machine suspends.
+    #[serde(deserialize_with = "parsed")]
+    pub suspend: SuspendPolicy,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        let classic = Cycle::classic();
+        let [work, short_break, .., long_break] = classic.steps() else {
+            unreachable!("the classic cycle has several steps");
+        };
+        Self {
+            work: work.duration,
+            short_break: short_break.duration,
+            long_break: long_break.duration,
+            long_break_interval: NonZeroUsize::new(classic.work_sessions()).unwrap(),
+            sequence: None,
+            suspend: SuspendPolicy::default(),
+        }
+    }
+}
+
+impl TimerConfig {
+    /// The cycle these settings describe.
+    pub fn cycle(&self) -> Cycle {
+        match &self.sequence {
+            Some(cycle) => cycle.clone(),
+            None => Cycle::standard(
+                self.work,
+                self.short_break,
+                self.long_break,
+                self.long_break_interval.get(),
+            )
+            .expect("lengths and interval are validated as they are read"),
+        }
+    }
+}
+
+/// Desktop notifications for the end of each phase.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "parsed")]
+    pub urgency: Urgency,
+    /// An icon name from the icon theme, or the path of an image.
+    pub icon: String,
+    /// How long notifications stay up.
+    #[serde(deserialize_with = "parsed")]
+    pub timeout: Expiry,
+    /// The notification when a work session ends.
+    pub work_end: Message,
+    /// The notification when a break ends.
+    pub break_end: Message,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            urgency: Urgency::Normal,
+            icon: "alarm-symbolic".to_owned(),
+            timeout: Expiry::After(Duration::from_secs(10)),
+            work_end: Message::default(),
+            break_end: Message::default(),
+        }
+    }
+}
+
+/// The text of a notification, as templates filled in with the status after
+/// the phase ended. Whatever is left out keeps the built-in text.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Message {
+    #[serde(deserialize_with = "some_parsed")]
+    pub summary: Option<Template>,
+    #[serde(deserialize_with = "some_parsed")]
+    pub body: Option<Template>,
+}
+
+/// How urgent a notification is, which servers may show differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    /// Critical notifications stay up until dismissed, whatever the timeout.
+    Critical,
+}
+
+impl FromStr for Urgency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Urgency::Low),
+            "normal" => Ok(Urgency::Normal),
+            "critical" => Ok(Urgency::Critical),
+            _ => Err(format!(
+                "unknown urgency {s:?}; expected low, normal or critical"
+            )),
+        }
+    }
+}
+
+/// When a notification goes away by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// When the notification server sees fit.
+    Default,
+    Never,
+    After(Duration),
+}
+
+impl FromStr for Expiry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Expiry::Default),
+            "never" => Ok(Expiry::Never),
+            _ => match parse_duration(s) {
+                Ok(duration) if duration.is_zero() => Ok(Expiry::Never),
+                Ok(duration) => Ok(Expiry::After(duration)),
+                Err(err) => Err(format!("{err}; or \"default\" or \"never\"")),
+            },
+        }
+    }
+}
+
+/// Sounds for the timer's events, each a bundled sound or a file, or none.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoundConfig {
+    pub enabled: bool,
+    /// From 0 for silence to 1 for the sound as it was recorded.
+    #[serde(deserialize_with = "volume")]
+    pub volume: f64,
+    #[serde(deserialize_with = "sound")]
+    pub work_start: Option<Sound>,
+    #[serde(deserialize_with = "sound")]
+    pub work_end: Option<Sound>,
+    #[serde(deserialize_with = "sound")]
+    pub break_start: Option<Sound>,
+    #[serde(deserialize_with = "sound")]
+    pub break_end: Option<Sound>,
+    /// Played every second of a running work session.
+    #[serde(deserialize_with = "sound")]
+    pub ticking: Option<Sound>,
+    /// The command that plays a file, which is given as its last argument,
+    /// instead of the first of the usual players that is installed.
+    /// `{volume}` in it stands for the volume from 0 to 1, and
+    /// `{volume_percent}` for the volume from 0 to 100.
+    pub player: Option<String>,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 1.0,
+            work_start: None,
+            work_end: Some(Sound::Bundled(BundledSound::Bell)),
+            break_start: None,
+            break_end: Some(Sound::Bundled(BundledSound::Chime)),
+            ticking: None,
+            player: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sound {
+    Bundled(BundledSound),
+    /// A WAV, OGG or FLAC file.
+    File(PathBuf),
+}
+
+/// The sounds that come with concentrato.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundledSound {
+    Bell,
+    Chime,
+    Ding,
+    Tick,
+}
+
+impl BundledSound {
+    pub const ALL: [BundledSound; 4] = [
+        BundledSound::Bell,
+        BundledSound::Chime,
+        BundledSound::Ding,
+        BundledSound::Tick,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BundledSound::Bell => "bell",
+            BundledSound::Chime => "chime",
+            BundledSound::Ding => "ding",
+            BundledSound::Tick => "tick",
+        }
+    }
+}
+
+impl FromStr for Sound {
+    type Err = String;
+
+    /// Reads the name of a bundled sound, or else the path of a file, where
+    /// `~/` stands for the home directory.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(bundled) = BundledSound::ALL.into_iter().find(|b| b.as_str() == s) {
+            return Ok(Sound::Bundled(bundled));
+        }
+        let path = paths::expand_home(s);
+        if !path.is_absolute() {
+            return Err(format!(
+                "{s:?} is neither bell, chime, ding nor tick, nor an absolute path"
+            ));
+        }
+        Ok(Sound::File(path))
+    }
+}
+
+/// Commands run on the timer's events, each given as one command line or a
+/// list of them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookConfig {
+    /// How long a command may run before it is killed; zero lets it run for
+    /// as long as it likes.
+    #[serde(deserialize_with = "duration")]
+    pub timeout: Duration,
+    #[serde(deserialize_with = "commands")]
+    pub work_start: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub work_end: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub break_start: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub break_end: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub pause: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub resume: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub skip: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub extend: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub stop: Vec<String>,
+    #[serde(deserialize_with = "commands")]
+    pub reset: Vec<String>,
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            work_start: Vec::new(),
+            work_end: Vec::new(),
+            break_start: Vec::new(),
+            break_end: Vec::new(),
+            pause: Vec::new(),
+            resume: Vec::new(),
+            skip: Vec::new(),
+            extend: Vec::new(),
+            stop: Vec::new(),
+            reset: Vec::new(),
+        }
+    }
+}
+
+impl HookConfig {
+    pub fn commands(&self, event: HookEvent) -> &[String] {
+        match event {
+            HookEvent::WorkStart => &self.work_start,
+            HookEvent::WorkEnd => &self.work_end,
+            HookEvent::BreakStart => &self.break_start,
+            HookEvent::BreakEnd => &self.break_end,
+            HookEvent::Pause => &self.pause,
+            HookEvent::Resume => &self.resume,
+            HookEvent::Skip => &self.skip,
+            HookEvent::Extend => &self.extend,
+            HookEvent::Stop => &self.stop,
+            HookEvent::Reset => &self.reset,
+        }
+    }
+}
+
+/// The events hooks can run on, named as in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    WorkStart,
+    /// A work session ran its course.
+    WorkEnd,
+    BreakStart,
+    BreakEnd,
+    Pause,
+    Resume,
+    Skip,
+    Extend,
+    Stop,
+    Reset,
+}
+
+impl HookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookEvent::WorkStart => "work_start",
+            HookEvent::WorkEnd => "work_end",
+            HookEvent::BreakStart => "break_start",
+            HookEvent::BreakEnd => "break_end",
+            HookEvent::Pause => "pause",
+            HookEvent::Resume => "resume",
+            HookEvent::Skip => "skip",
+            HookEvent::Extend => "extend",
+            HookEvent::Stop => "stop",
+            HookEvent::Reset => "reset",
+        }
+    }
+}
+
+/// A target for each day's work, and the days off that do not count against
+/// it. The goal is the same whatever the profile, so profiles cannot set it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoalConfig {
+    #[serde(deserialize_with = "goal")]
+    pub daily: Option<Goal>,
+    /// Days that keep a streak going even if the goal is missed on them.
+    #[serde(deserialize_with = "weekdays")]
+    pub rest_days: Vec<Weekday>,
+}
+
+/// Where an [`Override`] came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The named environment variable.
+    Env(String),
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Env(var) => write!(f, "${var}"),
+            Origin::CommandLine => f.write_str("the command line"),
+        }
+    }
+}
+
+/// A single setting given outside the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    /// Dotted path such as `timer.work`.
+    pub key: String,
+    pub value: String,
+    pub origin: Origin,
+}
+
+impl Override {
+    /// Parses a `section.key=value` command line argument.
+    pub fn from_arg(arg: &str) -> Result<Self, ConfigError> {
+        let (key, value) = arg
+            .split_once('=')
+            .filter(|(key, _)| !key.trim().is_empty())
+            .ok_or_else(|| ConfigError::OverrideSyntax(arg.to_owned()))?;
+        Ok(Self {
+            key: key.trim().to_owned(),
+            value: value.to_owned(),
+            origin: Origin::CommandLine,
+        })
+    }
+
+    /// Collects the `CONCENTRATO_SECTION__KEY` variables of this process.
+    ///
+    /// Variables without a `__` separator, like [`CONFIG_VAR`], are not
+    /// settings and are left alone.
+    pub fn from_env() -> Vec<Self> {
+        let mut overrides: Vec<_> = env::vars()
+            .filter_map(|(var, value)| {
+                let path = var.strip_prefix(ENV_PREFIX)?;
+                if !path.contains("__") {
+                    return None;
+                }
+                Some(Self {
+                    key: path.to_lowercase().replace("__", "."),
+                    value,
+                    origin: Origin::Env(var),
+                })
+            })
+            .collect();
+        // Keep error messages stable no matter how the environment is ordered.
+        overrides.sort_by(|a, b| a.key.cmp(&b.key));
+        overrides
+    }
+
+    fn apply(&self, table: &mut toml::Table) -> Result<(), ConfigError> {
+        let invalid = |message: &str| ConfigError::InvalidOverride {
+            key: self.key.clone(),
+            origin: self.origin.clone(),
+            message: message.to_owned(),
+        };
+        let mut table = table;
+        let mut path = self.key.split('.').peekable();
+        while let Some(part) = path.next() {
+            if part.is_empty() {
+                return Err(invalid("keys cannot have empty parts"));
+            }
+            if path.peek().is_none() {
+                let value = toml::Value::from_str(&self.value)
+                    .unwrap_or_else(|_| toml::Value::String(self.value.clone()));
+                table.insert(part.to_owned(), value);
+                return Ok(());
+            }
+            table = table
+                .entry(part)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| invalid(&format!("{part} is not a section")))?;
+        }
+        unreachable!("split always yields at least one part")
+    }
+}
+
+/// The config file to read: `$CONCENTRATO_CONFIG`, or `config.toml` in the
+/// XDG config directory.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os(CONFIG_VAR)
+        .map(PathBuf::from)
+        .or_else(|| paths::config_dir().map(|dir| dir.join("config.toml")))
+}
+
+impl Config {
+    /// Reads the config file at `path`, if it exists, and applies
+    /// `overrides` on top of it in order.
+    pub fn load(path: Option<&Path>, overrides: &[Override]) -> Result<Self, ConfigError> {
+        let text = match path.map(fs::read_to_string) {
+            Some(Ok(text)) => text,
+            Some(Err(err)) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Some(Err(source)) => {
+                return Err(ConfigError::Read {
+                    path: path.unwrap().to_owned(),
+                    source,
+                });
+            }
+            None => String::new(),
+        };
+        Self::parse(&text, path.unwrap_or(Path::new("config.toml")), overrides)
+    }
+
+    /// Reads the config in `text`, which came from `path`, and applies
+    /// `overrides` on top of it in order.
+    fn parse(text: &str, path: &Path, overrides: &[Override]) -> Result<Self, ConfigError> {
+        let invalid = |err: toml::de::Error| ConfigError::Invalid {
+            path: path.to_owned(),
+            message: err.to_string(),
+        };
+
+        // Reading the text directly keeps the line numbers in error messages,
+        // which are lost once it has become a table.
+        let mut config: Config = toml::from_str(text).map_err(invalid)?;
+        let mut table: toml::Table = toml::from_str(text).map_err(invalid)?;
+        for o in overrides {
+            o.apply(&mut table)?;
+            config = table.clone().try_into().map_err(|err: toml::de::Error| {
+                ConfigError::InvalidOverride {
+                    key: o.key.clone(),
+                    origin: o.origin.clone(),
+                    message: err.message().to_owned(),
+                }
+            })?;
+        }
+
+        config.profiles = resolve_profiles(&table, text, path)?;
+        if let Some(name) = &config.profile {
+            if !config.profiles.contains_key(name) {
+                return Err(ConfigError::UnknownProfile(name.clone()));
+            }
+        }
+        Ok(config)
+    }
+
+    /// The settings for a session using profile `name`, or the default
+    /// profile if there is no name.
+    pub fn profile(&self, name: Option<&str>) -> Result<&Config, ConfigError> {
+        match name.or(self.profile.as_deref()) {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .ok_or_else(|| ConfigError::UnknownProfile(name.to_owned())),
+            None => Ok(self),
+        }
+    }
+}
+
+/// Builds a complete config for every profile by layering it over the
+/// merged `table` of everything else.
+fn resolve_profiles(
+    table: &toml::Table,
+    text: &str,
+    path: &Path,
+) -> Result<BTreeMap<String, Config>, ConfigError> {
+    /// Where each profile starts in the file, for error messages.
+    #[derive(Deserialize)]
+    struct Spans {
+        #[serde(default)]
+        profiles: BTreeMap<String, toml::Spanned<de::IgnoredAny>>,
+    }
+
+    let Some(profiles) = table.get("profiles").and_then(toml::Value::as_table) else {
+        return Ok(BTreeMap::new());
+    };
+    let spans = toml::from_str::<Spans>(text).map_or_else(|_| BTreeMap::new(), |s| s.profiles);
+    let mut base = table.clone();
+    base.remove("profile");
+    base.remove("profiles");
+
+    let mut resolved = BTreeMap::new();
+    for (name, overlay) in profiles {
+        let invalid = |message: &str| {
+            let line = spans
+                .get(name)
+                .map(|span| text[..span.span().start].matches('\n').count() + 1);
+            ConfigError::Invalid {
+                path: path.to_owned(),
+                message: match line {
+                    Some(line) => format!("profile {name:?} on line {line}: {message}"),
+                    None => format!("profile {name:?}: {message}"),
+                },
+            }
+        };
+        let overlay = overlay
+            .as_table()
+            .expect("profiles are checked to be tables as they are read");
+        if overlay.contains_key("profile") || overlay.contains_key("profiles") {
+            return Err(invalid("profiles cannot pick or define other profiles"));
+        }
+        if overlay.contains_key("goal") {
+            return Err(invalid(
+                "the daily goal is the same whatever the profile, so it belongs outside profiles",
+            ));
+        }
+
+        let mut merged = base.clone();
+        merge(&mut merged, overlay);
+        // A profile that spells out its own lengths means them, even if the
+        // rest of the file uses a sequence.
+        if let Some(timer) = overlay.get("timer").and_then(toml::Value::as_table) {
+            let lengths = ["work", "short_break", "long_break", "long_break_interval"];
+            if !timer.contains_key("sequence") && lengths.iter().any(|k| timer.contains_key(*k)) {
+                if let Some(timer) = merged.get_mut("timer").and_then(toml::Value::as_table_mut) {
+                    timer.remove("sequence");
+                }
+            }
+        }
+        let config = merged
+            .try_into()
+            .map_err(|err: toml::de::Error| invalid(err.message()))?;
+        resolved.insert(name.clone(), config);
+    }
+    Ok(resolved)
+}
+
+/// Recursively copies `overlay` into `base`, keeping keys it does not set.
+fn merge(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Reads a phase length, which is a [`duration`] that cannot be zero.
+fn length<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let duration = duration(deserializer)?;
+    if duration.is_zero() {
+        return Err(de::Error::custom("a phase cannot be zero length"));
+    }
+    Ok(duration)
+}
+
+/// Reads a number of minutes, or a string in the syntax of
+/// [`parse_duration`].
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    struct Length;
+
+    impl de::Visitor<'_> for Length {
+        type Value = Duration;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a number of minutes or a duration such as \"1h30m\"")
+        }
+
+        fn visit_i64<E: de::Error>(self, minutes: i64) -> Result<Duration, E> {
+            let minutes = u64::try_from(minutes)
+                .map_err(|_| E::invalid_value(de::Unexpected::Signed(minutes), &self))?;
+            Ok(Duration::from_secs(minutes.saturating_mul(60)))
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Duration, E> {
+            parse_duration(text).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(Length)
+}
+
+/// Reads a goal: a number of pomodoros, or a duration of focus time as a
+/// string.
+fn goal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Goal>, D::Error> {
+    struct GoalVisitor;
+
+    impl de::Visitor<'_> for GoalVisitor {
+        type Value = Goal;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a number of pomodoros or a duration such as \"4h\"")
+        }
+
+        fn visit_i64<E: de::Error>(self, count: i64) -> Result<Goal, E> {
+            match u32::try_from(count) {
+                Ok(count) if count > 0 => Ok(Goal::Pomodoros(count)),
+                _ => Err(E::invalid_value(de::Unexpected::Signed(count), &self)),
+            }
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Goal, E> {
+            text.parse().map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(GoalVisitor).map(Some)
+}
+
+fn weekdays<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Weekday>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|day| goal::parse_weekday(day).map_err(de::Error::custom))
+        .collect()
+}
+
+/// Reads one command line, or a list of them.
+fn commands<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "a command line or a list of them")]
+    enum Commands {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Commands::deserialize(deserializer)? {
+        Commands::One(command) => vec![command],
+        Commands::Many(commands) => commands,
+    })
+}
+
+/// Reads a sound, where `"none"` or an empty string means silence.
+fn sound<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Sound>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "" | "none" => Ok(None),
+        sound => sound.parse().map(Some).map_err(de::Error::custom),
+    }
+}
+
+fn volume<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let volume = f64::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&volume) {
+        return Err(de::Error::custom(format!(
+            "volume {volume} is not between 0 and 1"
+        )));
+    }
+    Ok(volume)
+}
+
+/// Checks that profiles are tables, leaving [`Config::load`] to resolve them.
+fn unresolved<'de, D>(deserializer: D) -> Result<BTreeMap<String, Config>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, toml::Table>::deserialize(deserializer)?;
+    Ok(BTreeMap::new())
+}
+
+fn parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+fn some_parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parsed(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(m: u64) -> Duration {
+        Duration::from_secs(m * 60)
+    }
+
+    fn parse(text: &str, overrides: &[Override]) -> Result<Config, ConfigError> {
+        Config::parse(text, Path::new("config.toml"), overrides)
+    }
+
+    fn env(key: &str, value: &str) -> Override {
+        Override {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            origin: Origin::Env(format!(
+                "CONCENTRATO_{}",
+                key.to_uppercase().replace('.', "__")
+            )),
+        }
+    }
+
+    fn arg(arg: &str) -> Override {
+        Override::from_arg(arg).unwrap()
+    }
+
+    /// The line an error in the file is reported on, the text of that line,
+    /// and what the error says about it.
+    fn invalid(text: &str) -> (usize, String, String) {
+        let err = parse(text, &[]).unwrap_err();
+        let ConfigError::Invalid { path, message } = &err else {
+            panic!("{text:?} gave {err:?}");
+        };
+        assert_eq!(path, Path::new("config.toml"));
+        let (_, rest) = message
+            .split_once("at line ")
+            .unwrap_or_else(|| panic!("{message:?} has no line"));
+        let line: usize = rest[..rest.find(',').unwrap()].parse().unwrap();
+        let reason = message.lines().last().unwrap().to_owned();
+        (line, text.lines().nth(line - 1).unwrap().to_owned(), reason)
+    }
+
+    #[test]
+    fn defaults_to_the_classic_cycle() {
+        let config = parse("", &[]).unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.timer.cycle(), Cycle::classic());
+        assert_eq!(config.profile(None).unwrap(), &config);
+    }
+
+    #[test]
+    fn reads_a_missing_file_as_empty() {
+        let path = env::temp_dir().join("concentrato-no-such-config.toml");
+        assert_eq!(Config::load(Some(&path), &[]).unwrap(), Config::default());
+        assert_eq!(Config::load(None, &[]).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn layers_the_file_over_the_defaults() {
+        let config = parse("[timer]\nwork = \"50m\"\nshort_break = 10\n", &[]).unwrap();
+        assert_eq!(config.timer.work, minutes(50));
+        assert_eq!(config.timer.short_break, minutes(10));
+        assert_eq!(config.timer.long_break, minutes(15));
+        assert_eq!(config.sounds, SoundConfig::default());
+    }
+
+    #[test]
+    fn layers_overrides_over_the_file_in_order() {
+        let text = "[timer]\nwork = 50\nshort_break = 10\n";
+        let overrides = [
+            env("timer.short_break", "7m"),
+            env("timer.work", "40"),
+            arg("timer.work=45"),
+        ];
+        let config = parse(text, &overrides).unwrap();
+        assert_eq!(config.timer.work, minutes(45));
+        assert_eq!(config.timer.short_break, minutes(7));
+    }
+
+    #[test]
+    fn reads_override_values_as_toml_or_strings() {
+        let overrides = [
+            arg("timer.sequence=50/10x3 then 30"),
+            arg("timer.suspend=pause"),
+            arg("notifications.enabled=false"),
+            arg("hooks.work_end=[\"a\", \"b\"]"),
+        ];
+        let config = parse("", &overrides).unwrap();
+        assert_eq!(config.timer.cycle(), "50/10x3 then 30".parse().unwrap());
+        assert_eq!(config.timer.suspend, SuspendPolicy::Pause);
+        assert!(!config.notifications.enabled);
+        assert_eq!(config.hooks.work_end, ["a", "b"]);
+    }
+
+    #[test]
+    fn rejects_malformed_overrides() {
+        for bad in ["timer.work", "=25", " =25"] {
+            assert!(matches!(
+                Override::from_arg(bad),
+                Err(ConfigError::OverrideSyntax(arg)) if arg == bad
+            ));
+        }
+        let err = parse("", &[env("timer.work", "0")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid override timer.work from $CONCENTRATO_TIMER__WORK: \
+             a phase cannot be zero length"
+        );
+        let err = parse("[timer]\nwork = 25\n", &[arg("timer.work.minutes=5")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid override timer.work.minutes from the command line: work is not a section"
+        );
+        let err = parse("", &[arg("timer..work=5")]).unwrap_err();
+        assert!(err.to_string().ends_with("keys cannot have empty parts"));
+    }
+
+    #[test]
+    fn layers_profiles_over_the_rest_of_the_file() {
+        let text = "\
+profile = \"deep\"
+
+[timer]
+sequence = \"25/5x4 then 15\"
+suspend = \"pause\"
+
+[profiles.deep.timer]
+work = 50
+short_break = 10
+long_break_interval = 2
+
+[profiles.sprint.timer]
+sequence = \"15/3\"
+";
+        let config = parse(text, &[arg("sounds.volume=0.5")]).unwrap();
+        let deep = config.profile(None).unwrap();
+        assert_eq!(
+            deep.timer.cycle(),
+            Cycle::standard(minutes(50), minutes(10), minutes(15), 2).unwrap(),
+            "lengths in a profile replace the file's sequence"
+        );
+        assert_eq!(deep.timer.suspend, SuspendPolicy::Pause);
+        assert_eq!(deep.sounds.volume, 0.5);
+        let sprint = config.profile(Some("sprint")).unwrap();
+        assert_eq!(sprint.timer.cycle(), "15/3".parse().unwrap());
+        assert!(matches!(
+            config.profile(Some("nap")),
+            Err(ConfigError::UnknownProfile(name)) if name == "nap"
+        ));
+    }
+
+    #[test]
+    fn rejects_an_unknown_default_profile() {
+        let err = parse("profile = \"nap\"\n", &[]).unwrap_err();
+        assert!(matches!(err, ConfigError::UnknownProfile(name) if name == "nap"));
+    }
+
+    #[test]
+    fn reports_the_line_of_an_invalid_profile() {
+        let text = "[profiles.deep]\ntimer.work = 50\n\n[profiles.bad]\n\ntimer.work = 0\n";
+        assert_eq!(
+            parse(text, &[]).unwrap_err().to_string(),
+            "in config.toml: profile \"bad\" on line 4: a phase cannot be zero length"
+        );
+        let text = "[profiles.nested]\nprofile = \"nested\"\n";
+        assert_eq!(
+            parse(text, &[]).unwrap_err().to_string(),
+            "in config.toml: profile \"nested\" on line 1: \
+             profiles cannot pick or define other profiles"
+        );
+        let text = "[goal]\ndaily = 8\n\n[profiles.deep.goal]\ndaily = \"4h\"\n";
+        assert_eq!(
+            parse(text, &[]).unwrap_err().to_string(),
+            "in config.toml: profile \"deep\" on line 4: \
+             the daily goal is the same whatever the profile, so it belongs outside profiles"
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_bad_toml() {
+        let (line, text, reason) = invalid("[timer]\nwork = 25\n\n[sounds\nvolume = 1\n");
+        assert_eq!((line, text.as_str()), (4, "[sounds"));
+        assert_eq!(reason, "unclosed table, expected `]`");
+    }
+
+    #[test]
+    fn reports_the_line_and_field_of_invalid_values() {
+        let cases = [
+            (
+                "[timer]\nwork = 25\nshort_break = 0\n",
+                3,
+                "short_break",
+                "a phase cannot be zero length",
+            ),
+            (
+                "[timer]\nwork = \"25x\"\n",
+                2,
+                "work",
+                "invalid duration \"25x\": units must be h, m or s",
+            ),
+            (
+                "[timer]\nlong_break_interval = 0\n",
+                2,
+                "long_break_interval",
+                "invalid value: integer `0`, expected a nonzero usize",
+            ),
+            (
+                "[timer]\nsequence = \"25/5x0\"\n",
+                2,
+                "sequence",
+                "invalid cycle \"25/5x0\": a round cannot be repeated zero times",
+            ),
+            (
+                "[sounds]\n\nvolume = 1.5\n",
+                3,
+                "volume",
+                "volume 1.5 is not between 0 and 1",
+            ),
+            (
+                "[sounds]\nwork_end = \"sounds/bell.ogg\"\n",
+                2,
+                "work_end",
+                "\"sounds/bell.ogg\" is neither bell, chime, ding nor tick, nor an absolute path",
+            ),
+            (
+                "[notifications]\nurgency = \"loud\"\n",
+                2,
+                "urgency",
+                "unknown urgency \"loud\"; expected low, normal or critical",
+            ),
+            (
+                "[goal]\ndaily = 4\nrest_days = [\"funday\"]\n",
+                3,
+                "rest_days",
+                "unknown day \"funday\"; expected mon, tue, wed, thu, fri, sat or sun",
+            ),
+        ];
+        for (text, line, field, reason) in cases {
+            let got = invalid(text);
+            assert_eq!(got.0, line, "{text:?}");
+            assert!(got.1.starts_with(field), "{text:?} blamed {:?}", got.1);
+            assert_eq!(got.2, reason, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn reports_unknown_fields() {
+        let (line, text, reason) = invalid("[timer]\nwork = 25\nwrok = 30\n");
+        assert_eq!((line, text.as_str()), (3, "wrok = 30"));
+        assert!(reason.starts_with("unknown field `wrok`, expected one of `work`"));
+        let (line, _, reason) = invalid("# settings\n[timers]\n");
+        assert_eq!(line, 2);
+        assert!(reason.starts_with("unknown field `timers`"));
+    }
+}
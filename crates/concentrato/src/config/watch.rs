@@ -0,0 +1,234 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+/// Editors tend to save in several steps, so changes are only reported once
+/// the file has been left alone for this long.
+const SETTLE: Duration = Duration::from_millis(150);
+
+/// Notices when the config file changes on disk, using inotify.
+///
+/// It is the file's directory that is watched, since many editors save by
+/// writing a new file and renaming it over the old one, which a watch on the
+/// file itself would not survive. Until the directory exists, its nearest
+/// ancestor that does is watched instead, one level closer each time a
+/// directory appears, and the same happens if the directory goes away.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    inotify: Inotify,
+    path: PathBuf,
+    name: OsString,
+    dir: PathBuf,
+    /// `dir`, or its nearest ancestor while `dir` does not exist.
+    watched: PathBuf,
+    watch: WatchDescriptor,
+    buffer: Vec<u8>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching `path`, which need not exist yet, nor its directory.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} does not name a file", path.display()),
+            ));
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let inotify = Inotify::init()?;
+        let (watched, watch) = arm(&inotify, dir)?;
+        Ok(Self {
+            inotify,
+            path: path.to_owned(),
+            name: name.to_owned(),
+            dir: dir.to_owned(),
+            watched,
+            watch,
+            buffer: vec![0; 4096],
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Blocks until the file has been written, replaced or removed, and has
+    /// then settled.
+    pub fn wait(&mut self) -> io::Result<()> {
+        while !self.read(true)? {}
+        // Swallow the rest of the burst so that one save is one change,
+        // though still following the directory if it goes away.
+        loop {
+            thread::sleep(SETTLE);
+            match self.read(false) {
+                Ok(_) => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Reads the events that have arrived, waiting for some if `block`, and
+    /// tells whether the file changed.
+    fn read(&mut self, block: bool) -> io::Result<bool> {
+        let in_dir = self.watched == self.dir;
+        let events = if block {
+            self.inotify.read_events_blocking(&mut self.buffer)?
+        } else {
+            self.inotify.read_events(&mut self.buffer)?
+        };
+        let (mut changed, mut moved) = (false, false);
+        for event in events {
+            // Events can still arrive from watches given up on before.
+            if event.wd != self.watch {
+                continue;
+            }
+            changed |= in_dir && event.name == Some(self.name.as_os_str());
+            moved |= !in_dir
+                || event
+                    .mask
+                    .intersects(EventMask::DELETE_SELF | EventMask::MOVE_SELF | EventMask::IGNORED);
+        }
+        if moved {
+            self.rearm()?;
+            // The directory may have come with the file already in it.
+            changed |= !in_dir && self.watched == self.dir && self.path.exists();
+        }
+        Ok(changed)
+    }
+
+    /// Moves the watch to the directory, or the ancestor closest to it,
+    /// after one of them appeared or went away.
+    fn rearm(&mut self) -> io::Result<()> {
+        // The watch is already gone if its directory is.
+        let _ = self.inotify.watches().remove(self.watch.clone());
+        (self.watched, self.watch) = arm(&self.inotify, &self.dir)?;
+        Ok(())
+    }
+}
+
+/// Watches `dir` for changes to the files in it or, while it does not
+/// exist, its nearest ancestor for the next directory on the way to it.
+fn arm(inotify: &Inotify, dir: &Path) -> io::Result<(PathBuf, WatchDescriptor)> {
+    let watched = dir
+        .ancestors()
+        .map(|ancestor| {
+            if ancestor.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                ancestor
+            }
+        })
+        .find(|ancestor| ancestor.is_dir())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no directory on the way to {} exists", dir.display()),
+            )
+        })?;
+    let contents = if watched == dir {
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE
+    } else {
+        WatchMask::CREATE | WatchMask::MOVED_TO
+    };
+    let watch = inotify.watches().add(
+        watched,
+        contents | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF | WatchMask::ONLYDIR,
+    )?;
+    Ok((watched.to_owned(), watch))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::mpsc::{self, Receiver};
+
+    use super::*;
+    use crate::daemon::testing::TempDir;
+
+    /// Watches `path` on a thread of its own, reporting each change.
+    fn watch(path: &Path) -> Receiver<()> {
+        let mut watcher = ConfigWatcher::new(path).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(
+            move || {
+                while watcher.wait().is_ok() && sender.send(()).is_ok() {}
+            },
+        );
+        receiver
+    }
+
+    /// Waits for a change, then for the watcher to have settled.
+    fn changed(changes: &Receiver<()>, what: &str) {
+        assert!(
+            changes.recv_timeout(Duration::from_secs(5)).is_ok(),
+            "no change seen after {what}"
+        );
+        while changes.recv_timeout(SETTLE * 3).is_ok() {}
+    }
+
+    fn unchanged(changes: &Receiver<()>, what: &str) {
+        assert!(
+            changes.recv_timeout(SETTLE * 3).is_err(),
+            "a change seen after {what}"
+        );
+    }
+
+    #[test]
+    fn sees_the_file_written_replaced_and_removed() {
+        let dir = TempDir::new();
+        let path = dir.join("config.toml");
+        let changes = watch(&path);
+
+        fs::write(&path, "[timer]\nwork = 50\n").unwrap();
+        changed(&changes, "a write");
+
+        // As editors that save to a new file and rename it over the old.
+        let temporary = dir.join(".config.toml.swp");
+        fs::write(&temporary, "[timer]\nwork = 25\n").unwrap();
+        fs::rename(&temporary, &path).unwrap();
+        changed(&changes, "a replace");
+
+        fs::write(dir.join("other.toml"), "").unwrap();
+        unchanged(&changes, "a write to another file");
+
+        fs::remove_file(&path).unwrap();
+        changed(&changes, "a delete");
+    }
+
+    #[test]
+    fn waits_for_the_directory_to_appear() {
+        let dir = TempDir::new();
+        let config_dir = dir.join("xdg").join("concentrato");
+        let path = config_dir.join("config.toml");
+        let changes = watch(&path);
+
+        fs::create_dir_all(&config_dir).unwrap();
+        unchanged(&changes, "creating the directory");
+        fs::write(&path, "").unwrap();
+        changed(&changes, "a write to a new directory");
+
+        // And again, after the directory goes away and comes back.
+        fs::remove_dir_all(dir.join("xdg")).unwrap();
+        changed(&changes, "removing the directory");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(&path, "").unwrap();
+        changed(&changes, "a write to a recreated directory");
+        fs::write(&path, "[timer]\n").unwrap();
+        changed(&changes, "another write");
+    }
+
+    #[test]
+    fn refuses_a_path_without_a_file_name() {
+        let err = ConfigWatcher::new(Path::new("/")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
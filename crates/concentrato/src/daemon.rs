@@ -0,0 +1,1156 @@
+//! The long-running process that owns the timer.
+//!
+//! The daemon listens on a Unix socket speaking the [`protocol`], serving
+//! each connection on a thread of its own. Another thread ticks the timer so
+//! phases end on time, and a third reloads the config file when it changes.
+//! Everything they share lives in one [`Engine`] behind a mutex, which also
+//! keeps the channels to the connections subscribed to its changes. Desktop
+//! notifications, sounds, hooks and the D-Bus service are subscribers too.
+//!
+//! Only one daemon serves a socket at a time; see [`Instance`]. Its progress
+//! is saved as a [`Checkpoint`] whenever it changes, and picked up again when
+//! the daemon next starts. Each phase that ends is recorded in the
+//! [`History`].
+//!
+//! [`protocol`]: crate::protocol
+
+use std::collections::BTreeMap;
+use std::fs::DirBuilder;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{Local, NaiveDate};
+use concentrato_core::{Event, Phase, Snapshot, Timer, TransitionError, format_duration};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, ConfigError, ConfigWatcher, Override};
+use crate::goal::Day;
+use crate::history::{self, History, Outcome, Session};
+use crate::protocol::{
+    self, Envelope, ErrorKind, EventKind, Notification, Request, Response, Status, VERSION,
+};
+
+mod checkpoint;
+mod dbus;
+mod hooks;
+mod instance;
+mod notify;
+mod sound;
+mod systemd;
+#[cfg(test)]
+pub(crate) mod testing;
+
+use checkpoint::Checkpoint;
+use instance::{Claim, Instance};
+
+/// The longest the ticker sleeps, which bounds how late a suspend is noticed.
+const TICK: Duration = Duration::from_secs(1);
+/// How often a running phase is checkpointed when nothing else happens.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub socket: PathBuf,
+    /// The config file, which is watched for changes.
+    pub config_path: Option<PathBuf>,
+    pub overrides: Vec<Override>,
+    /// Where progress is saved so that it survives the daemon.
+    pub checkpoint: Option<PathBuf>,
+    /// The database sessions are recorded in.
+    pub history: Option<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DaemonError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("cannot listen on {}: {source}", path.display())]
+    Listen { path: PathBuf, source: io::Error },
+    #[error("{}", already_running(path, *pid))]
+    AlreadyRunning { path: PathBuf, pid: Option<u32> },
+}
+
+fn already_running(path: &Path, pid: Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!(
+            "a daemon is already running as process {pid}, listening on {}",
+            path.display()
+        ),
+        None => format!("a daemon is already listening on {}", path.display()),
+    }
+}
+
+/// Runs the daemon until the process is killed.
+///
+/// Under systemd's socket activation the daemon serves the socket it is
+/// handed rather than binding its own.
+pub fn run(options: Options) -> Result<(), DaemonError> {
+    let config = Config::load(options.config_path.as_deref(), &options.overrides)?;
+    let (_instance, listener) = match systemd::listener() {
+        Ok(Some(listener)) => adopt(listener, &options.socket)?,
+        Ok(None) => listen(&options.socket)?,
+        Err(err) => {
+            return Err(DaemonError::Listen {
+                path: options.socket,
+                source: err,
+            });
+        }
+    };
+
+    let history = options.history.and_then(|path| match History::open(&path) {
+        Ok(history) => Some(history),
+        Err(err) => {
+            warn!("not recording sessions: {err}");
+            None
+        }
+    });
+    let mut engine = Engine::new(config, options.checkpoint, history);
+    engine.restore();
+    let engine = Arc::new(Mutex::new(engine));
+    {
+        let engine = Arc::clone(&engine);
+        thread::spawn(move || tick(&engine));
+    }
+    notify::spawn(Arc::clone(&engine));
+    sound::spawn(Arc::clone(&engine));
+    hooks::spawn(Arc::clone(&engine));
+    dbus::spawn(Arc::clone(&engine));
+    if let Some(path) = options.config_path {
+        let engine = Arc::clone(&engine);
+        thread::spawn(move || watch_config(&path, &options.overrides, &engine));
+    }
+    systemd::notify_ready();
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let engine = Arc::clone(&engine);
+                thread::spawn(move || {
+                    if let Err(err) = serve(stream, &engine) {
+                        debug!("client connection failed: {err}");
+                    }
+                });
+            }
+            Err(err) => warn!("cannot accept a connection: {err}"),
+        }
+    }
+    Ok(())
+}
+
+/// Becomes the daemon for the socket at `path` and starts listening on it.
+fn listen(path: &Path) -> Result<(Instance, UnixListener), DaemonError> {
+    let error = |source| DaemonError::Listen {
+        path: path.to_owned(),
+        source,
+    };
+    if let Some(dir) = path.parent() {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .map_err(error)?;
+    }
+    let instance = match Instance::claim(path).map_err(error)? {
+        Claim::Acquired(instance) => instance,
+        Claim::Taken { pid } => {
+            return Err(DaemonError::AlreadyRunning {
+                path: path.to_owned(),
+                pid,
+            });
+        }
+    };
+    let listener = UnixListener::bind(path).map_err(error)?;
+    info!("listening on {}", path.display());
+    Ok((instance, listener))
+}
+
+/// Becomes the daemon for the socket systemd bound and passed on as
+/// `listener`, which `path` names unless the socket unit put it elsewhere.
+fn adopt(listener: UnixListener, path: &Path) -> Result<(Instance, UnixListener), DaemonError> {
+    let path = listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(Path::to_owned))
+        .unwrap_or_else(|| path.to_owned());
+    let instance = match Instance::adopt(&path) {
+        Ok(Claim::Acquired(instance)) => instance,
+        Ok(Claim::Taken { pid }) => return Err(DaemonError::AlreadyRunning { path, pid }),
+        Err(source) => return Err(DaemonError::Listen { path, source }),
+    };
+    info!("listening on {}, from systemd", path.display());
+    Ok((instance, listener))
+}
+
+/// The timer and everything needed to drive it.
+#[derive(Debug)]
+struct Engine {
+    timer: Timer,
+    config: Config,
+    /// The profile a client switched to, overriding the config's default.
+    profile: Option<String>,
+    /// What the current session is for.
+    task: Option<String>,
+    /// When the current phase was first started.
+    started_at: Option<SystemTime>,
+    /// How many times the current phase has been paused.
+    pauses: u32,
+    tally: Tally,
+    /// What the days in the history added up to as of `past_day`, for
+    /// streaks.
+    past: BTreeMap<NaiveDate, Day>,
+    past_day: NaiveDate,
+    checkpoint: Option<PathBuf>,
+    last_checkpoint: Instant,
+    history: Option<History>,
+    /// The record of the phase the timer last moved on from, which a
+    /// prolong takes up again.
+    ended: Option<i64>,
+    /// The record the current phase carries on from after a prolong, which
+    /// it becomes part of when it ends.
+    prolonging: Option<i64>,
+    subscribers: Vec<Subscriber>,
+}
+
+/// The work done on one day, which starts over at midnight rather than with
+/// the cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Tally {
+    day: NaiveDate,
+    /// Work sessions completed.
+    count: u32,
+    #[serde(default)]
+    focus: Duration,
+}
+
+impl Tally {
+    /// What today's work has added up to.
+    fn today(self) -> Day {
+        if self.day == today() {
+            Day {
+                pomodoros: self.count,
+                focus: self.focus,
+            }
+        } else {
+            Day::default()
+        }
+    }
+
+    /// Counts a work session that ran for `focus`.
+    fn add(&mut self, focus: Duration, completed: bool) {
+        let day = today();
+        if self.day != day {
+            *self = Tally {
+                day,
+                ..Tally::default()
+            };
+        }
+        self.count += u32::from(completed);
+        self.focus += focus;
+    }
+
+    /// Takes back a session that turned out not to be over.
+    fn take_back(&mut self) {
+        if self.day == today() {
+            self.count = self.count.saturating_sub(1);
+        }
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// A connection waiting to hear about changes to the timer.
+#[derive(Debug)]
+struct Subscriber {
+    sender: Sender<Notification>,
+    /// Whether it wants every tick, not just changes.
+    ticks: bool,
+}
+
+impl Engine {
+    fn new(config: Config, checkpoint: Option<PathBuf>, history: Option<History>) -> Self {
+        let mut engine = Self {
+            timer: Timer::default(),
+            config,
+            profile: None,
+            task: None,
+            started_at: None,
+            pauses: 0,
+            tally: Tally::default(),
+            past: BTreeMap::new(),
+            past_day: NaiveDate::default(),
+            checkpoint,
+            last_checkpoint: Instant::now(),
+            history,
+            ended: None,
+            prolonging: None,
+            subscribers: Vec::new(),
+        };
+        engine.apply_settings();
+        engine.load_past();
+        engine
+    }
+
+    /// Reads what each day in the history added up to.
+    fn load_past(&mut self) {
+        self.past_day = today();
+        let Some(history) = &self.history else {
+            return;
+        };
+        match history.days() {
+            Ok(days) => self.past = days,
+            Err(err) => warn!("cannot read past days for streaks: {err}"),
+        }
+    }
+
+    /// Picks up where the last daemon left off, if it saved a checkpoint.
+    fn restore(&mut self) {
+        let Some(path) = self.checkpoint.clone() else {
+            return;
+        };
+        let checkpoint = match Checkpoint::load(&path) {
+            Ok(Some(checkpoint)) => checkpoint,
+            Ok(None) => return,
+            Err(err) => {
+                warn!("ignoring unreadable checkpoint {}: {err}", path.display());
+                return;
+            }
+        };
+        if let Some(name) = checkpoint.profile {
+            if self.config.profile(Some(&name)).is_ok() {
+                self.profile = Some(name);
+                self.apply_settings();
+            } else {
+                warn!("profile {name:?} is gone, going back to the default");
+            }
+        }
+        // The clock may have been changed since; treat that as no downtime
+        // rather than refusing to carry on.
+        let downtime = SystemTime::now()
+            .duration_since(checkpoint.saved_at)
+            .unwrap_or_default();
+        self.task = checkpoint.task;
+        self.started_at = checkpoint.started_at;
+        self.pauses = checkpoint.pauses;
+        self.tally = checkpoint.tally;
+        self.prolonging = checkpoint.prolonging;
+        let event = self.timer.restore(checkpoint.timer, downtime);
+        info!(
+            "picked up {} ({}) from {}",
+            self.timer.phase(),
+            self.timer.status(),
+            path.display()
+        );
+        match event {
+            Some(event) => self.on_event(event, &checkpoint.timer),
+            None => self.save(),
+        }
+    }
+
+    /// Saves the engine's progress, if it has somewhere to.
+    fn save(&mut self) {
+        let Some(path) = &self.checkpoint else {
+            return;
+        };
+        let checkpoint = Checkpoint {
+            saved_at: SystemTime::now(),
+            started_at: self.started_at,
+            pauses: self.pauses,
+            profile: self.profile.clone(),
+            task: self.task.clone(),
+            tally: self.tally,
+            prolonging: self.prolonging,
+            timer: self.timer.snapshot(),
+        };
+        if let Err(err) = checkpoint.save(path) {
+            warn!("cannot save a checkpoint to {}: {err}", path.display());
+        }
+        self.last_checkpoint = Instant::now();
+    }
+
+    /// The settings of the profile in use.
+    fn settings(&self) -> &Config {
+        self.config
+            .profile(self.profile.as_deref())
+            .unwrap_or(&self.config)
+    }
+
+    /// Hands the timer settings of the profile in use to the timer, which
+    /// keeps a phase that has already started as it is.
+    fn apply_settings(&mut self) {
+        let settings = &self.settings().timer;
+        let (cycle, suspend) = (settings.cycle(), settings.suspend);
+        self.timer.set_cycle(cycle);
+        self.timer.set_suspend_policy(suspend);
+    }
+
+    fn reload(&mut self, config: Config) {
+        self.config = config;
+        if let Some(name) = &self.profile {
+            if self.config.profile(Some(name)).is_err() {
+                warn!("profile {name:?} is gone, going back to the default");
+                self.profile = None;
+            }
+        }
+        self.apply_settings();
+        self.save();
+    }
+
+    fn handle(&mut self, request: Request) -> Response {
+        let before = self.timer.snapshot();
+        let result = match request {
+            Request::Status => Ok(None),
+            Request::Start { profile, task } => self.start(profile, task).map(Some),
+            Request::Pause => self.timer.pause().map(Some).map_err(Refusal::from),
+            Request::Resume => self.timer.resume().map(Some).map_err(Refusal::from),
+            Request::Toggle => self.toggle().map(Some),
+            Request::Extend { by_ms } => Ok(Some(self.timer.extend(Duration::from_millis(by_ms)))),
+            Request::Stop => self.timer.stop().map(Some).map_err(Refusal::from),
+            Request::Skip => Ok(Some(self.timer.skip())),
+            Request::Reset => Ok(Some(self.timer.reset())),
+            Request::SetTask { task } => {
+                self.set_task(task);
+                Ok(None)
+            }
+            // `serve` turns the connection over to the subscription itself,
+            // so all that is left here is the status to start it with.
+            Request::Subscribe { .. } => Ok(None),
+        };
+        match result {
+            Ok(event) => {
+                if let Some(event) = event {
+                    self.on_event(event, &before);
+                }
+                Response::Ok {
+                    status: self.status(),
+                }
+            }
+            Err(Refusal { kind, message }) => Response::Error { kind, message },
+        }
+    }
+
+    fn start(&mut self, profile: Option<String>, task: Option<String>) -> Result<Event, Refusal> {
+        // Only an idle phase can start, and only then is it safe to change
+        // its length by switching profiles.
+        if let (Some(name), concentrato_core::Status::Idle) = (profile, self.timer.status()) {
+            self.config.profile(Some(&name)).map_err(|err| Refusal {
+                kind: ErrorKind::UnknownProfile,
+                message: err.to_string(),
+            })?;
+            info!("switching to profile {name:?}");
+            self.profile = Some(name);
+            self.apply_settings();
+        }
+        let event = self.timer.start()?;
+        if let Some(task) = task {
+            self.task = Some(task).filter(|task| !task.is_empty());
+        }
+        Ok(event)
+    }
+
+    /// Registers a subscriber, returning the status it starts from and where
+    /// its notifications will arrive.
+    fn subscribe(&mut self, ticks: bool) -> (Status, Receiver<Notification>) {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(Subscriber { sender, ticks });
+        (self.status(), receiver)
+    }
+
+    /// Sends a notification to the subscribers `filter` picks, forgetting
+    /// those whose connection has gone away.
+    fn notify(&mut self, notification: Notification, filter: impl Fn(&Subscriber) -> bool) {
+        self.subscribers.retain(|subscriber| {
+            !filter(subscriber) || subscriber.sender.send(notification.clone()).is_ok()
+        });
+    }
+
+    fn toggle(&mut self) -> Result<Event, Refusal> {
+        let event = match self.timer.status() {
+            concentrato_core::Status::Idle => return self.start(None, None),
+            concentrato_core::Status::Running => self.timer.pause()?,
+            concentrato_core::Status::Paused => self.timer.resume()?,
+        };
+        Ok(event)
+    }
+
+    /// Relabels the session, telling subscribers since no timer event will.
+    fn set_task(&mut self, task: String) {
+        self.task = Some(task).filter(|task| !task.is_empty());
+        let kind = EventKind::TaskChanged;
+        match &self.task {
+            Some(task) => {
+                info!(event = kind.as_str(), task = task.as_str(); "task set to {task:?}")
+            }
+            None => info!(event = kind.as_str(); "task cleared"),
+        }
+        self.save();
+        let notification = Notification::Changed {
+            event: kind,
+            phase: self.timer.phase(),
+            status: self.status(),
+        };
+        self.notify(notification, |_| true);
+    }
+
+    /// Gives the phase that just ended `by` more, logging why not if it
+    /// cannot.
+    fn prolong(&mut self, by: Duration) {
+        let before = self.timer.snapshot();
+        match self.timer.prolong(by) {
+            Ok(event) => {
+                // The session counted as completed is back under way, unless
+                // it was skipped and never counted.
+                if self.timer.completed() < before.completed {
+                    self.tally.take_back();
+                }
+                self.prolonging = self.ended.take();
+                self.on_event(event, &before);
+            }
+            Err(err) => info!("not prolonging the last phase: {err}"),
+        }
+    }
+
+    fn tick(&mut self) {
+        // Yesterday is part of the past now.
+        if self.past_day != today() {
+            self.load_past();
+        }
+        let before = self.timer.snapshot();
+        if let Some(event) = self.timer.tick() {
+            self.on_event(event, &before);
+        } else if self.timer.status() == concentrato_core::Status::Running
+            && self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL
+        {
+            // Keep the elapsed time on disk fresh, since it is all a pause
+            // or discard policy has to go on after a crash.
+            self.save();
+        }
+        if self.subscribers.iter().any(|subscriber| subscriber.ticks) {
+            let status = self.status();
+            self.notify(Notification::Tick { status }, |subscriber| subscriber.ticks);
+        }
+    }
+
+    /// Acts on what the timer did, `before` being its progress until then.
+    fn on_event(&mut self, event: Event, before: &Snapshot) {
+        let phase = event.phase();
+        let outcome = match event {
+            Event::Completed { .. } => Some(Outcome::Completed),
+            Event::Skipped { .. } => Some(Outcome::Skipped),
+            Event::Stopped { .. } | Event::Discarded { .. } | Event::Reset { .. } => {
+                Some(Outcome::Interrupted)
+            }
+            Event::Started { .. }
+            | Event::Paused { .. }
+            | Event::Resumed { .. }
+            | Event::Extended { .. } => None,
+        };
+        if let Some(outcome) = outcome {
+            let id = self.record(outcome, before);
+            // Prolonging goes back to the phase before the current one, so
+            // only a phase the timer moved on from can be taken up again.
+            self.ended = id.filter(|_| event.next().is_some());
+        }
+        match event {
+            Event::Started { .. } => {
+                self.ended = None;
+                self.pauses = 0;
+            }
+            Event::Paused { .. } => self.pauses += 1,
+            _ => {}
+        }
+        self.started_at = match event {
+            Event::Started { .. } => Some(SystemTime::now()),
+            Event::Paused { .. } | Event::Resumed { .. } | Event::Extended { .. } => {
+                self.started_at
+            }
+            Event::Stopped { .. }
+            | Event::Completed { .. }
+            | Event::Skipped { .. }
+            | Event::Discarded { .. }
+            | Event::Reset { .. } => None,
+        };
+        let message = match event {
+            Event::Started { .. } => format!("{phase} started"),
+            Event::Paused { .. } => format!("{phase} paused"),
+            Event::Resumed { .. } => format!("{phase} resumed"),
+            Event::Extended { by, .. } => format!("{phase} extended by {}", format_duration(by)),
+            Event::Stopped { .. } => format!("{phase} stopped"),
+            Event::Completed { next, .. } => format!("{phase} completed, {next} is next"),
+            Event::Skipped { next, .. } => format!("{phase} skipped, {next} is next"),
+            Event::Discarded { .. } => format!("{phase} discarded after a suspend"),
+            Event::Reset { .. } => format!("cycle reset during {phase}"),
+        };
+        // The journal keeps these as fields, to pick the timer's events out
+        // with `journalctl CONCENTRATO_EVENT=completed` and the like.
+        info!(
+            event = EventKind::from(&event).as_str(),
+            phase = phase.as_str(),
+            task = self.task.as_deref().unwrap_or_default();
+            "{message}"
+        );
+        self.save();
+        let notification = Notification::Changed {
+            event: (&event).into(),
+            phase,
+            status: self.status(),
+        };
+        self.notify(notification, |_| true);
+    }
+
+    /// Counts the phase that just ended with `outcome` towards today and
+    /// records it in the history, returning the id it is kept under. A
+    /// phase that never started is no session and is not counted.
+    fn record(&mut self, outcome: Outcome, before: &Snapshot) -> Option<i64> {
+        let prolonging = self.prolonging.take();
+        let started_at = self.started_at?;
+        // Time past the planned length, as after a late tick, is not part
+        // of the phase.
+        let actual = before.elapsed.min(before.planned);
+        if before.phase == Phase::Work {
+            self.tally.add(actual, outcome == Outcome::Completed);
+        }
+        let profile = self.profile.clone().or_else(|| self.config.profile.clone());
+        let history = self.history.as_mut()?;
+        let session = Session {
+            started_at,
+            ended_at: started_at + actual + before.paused,
+            phase: before.phase,
+            planned: before.planned,
+            actual,
+            paused: before.paused,
+            pauses: self.pauses,
+            outcome,
+            task: self.task.clone(),
+            tags: self.task.as_deref().map(history::tags).unwrap_or_default(),
+            profile,
+        };
+        let result = match prolonging {
+            Some(id) => history.carry_on(id, &session),
+            None => history.add(&session),
+        };
+        match result {
+            Ok(id) => Some(id),
+            Err(err) => {
+                warn!("cannot record the {} session: {err}", before.phase);
+                None
+            }
+        }
+    }
+
+    /// How long the ticker can sleep before the timer needs looking at.
+    ///
+    /// While a phase runs, ticks line up with the seconds of its countdown,
+    /// so that subscribers see each one go by.
+    fn next_tick(&self) -> Duration {
+        match self.timer.status() {
+            concentrato_core::Status::Running => {
+                let remaining = self.timer.remaining();
+                let fraction = Duration::from_nanos(remaining.subsec_nanos().into());
+                if fraction.is_zero() {
+                    remaining.min(TICK)
+                } else {
+                    fraction
+                }
+            }
+            _ => TICK,
+        }
+    }
+
+    fn status(&self) -> Status {
+        let so_far = self.tally.today();
+        Status {
+            phase: self.timer.phase(),
+            state: self.timer.status(),
+            planned_ms: protocol::millis(self.timer.planned()),
+            elapsed_ms: protocol::millis(self.timer.elapsed()),
+            remaining_ms: protocol::millis(self.timer.remaining()),
+            position: self.timer.position(),
+            cycle_length: self.timer.cycle().len(),
+            completed: self.timer.completed(),
+            today: so_far.pomodoros,
+            today_focus_ms: protocol::millis(so_far.focus),
+            goal: self.config.goal.daily,
+            streak: self.config.goal.streak(&self.past, today(), so_far),
+            profile: self.profile.clone().or_else(|| self.config.profile.clone()),
+            task: self.task.clone(),
+        }
+    }
+}
+
+/// A request the engine turned down, on its way to becoming a response.
+struct Refusal {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl From<TransitionError> for Refusal {
+    fn from(err: TransitionError) -> Self {
+        Self {
+            kind: ErrorKind::InvalidTransition,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Locks `mutex`, carrying on even if a thread panicked while holding it:
+/// every change to what the daemon shares leaves it consistent.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn tick(engine: &Mutex<Engine>) {
+    loop {
+        let wait = {
+            let mut engine = lock(engine);
+            engine.tick();
+            engine.next_tick()
+        };
+        // Never spin, even when a phase is due this very moment.
+        thread::sleep(wait.max(Duration::from_millis(10)));
+    }
+}
+
+fn watch_config(path: &Path, overrides: &[Override], engine: &Mutex<Engine>) {
+    let mut watcher = match ConfigWatcher::new(path) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            warn!("not watching {} for changes: {err}", path.display());
+            return;
+        }
+    };
+    loop {
+        if let Err(err) = watcher.wait() {
+            warn!("stopped watching {} for changes: {err}", path.display());
+            return;
+        }
+        match Config::load(Some(path), overrides) {
+            Ok(config) => {
+                info!("reloaded {}", path.display());
+                lock(engine).reload(config);
+            }
+            Err(err) => warn!("keeping the previous settings: {err}"),
+        }
+    }
+}
+
+fn serve(stream: UnixStream, engine: &Mutex<Engine>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match parse(&line) {
+            Ok(Request::Subscribe { ticks }) => {
+                let (status, notifications) = lock(engine).subscribe(ticks);
+                send(&mut writer, Response::Ok { status })?;
+                return stream_notifications(&mut writer, notifications);
+            }
+            Ok(request) => lock(engine).handle(request),
+            Err((kind, message)) => Response::Error { kind, message },
+        };
+        send(&mut writer, response)?;
+    }
+    Ok(())
+}
+
+/// Writes notifications to a subscribed client until it hangs up.
+fn stream_notifications(
+    writer: &mut UnixStream,
+    notifications: Receiver<Notification>,
+) -> io::Result<()> {
+    for notification in notifications {
+        send(writer, notification)?;
+    }
+    Ok(())
+}
+
+/// Writes one message to a client as a line of JSON.
+fn send<T: serde::Serialize>(writer: &mut UnixStream, message: T) -> io::Result<()> {
+    let mut line = serde_json::to_string(&Envelope::new(message))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+/// Reads a request, or the kind of error to refuse it with and why.
+fn parse(line: &str) -> Result<Request, (ErrorKind, String)> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: u32,
+    }
+
+    let bad_request = |err: serde_json::Error| (ErrorKind::BadRequest, err.to_string());
+    // The version is checked first so that a client from the future is told
+    // so, rather than that its request makes no sense.
+    let version = serde_json::from_str::<Version>(line)
+        .map_err(bad_request)?
+        .version;
+    if version != VERSION {
+        return Err((
+            ErrorKind::UnsupportedVersion,
+            format!("the daemon speaks protocol version {VERSION}, not {version}"),
+        ));
+    }
+    serde_json::from_str::<Envelope<Request>>(line)
+        .map(|envelope| envelope.message)
+        .map_err(bad_request)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use concentrato_core::{Status as State, SuspendPolicy};
+
+    use super::*;
+    use crate::config::TimerConfig;
+    use crate::daemon::testing::{Peer, TempDir};
+
+    fn engine(config: Config) -> Arc<Mutex<Engine>> {
+        Arc::new(Mutex::new(Engine::new(config, None, None)))
+    }
+
+    /// Connects a new client to `engine`, served over a socket pair.
+    fn connect(engine: &Arc<Mutex<Engine>>) -> Peer {
+        let (client, server) = UnixStream::pair().expect("a socket pair is created");
+        let engine = Arc::clone(engine);
+        thread::spawn(move || serve(server, &engine));
+        Peer::new(client)
+    }
+
+    /// Sends `command` with the fields in `rest`, returning the response.
+    fn request(peer: &mut Peer, command: &str, rest: &str) -> Response {
+        peer.send(&format!(
+            r#"{{"version":{VERSION},"command":"{command}"{rest}}}"#
+        ));
+        let envelope: Envelope<Response> = peer.receive().expect("the daemon responds");
+        assert_eq!(envelope.version, VERSION);
+        envelope.message
+    }
+
+    fn ok(response: Response) -> Status {
+        match response {
+            Response::Ok { status } => status,
+            other => panic!("expected a status, got {other:?}"),
+        }
+    }
+
+    fn refused(response: Response) -> ErrorKind {
+        match response {
+            Response::Error { kind, .. } => kind,
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn checks_the_version_before_the_request() {
+        let (kind, message) = parse(r#"{"version":2,"command":"launch"}"#).unwrap_err();
+        assert_eq!(kind, ErrorKind::UnsupportedVersion);
+        assert_eq!(message, "the daemon speaks protocol version 1, not 2");
+
+        for line in [
+            "not json",
+            r#"{"command":"status"}"#,
+            r#"{"version":"1","command":"status"}"#,
+            r#"{"version":1,"command":"launch"}"#,
+            r#"{"version":1,"command":"extend"}"#,
+        ] {
+            let (kind, _) = parse(line).unwrap_err();
+            assert_eq!(kind, ErrorKind::BadRequest, "{line}");
+        }
+
+        assert_eq!(
+            parse(r#"{"version":1,"command":"extend","by_ms":60000}"#),
+            Ok(Request::Extend { by_ms: 60_000 })
+        );
+    }
+
+    #[test]
+    fn refuses_bad_requests_and_carries_on() {
+        let engine = engine(Config::default());
+        let mut peer = connect(&engine);
+
+        peer.send("not json");
+        let envelope: Envelope<Response> = peer.receive().expect("the daemon responds");
+        assert_eq!(refused(envelope.message), ErrorKind::BadRequest);
+
+        peer.send(r#"{"version":99,"command":"start"}"#);
+        let envelope: Envelope<Response> = peer.receive().expect("the daemon responds");
+        assert_eq!(refused(envelope.message), ErrorKind::UnsupportedVersion);
+        assert_eq!(lock(&engine).timer.status(), State::Idle);
+
+        // Blank lines go unanswered, and the connection is still good.
+        peer.send("");
+        assert_eq!(ok(request(&mut peer, "status", "")).state, State::Idle);
+    }
+
+    #[test]
+    fn changes_the_timer_on_each_command() {
+        let engine = engine(Config::default());
+        let mut peer = connect(&engine);
+
+        let status = ok(request(&mut peer, "status", ""));
+        assert_eq!((status.phase, status.state), (Phase::Work, State::Idle));
+        assert_eq!(status.planned_ms, 25 * 60_000);
+
+        let status = ok(request(&mut peer, "start", r#","task":"write tests""#));
+        assert_eq!(status.state, State::Running);
+        assert_eq!(status.task.as_deref(), Some("write tests"));
+        assert_eq!(ok(request(&mut peer, "pause", "")).state, State::Paused);
+        assert_eq!(
+            refused(request(&mut peer, "pause", "")),
+            ErrorKind::InvalidTransition
+        );
+        assert_eq!(ok(request(&mut peer, "resume", "")).state, State::Running);
+        assert_eq!(ok(request(&mut peer, "toggle", "")).state, State::Paused);
+        assert_eq!(ok(request(&mut peer, "toggle", "")).state, State::Running);
+
+        let status = ok(request(&mut peer, "extend", r#","by_ms":60000"#));
+        assert_eq!(status.planned_ms, 26 * 60_000);
+        let status = ok(request(&mut peer, "set_task", r#","task":"review""#));
+        assert_eq!(status.task.as_deref(), Some("review"));
+
+        let status = ok(request(&mut peer, "stop", ""));
+        assert_eq!((status.phase, status.state), (Phase::Work, State::Idle));
+        assert_eq!(
+            refused(request(&mut peer, "stop", "")),
+            ErrorKind::InvalidTransition
+        );
+
+        let status = ok(request(&mut peer, "skip", ""));
+        assert_eq!(
+            (status.phase, status.state),
+            (Phase::ShortBreak, State::Idle)
+        );
+        assert_eq!(status.position, 1);
+        assert_eq!(ok(request(&mut peer, "toggle", "")).state, State::Running);
+
+        let status = ok(request(&mut peer, "reset", ""));
+        assert_eq!((status.phase, status.state), (Phase::Work, State::Idle));
+        assert_eq!((status.position, status.completed), (0, 0));
+        assert_eq!(lock(&engine).timer.status(), State::Idle);
+    }
+
+    #[test]
+    fn starts_with_a_profile_only_if_it_exists() {
+        let dir = TempDir::new();
+        let path = dir.join("config.toml");
+        fs::write(&path, "[profiles.deep.timer]\nwork = 90\n").expect("the config is written");
+        let config = Config::load(Some(&path), &[]).expect("the config is valid");
+        let engine = engine(config);
+        let mut peer = connect(&engine);
+
+        let response = request(&mut peer, "start", r#","profile":"shallow""#);
+        assert_eq!(refused(response), ErrorKind::UnknownProfile);
+        assert_eq!(lock(&engine).timer.status(), State::Idle);
+
+        let status = ok(request(&mut peer, "start", r#","profile":"deep""#));
+        assert_eq!(status.state, State::Running);
+        assert_eq!(status.profile.as_deref(), Some("deep"));
+        assert_eq!(status.planned_ms, 90 * 60_000);
+    }
+
+    #[test]
+    fn streams_changes_to_subscribers() {
+        let engine = engine(Config::default());
+        let mut subscriber = connect(&engine);
+        let status = ok(request(&mut subscriber, "subscribe", ""));
+        assert_eq!(status.state, State::Idle);
+
+        let mut peer = connect(&engine);
+        ok(request(&mut peer, "start", ""));
+        let envelope: Envelope<Notification> = subscriber.receive().expect("the daemon notifies");
+        match envelope.message {
+            Notification::Changed {
+                event,
+                phase,
+                status,
+            } => {
+                assert_eq!((event, phase), (EventKind::Started, Phase::Work));
+                assert_eq!(status.state, State::Running);
+            }
+            other => panic!("expected a change, got {other:?}"),
+        }
+
+        ok(request(&mut peer, "set_task", r#","task":"notes""#));
+        let envelope: Envelope<Notification> = subscriber.receive().expect("the daemon notifies");
+        assert!(matches!(
+            envelope.message,
+            Notification::Changed {
+                event: EventKind::TaskChanged,
+                ..
+            }
+        ));
+
+        // Only those who asked for them hear the ticks.
+        let mut ticking = connect(&engine);
+        ok(request(&mut ticking, "subscribe", r#","ticks":true"#));
+        lock(&engine).tick();
+        let envelope: Envelope<Notification> = ticking.receive().expect("the daemon ticks");
+        assert!(matches!(envelope.message, Notification::Tick { .. }));
+        ok(request(&mut peer, "pause", ""));
+        let envelope: Envelope<Notification> = subscriber.receive().expect("the daemon notifies");
+        assert!(matches!(
+            envelope.message,
+            Notification::Changed {
+                event: EventKind::Paused,
+                ..
+            }
+        ));
+    }
+
+    /// An engine picking up a checkpoint saved `downtime` ago, ten minutes
+    /// into a work session that was `state`, under `suspend`.
+    fn restored(dir: &TempDir, suspend: SuspendPolicy, state: State, downtime: Duration) -> Engine {
+        let path = dir.join("checkpoint.json");
+        let saved_at = SystemTime::now() - downtime;
+        let checkpoint = Checkpoint {
+            saved_at,
+            started_at: Some(saved_at - Duration::from_secs(10 * 60)),
+            pauses: 0,
+            profile: None,
+            task: Some("write".to_owned()),
+            tally: Tally::default(),
+            prolonging: None,
+            timer: Snapshot {
+                position: 0,
+                phase: Phase::Work,
+                status: state,
+                planned: Duration::from_secs(25 * 60),
+                elapsed: Duration::from_secs(10 * 60),
+                paused: Duration::ZERO,
+                completed: 0,
+                previous_completed: false,
+            },
+        };
+        checkpoint.save(&path).expect("the checkpoint is saved");
+        let config = Config {
+            timer: TimerConfig {
+                suspend,
+                ..TimerConfig::default()
+            },
+            ..Config::default()
+        };
+        let mut engine = Engine::new(config, Some(path), None);
+        engine.restore();
+        engine
+    }
+
+    /// What the engine saved last, as a timer status and elapsed minutes.
+    fn saved(dir: &TempDir) -> (State, u64) {
+        let checkpoint = Checkpoint::load(&dir.join("checkpoint.json"))
+            .expect("the checkpoint is readable")
+            .expect("the checkpoint is there");
+        (
+            checkpoint.timer.status,
+            checkpoint.timer.elapsed.as_secs() / 60,
+        )
+    }
+
+    const DOWNTIME: Duration = Duration::from_secs(5 * 60);
+
+    #[test]
+    fn counts_downtime_towards_a_running_phase() {
+        let dir = TempDir::new();
+        let engine = restored(&dir, SuspendPolicy::Count, State::Running, DOWNTIME);
+        let status = engine.status();
+        assert_eq!((status.phase, status.state), (Phase::Work, State::Running));
+        assert_eq!(status.elapsed_ms / 60_000, 15);
+        assert_eq!(status.task.as_deref(), Some("write"));
+        assert_eq!(saved(&dir), (State::Running, 15));
+    }
+
+    #[test]
+    fn completes_a_phase_that_ran_out_during_downtime() {
+        let dir = TempDir::new();
+        let downtime = Duration::from_secs(60 * 60);
+        let mut engine = restored(&dir, SuspendPolicy::Count, State::Running, downtime);
+        engine.tick();
+        let status = engine.status();
+        assert_eq!(
+            (status.phase, status.state),
+            (Phase::ShortBreak, State::Idle)
+        );
+        assert_eq!((status.completed, status.today), (1, 1));
+    }
+
+    #[test]
+    fn pauses_a_running_phase_through_downtime() {
+        let dir = TempDir::new();
+        let engine = restored(&dir, SuspendPolicy::Pause, State::Running, DOWNTIME);
+        let status = engine.status();
+        assert_eq!((status.phase, status.state), (Phase::Work, State::Paused));
+        assert_eq!(status.elapsed_ms / 60_000, 10);
+        assert_eq!(engine.pauses, 1);
+        assert_eq!(saved(&dir), (State::Paused, 10));
+    }
+
+    #[test]
+    fn discards_a_running_phase_after_downtime() {
+        let dir = TempDir::new();
+        let engine = restored(&dir, SuspendPolicy::Discard, State::Running, DOWNTIME);
+        let status = engine.status();
+        assert_eq!((status.phase, status.state), (Phase::Work, State::Idle));
+        assert_eq!(status.elapsed_ms, 0);
+        assert_eq!(engine.started_at, None);
+        assert_eq!(saved(&dir), (State::Idle, 0));
+    }
+
+    #[test]
+    fn keeps_a_paused_phase_paused_whatever_the_policy() {
+        for suspend in [SuspendPolicy::Count, SuspendPolicy::Discard] {
+            let dir = TempDir::new();
+            let engine = restored(&dir, suspend, State::Paused, DOWNTIME);
+            let status = engine.status();
+            assert_eq!(status.state, State::Paused, "{suspend:?}");
+            assert_eq!(status.elapsed_ms / 60_000, 10, "{suspend:?}");
+            assert!(engine.timer.snapshot().paused >= DOWNTIME, "{suspend:?}");
+        }
+    }
+
+    #[test]
+    fn takes_back_only_a_completed_session_when_prolonging() {
+        let dir = TempDir::new();
+        let hour = Duration::from_secs(60 * 60);
+        let mut engine = restored(&dir, SuspendPolicy::Count, State::Running, hour);
+        engine.tick();
+        let counts = |engine: &Engine| {
+            let status = engine.status();
+            (status.phase, status.completed, status.today)
+        };
+        assert_eq!(counts(&engine), (Phase::ShortBreak, 1, 1));
+
+        engine.prolong(Duration::from_secs(5 * 60));
+        assert_eq!(counts(&engine), (Phase::Work, 0, 0));
+        engine.handle(Request::Skip);
+        engine.handle(Request::Skip);
+        engine.handle(Request::Start {
+            profile: None,
+            task: None,
+        });
+        engine.handle(Request::Skip);
+        assert_eq!(counts(&engine), (Phase::ShortBreak, 0, 0));
+
+        // The work session was skipped, so there is nothing to take back.
+        let mut engine = restored(&dir, SuspendPolicy::Count, State::Running, hour);
+        engine.tick();
+        engine.handle(Request::Skip);
+        engine.handle(Request::Start {
+            profile: None,
+            task: None,
+        });
+        engine.handle(Request::Skip);
+        assert_eq!(counts(&engine), (Phase::ShortBreak, 1, 1));
+        engine.prolong(Duration::from_secs(5 * 60));
+        assert_eq!(counts(&engine), (Phase::Work, 1, 1));
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs::{self, DirBuilder, File};
+use std::io::{self, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::Path;
+use std::time::SystemTime;
+
+use concentrato_core::Snapshot;
+use serde::{Deserialize, Serialize};
+
+use super::Tally;
+
+/// The daemon's progress as saved to disk, so that a crash or reboot does not
+/// lose the session in flight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    /// When the checkpoint was written.
+    pub(crate) saved_at: SystemTime,
+    /// When the current phase was first started, if it has been.
+    pub(crate) started_at: Option<SystemTime>,
+    /// How many times the current phase has been paused.
+    #[serde(default)]
+    pub(crate) pauses: u32,
+    pub(crate) profile: Option<String>,
+    #[serde(default)]
+    pub(crate) task: Option<String>,
+    #[serde(default)]
+    pub(crate) tally: Tally,
+    /// The history record the current phase carries on from, if it was
+    /// prolonged.
+    #[serde(default)]
+    pub(crate) prolonging: Option<i64>,
+    pub(crate) timer: Snapshot,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at `path`, if there is one.
+    pub(crate) fn load(path: &Path) -> io::Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes the checkpoint to `path` so that, whenever the process dies,
+    /// the file holds either this checkpoint or the previous one in full.
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        let temporary = path.with_extension("tmp");
+        let mut file = File::create(&temporary)?;
+        serde_json::to_writer(&mut file, self)?;
+        file.write_all(b"\n")?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)?;
+        if let Some(dir) = path.parent() {
+            // Make the rename itself durable.
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use concentrato_core::{Phase, Status};
+
+    use super::*;
+    use crate::daemon::testing::TempDir;
+
+    fn checkpoint(task: &str) -> Checkpoint {
+        let saved_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_760_000_000);
+        Checkpoint {
+            saved_at,
+            started_at: Some(saved_at - Duration::from_secs(600)),
+            pauses: 1,
+            profile: Some("deep".to_owned()),
+            task: Some(task.to_owned()),
+            tally: Tally::default(),
+            prolonging: Some(7),
+            timer: Snapshot {
+                position: 2,
+                phase: Phase::Work,
+                status: Status::Running,
+                planned: Duration::from_secs(25 * 60),
+                elapsed: Duration::from_millis(9 * 60_000 + 1),
+                paused: Duration::from_secs(60),
+                completed: 1,
+                previous_completed: true,
+            },
+        }
+    }
+
+    #[test]
+    fn loads_what_was_saved() {
+        let dir = TempDir::new();
+        let path = dir.join("state").join("checkpoint.json");
+        assert!(Checkpoint::load(&path).unwrap().is_none());
+
+        checkpoint("write").save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint("write")));
+        checkpoint("review").save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint("review")));
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn keeps_the_last_checkpoint_through_a_torn_write() {
+        let dir = TempDir::new();
+        let path = dir.join("checkpoint.json");
+        checkpoint("write").save(&path).unwrap();
+
+        // A daemon that died mid-save leaves half a checkpoint beside the
+        // whole one, which is read as if nothing happened.
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, r#"{"saved_at":{"secs_since_epoch":17"#).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint("write")));
+
+        // The next save writes over it rather than after it.
+        checkpoint("review").save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint("review")));
+        assert!(!temporary.exists());
+    }
+
+    #[test]
+    fn refuses_a_corrupt_checkpoint() {
+        let dir = TempDir::new();
+        let path = dir.join("checkpoint.json");
+        fs::write(&path, "{}").unwrap();
+        let err = Checkpoint::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
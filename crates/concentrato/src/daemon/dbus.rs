@@ -0,0 +1,518 @@
+//! The timer as a service on the D-Bus session bus.
+//!
+//! The daemon owns the name `org.concentrato.Timer` and serves the
+//! interface of the same name at `/org/concentrato/Timer`, alongside its
+//! socket. Methods make the same requests the protocol does, properties
+//! report the status, and signals announce ticks and phase changes, so
+//! desktop shells can integrate without speaking the socket protocol.
+//! `docs/dbus.md` describes the interface.
+//!
+//! Without a session bus, or if another daemon already owns the name, the
+//! daemon carries on without the service.
+
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use concentrato_core::Status as State;
+use log::{debug, info, warn};
+use zbus::blocking::object_server::InterfaceRef;
+use zbus::blocking::{Connection, connection};
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+
+use super::{Engine, lock};
+use crate::protocol::{ErrorKind, EventKind, Notification, Request, Response, Status};
+
+const NAME: &str = "org.concentrato.Timer";
+const PATH: &str = "/org/concentrato/Timer";
+
+/// Starts serving `engine` on the session bus in the background.
+pub(super) fn spawn(engine: Arc<Mutex<Engine>>) {
+    thread::spawn(move || {
+        if let Some((connection, notifications)) = serve(connection::Builder::session(), &engine) {
+            announce_all(&connection, notifications);
+        }
+    });
+}
+
+/// Serves `engine` on the bus `builder` connects to, returning the
+/// connection and the notifications to announce on it.
+fn serve(
+    builder: zbus::Result<connection::Builder<'_>>,
+    engine: &Arc<Mutex<Engine>>,
+) -> Option<(Connection, Receiver<Notification>)> {
+    let service = Service {
+        engine: Arc::clone(engine),
+    };
+    let connection = match builder
+        .and_then(|builder| builder.name(NAME))
+        .and_then(|builder| builder.serve_at(PATH, service))
+        .and_then(|builder| builder.build())
+    {
+        Ok(connection) => connection,
+        Err(err) => {
+            info!("not on the session bus: {err}");
+            return None;
+        }
+    };
+    info!("serving {NAME} on the session bus");
+    let (_, notifications) = lock(engine).subscribe(true);
+    Some((connection, notifications))
+}
+
+/// Sends the signals that go with each of `notifications`, until the
+/// engine goes away.
+fn announce_all(connection: &Connection, notifications: Receiver<Notification>) {
+    let service = match connection.object_server().interface::<_, Service>(PATH) {
+        Ok(service) => service,
+        Err(err) => {
+            warn!("cannot send D-Bus signals: {err}");
+            return;
+        }
+    };
+    for notification in notifications {
+        if let Err(err) = announce(&service, &notification) {
+            debug!("cannot send a D-Bus signal: {err}");
+        }
+    }
+}
+
+/// Sends the signals that go with `notification`.
+fn announce(service: &InterfaceRef<Service>, notification: &Notification) -> zbus::Result<()> {
+    let emitter = service.signal_emitter();
+    zbus::block_on(async {
+        match notification {
+            Notification::Tick { status } => {
+                if status.state == State::Running {
+                    Service::tick(emitter, status.remaining_ms).await?;
+                }
+            }
+            Notification::Changed {
+                event,
+                phase,
+                status,
+            } => {
+                // Every event that ends a phase, even one that leaves the
+                // timer on the same phase, so that listeners never miss the
+                // phase they were showing going away.
+                if matches!(
+                    event,
+                    EventKind::Completed
+                        | EventKind::Skipped
+                        | EventKind::Stopped
+                        | EventKind::Discarded
+                        | EventKind::Reset
+                ) {
+                    Service::phase_ended(
+                        emitter,
+                        phase.as_str(),
+                        status.phase.as_str(),
+                        event.as_str(),
+                    )
+                    .await?;
+                }
+                let service = service.get();
+                service.phase_changed(emitter).await?;
+                service.state_changed(emitter).await?;
+                service.planned_changed(emitter).await?;
+                service.cycle_index_changed(emitter).await?;
+                service.cycle_length_changed(emitter).await?;
+                service.completed_changed(emitter).await?;
+                service.task_changed(emitter).await?;
+                service.profile_changed(emitter).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+struct Service {
+    engine: Arc<Mutex<Engine>>,
+}
+
+impl Service {
+    fn request(&self, request: Request) -> Result<(), ServiceError> {
+        match lock(&self.engine).handle(request) {
+            Response::Ok { .. } => Ok(()),
+            Response::Error { kind, message } => Err(ServiceError::from_kind(kind, message)),
+        }
+    }
+
+    fn status(&self) -> Status {
+        lock(&self.engine).status()
+    }
+}
+
+#[interface(name = "org.concentrato.Timer")]
+impl Service {
+    /// Starts the current phase.
+    fn start(&self) -> Result<(), ServiceError> {
+        self.request(Request::Start {
+            profile: None,
+            task: None,
+        })
+    }
+
+    /// Starts the current phase, switching to `profile` first and labelling
+    /// the session `task`; empty strings leave either as they are.
+    fn start_with(&self, profile: &str, task: &str) -> Result<(), ServiceError> {
+        let given = |s: &str| Some(s.to_owned()).filter(|s| !s.is_empty());
+        self.request(Request::Start {
+            profile: given(profile),
+            task: given(task),
+        })
+    }
+
+    fn pause(&self) -> Result<(), ServiceError> {
+        self.request(Request::Pause)
+    }
+
+    fn resume(&self) -> Result<(), ServiceError> {
+        self.request(Request::Resume)
+    }
+
+    fn toggle(&self) -> Result<(), ServiceError> {
+        self.request(Request::Toggle)
+    }
+
+    /// Makes the current phase `seconds` longer.
+    fn extend(&self, seconds: u32) -> Result<(), ServiceError> {
+        self.request(Request::Extend {
+            by_ms: u64::from(seconds) * 1000,
+        })
+    }
+
+    fn stop(&self) -> Result<(), ServiceError> {
+        self.request(Request::Stop)
+    }
+
+    fn skip(&self) -> Result<(), ServiceError> {
+        self.request(Request::Skip)
+    }
+
+    fn reset(&self) -> Result<(), ServiceError> {
+        self.request(Request::Reset)
+    }
+
+    /// `work`, `short_break` or `long_break`.
+    #[zbus(property)]
+    fn phase(&self) -> String {
+        self.status().phase.to_string()
+    }
+
+    /// `idle`, `running` or `paused`.
+    #[zbus(property)]
+    fn state(&self) -> String {
+        self.status().state.to_string()
+    }
+
+    /// Milliseconds the phase is meant to last.
+    #[zbus(property)]
+    fn planned(&self) -> u64 {
+        self.status().planned_ms
+    }
+
+    /// Milliseconds left, which changes too often to be announced; the
+    /// `Tick` signal carries it instead.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn remaining(&self) -> u64 {
+        self.status().remaining_ms
+    }
+
+    /// Milliseconds the phase has run, excluding pauses; like `Remaining`,
+    /// it is not announced.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn elapsed(&self) -> u64 {
+        self.status().elapsed_ms
+    }
+
+    /// The phase's index within the cycle.
+    #[zbus(property)]
+    fn cycle_index(&self) -> u32 {
+        u32::try_from(self.status().position).unwrap_or(u32::MAX)
+    }
+
+    #[zbus(property)]
+    fn cycle_length(&self) -> u32 {
+        u32::try_from(self.status().cycle_length).unwrap_or(u32::MAX)
+    }
+
+    /// Work sessions completed since the daemon started or was reset.
+    #[zbus(property)]
+    fn completed(&self) -> u32 {
+        self.status().completed
+    }
+
+    /// What the session is for, or an empty string.
+    #[zbus(property)]
+    fn task(&self) -> String {
+        self.status().task.unwrap_or_default()
+    }
+
+    /// The profile in use, or an empty string for the plain settings.
+    #[zbus(property)]
+    fn profile(&self) -> String {
+        self.status().profile.unwrap_or_default()
+    }
+
+    /// `phase` ended for `reason`, which is `completed`, `skipped`,
+    /// `stopped`, `discarded` or `reset`, and `next` is the phase that
+    /// follows. After a stop or discard, and after some resets, `next` is
+    /// `phase` again, starting over.
+    // zbus names the notifier of the `Phase` property `phase_changed`, so
+    // this cannot have the Rust name that matches its wire name.
+    #[zbus(signal, name = "PhaseChanged")]
+    async fn phase_ended(
+        emitter: &SignalEmitter<'_>,
+        phase: &str,
+        next: &str,
+        reason: &str,
+    ) -> zbus::Result<()>;
+
+    /// Another second of a running phase went by.
+    #[zbus(signal)]
+    async fn tick(emitter: &SignalEmitter<'_>, remaining_ms: u64) -> zbus::Result<()>;
+}
+
+/// The errors methods return, named after the protocol's.
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "org.concentrato.Timer.Error")]
+enum ServiceError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    BadRequest(String),
+    UnsupportedVersion(String),
+    InvalidTransition(String),
+    UnknownProfile(String),
+}
+
+impl ServiceError {
+    fn from_kind(kind: ErrorKind, message: String) -> Self {
+        match kind {
+            ErrorKind::BadRequest => ServiceError::BadRequest(message),
+            ErrorKind::UnsupportedVersion => ServiceError::UnsupportedVersion(message),
+            ErrorKind::InvalidTransition => ServiceError::InvalidTransition(message),
+            ErrorKind::UnknownProfile => ServiceError::UnknownProfile(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use zbus::blocking::Proxy;
+    use zbus::blocking::proxy::Builder;
+    use zbus::proxy::CacheProperties;
+    use zbus::zvariant::{OwnedValue, Str, Type};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::daemon::testing::{Bus, eventually};
+
+    /// Serves a fresh engine on `bus`, returning it and a client's proxy for
+    /// the service, which reads properties afresh every time.
+    fn serving(bus: &Bus) -> (Arc<Mutex<Engine>>, Proxy<'static>) {
+        let engine = Arc::new(Mutex::new(Engine::new(Config::default(), None, None)));
+        let (connection, notifications) =
+            serve(Ok(bus.builder()), &engine).expect("the service starts");
+        thread::spawn(move || announce_all(&connection, notifications));
+        (engine, client(bus, NAME))
+    }
+
+    fn client(bus: &Bus, interface: &'static str) -> Proxy<'static> {
+        Builder::new(&bus.connect())
+            .destination(NAME)
+            .and_then(|builder| builder.path(PATH))
+            .and_then(|builder| builder.interface(interface))
+            .map(|builder| builder.cache_properties(CacheProperties::No))
+            .and_then(|builder| builder.build())
+            .expect("the proxy is created")
+    }
+
+    fn call(proxy: &Proxy<'_>, method: &str) -> zbus::Result<()> {
+        proxy.call(method, &())
+    }
+
+    /// The name of the error `result` failed with.
+    fn error(result: zbus::Result<()>) -> String {
+        match result {
+            Err(zbus::Error::MethodError(name, _, _)) => name.to_string(),
+            other => panic!("expected a method error, got {other:?}"),
+        }
+    }
+
+    fn state(engine: &Mutex<Engine>) -> State {
+        lock(engine).timer.status()
+    }
+
+    /// The bodies of the `signal`s `proxy` receives from now on.
+    fn listen<T>(proxy: &Proxy<'static>, signal: &'static str) -> mpsc::Receiver<T>
+    where
+        T: for<'d> serde::Deserialize<'d> + Type + Send + 'static,
+    {
+        let signals = proxy
+            .receive_signal(signal)
+            .expect("the match rule is added");
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for signal in signals {
+                let body = signal
+                    .body()
+                    .deserialize()
+                    .expect("the signal is well formed");
+                if sender.send(body).is_err() {
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+
+    fn next<T>(signals: &mpsc::Receiver<T>) -> T {
+        signals
+            .recv_timeout(Duration::from_secs(5))
+            .expect("the signal arrives")
+    }
+
+    #[test]
+    fn methods_drive_the_timer() {
+        let Some(bus) = Bus::start() else { return };
+        let (engine, proxy) = serving(&bus);
+
+        call(&proxy, "Start").unwrap();
+        assert_eq!(state(&engine), State::Running);
+        assert_eq!(
+            error(call(&proxy, "Start")),
+            "org.concentrato.Timer.Error.InvalidTransition"
+        );
+        call(&proxy, "Pause").unwrap();
+        assert_eq!(state(&engine), State::Paused);
+        call(&proxy, "Resume").unwrap();
+        assert_eq!(state(&engine), State::Running);
+        call(&proxy, "Toggle").unwrap();
+        assert_eq!(state(&engine), State::Paused);
+        call(&proxy, "Toggle").unwrap();
+        assert_eq!(state(&engine), State::Running);
+        proxy.call::<_, _, ()>("Extend", &60u32).unwrap();
+        assert_eq!(lock(&engine).timer.planned(), Duration::from_secs(26 * 60));
+        call(&proxy, "Stop").unwrap();
+        assert_eq!(state(&engine), State::Idle);
+        assert_eq!(
+            error(call(&proxy, "Stop")),
+            "org.concentrato.Timer.Error.InvalidTransition"
+        );
+        call(&proxy, "Skip").unwrap();
+        assert_eq!(lock(&engine).timer.position(), 1);
+        call(&proxy, "Reset").unwrap();
+        assert_eq!(lock(&engine).timer.position(), 0);
+
+        assert_eq!(
+            error(proxy.call("StartWith", &("nap", ""))),
+            "org.concentrato.Timer.Error.UnknownProfile"
+        );
+        assert_eq!(state(&engine), State::Idle);
+        proxy.call::<_, _, ()>("StartWith", &("", "write")).unwrap();
+        assert_eq!(state(&engine), State::Running);
+        assert_eq!(lock(&engine).task.as_deref(), Some("write"));
+    }
+
+    #[test]
+    fn properties_report_the_status() {
+        let Some(bus) = Bus::start() else { return };
+        let (_engine, proxy) = serving(&bus);
+        let string = |name| proxy.get_property::<String>(name).unwrap();
+        let number = |name| proxy.get_property::<u32>(name).unwrap();
+        let millis = |name| proxy.get_property::<u64>(name).unwrap();
+
+        assert_eq!(string("Phase"), "work");
+        assert_eq!(string("State"), "idle");
+        assert_eq!(millis("Planned"), 25 * 60_000);
+        assert_eq!(millis("Remaining"), 25 * 60_000);
+        assert_eq!(millis("Elapsed"), 0);
+        assert_eq!(number("CycleIndex"), 0);
+        assert_eq!(number("CycleLength"), 8);
+        assert_eq!(string("Task"), "");
+
+        call(&proxy, "Skip").unwrap();
+        assert_eq!(string("Phase"), "short_break");
+        assert_eq!(number("CycleIndex"), 1);
+        assert_eq!(millis("Remaining"), 5 * 60_000);
+
+        proxy.call::<_, _, ()>("StartWith", &("", "write")).unwrap();
+        assert_eq!(string("State"), "running");
+        assert_eq!(string("Task"), "write");
+        eventually("time to pass", || millis("Remaining") < 5 * 60_000);
+        assert!(millis("Elapsed") > 0);
+    }
+
+    #[test]
+    fn announces_ticks_while_running() {
+        let Some(bus) = Bus::start() else { return };
+        let (engine, proxy) = serving(&bus);
+        let ticks = listen::<u64>(&proxy, "Tick");
+
+        lock(&engine).tick();
+        call(&proxy, "Start").unwrap();
+        lock(&engine).tick();
+        let remaining = next(&ticks);
+        assert!(remaining > 0 && remaining <= 25 * 60_000);
+        call(&proxy, "Pause").unwrap();
+        lock(&engine).tick();
+        call(&proxy, "Resume").unwrap();
+        lock(&engine).tick();
+        // The ticks of an idle or paused timer were never sent.
+        assert!(next(&ticks) <= remaining);
+        assert!(ticks.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn announces_phase_changes() {
+        let Some(bus) = Bus::start() else { return };
+        let (_engine, proxy) = serving(&bus);
+        let changes = listen::<(String, String, String)>(&proxy, "PhaseChanged");
+        let properties = listen::<(String, HashMap<String, OwnedValue>, Vec<String>)>(
+            &client(&bus, "org.freedesktop.DBus.Properties"),
+            "PropertiesChanged",
+        );
+        let change = |phase: &str, next: &str, reason: &str| {
+            (phase.to_owned(), next.to_owned(), reason.to_owned())
+        };
+        // Each change to the timer updates the properties it announces, one
+        // signal apiece. They carry the status as it is when they are sent,
+        // so each change is waited for before the next is made.
+        let announced = |count| {
+            let mut all = HashMap::new();
+            for _ in 0..count {
+                let (interface, changed, _) = next(&properties);
+                assert_eq!(interface, NAME);
+                all.extend(changed);
+            }
+            all
+        };
+        let text = |text: &str| OwnedValue::from(Str::from(text.to_owned()));
+
+        call(&proxy, "Start").unwrap();
+        let started = announced(8);
+        assert_eq!(started["Phase"], text("work"));
+        assert_eq!(started["State"], text("running"));
+        assert!(!started.contains_key("Remaining"));
+        call(&proxy, "Skip").unwrap();
+        assert_eq!(next(&changes), change("work", "short_break", "skipped"));
+        let skipped = announced(8);
+        assert_eq!(skipped["Phase"], text("short_break"));
+        assert_eq!(skipped["CycleIndex"], OwnedValue::from(1u32));
+        call(&proxy, "Start").unwrap();
+        call(&proxy, "Stop").unwrap();
+        assert_eq!(
+            next(&changes),
+            change("short_break", "short_break", "stopped")
+        );
+        call(&proxy, "Reset").unwrap();
+        assert_eq!(next(&changes), change("short_break", "work", "reset"));
+        assert!(changes.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+}
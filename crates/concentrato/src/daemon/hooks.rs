@@ -0,0 +1,373 @@
+//! Commands the user runs on the timer's events.
+//!
+//! Each command is run by `sh -c` with the event's details in
+//! `CONCENTRATO_*` environment variables, and as a line of JSON on its
+//! standard input for anything that needs more. Hooks run one at a time, in
+//! the order of the events, so that a hook for one event never overtakes the
+//! hook for the one before; the timeout keeps a stuck one from holding up the
+//! rest. What they print is logged.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::iter;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use concentrato_core::Phase;
+use log::{debug, info, warn};
+use serde_json::json;
+
+use super::{Engine, lock};
+use crate::config::HookEvent;
+use crate::protocol::{self, EventKind, Notification, Status};
+
+/// How often a running hook is checked on.
+const POLL: Duration = Duration::from_millis(20);
+/// How long to keep reading what a hook printed once it has exited, which
+/// only takes long if it left something running in the background.
+const LINGER: Duration = Duration::from_millis(100);
+
+/// Starts running hooks for `engine` in the background.
+pub(super) fn spawn(engine: Arc<Mutex<Engine>>) {
+    thread::spawn(move || {
+        let (_, notifications) = lock(&engine).subscribe(false);
+        for notification in notifications {
+            let Notification::Changed {
+                event,
+                phase,
+                status,
+            } = notification
+            else {
+                continue;
+            };
+            let Some(hook) = hook_event(event, phase) else {
+                continue;
+            };
+            let settings = lock(&engine).settings().hooks.clone();
+            for command in settings.commands(hook) {
+                run(command, hook, phase, &status, settings.timeout);
+            }
+        }
+    });
+}
+
+/// The hook event for a timer event in `phase`.
+fn hook_event(event: EventKind, phase: Phase) -> Option<HookEvent> {
+    Some(match (event, phase.is_break()) {
+        (EventKind::Started, false) => HookEvent::WorkStart,
+        (EventKind::Started, true) => HookEvent::BreakStart,
+        (EventKind::Completed, false) => HookEvent::WorkEnd,
+        (EventKind::Completed, true) => HookEvent::BreakEnd,
+        (EventKind::Paused, _) => HookEvent::Pause,
+        (EventKind::Resumed, _) => HookEvent::Resume,
+        (EventKind::Skipped, _) => HookEvent::Skip,
+        (EventKind::Extended, _) => HookEvent::Extend,
+        (EventKind::Stopped, _) => HookEvent::Stop,
+        (EventKind::Reset, _) => HookEvent::Reset,
+        (EventKind::Discarded | EventKind::TaskChanged, _) => return None,
+    })
+}
+
+/// Runs one hook command for `event`, which happened in `phase` and left
+/// the timer at `status`.
+fn run(command: &str, event: HookEvent, phase: Phase, status: &Status, timeout: Duration) {
+    debug!("running {} hook: {command}", event.as_str());
+    let input = json!({
+        "event": event.as_str(),
+        "phase": phase,
+        "status": status,
+    });
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(environment(event, phase, status))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // In a process group of its own, so that a timeout kills whatever
+        // the hook started as well.
+        .process_group(0)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            warn!("cannot run {} hook {command:?}: {err}", event.as_str());
+            return;
+        }
+    };
+    // Hooks that do not read their input are fine, so a closed pipe is no
+    // error.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{input}");
+    }
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let result = wait(&mut child, timeout);
+    let (stdout, stderr) = (output(stdout), output(stderr));
+    for line in &stdout {
+        info!("{} hook: {line}", event.as_str());
+    }
+    match result {
+        Ok(Some(status)) if status.success() => {}
+        Ok(Some(status)) => {
+            warn!("{} hook {command:?} failed: {status}", event.as_str());
+            for line in &stderr {
+                warn!("{} hook: {line}", event.as_str());
+            }
+        }
+        Ok(None) => warn!(
+            "killed {} hook {command:?} after {}s",
+            event.as_str(),
+            timeout.as_secs_f64()
+        ),
+        Err(err) => warn!("lost track of {} hook {command:?}: {err}", event.as_str()),
+    }
+}
+
+/// The details of `event` as environment variables.
+fn environment(event: HookEvent, phase: Phase, status: &Status) -> [(&'static str, String); 11] {
+    let secs = |duration: Duration| duration.as_secs().to_string();
+    [
+        ("CONCENTRATO_EVENT", event.as_str().to_owned()),
+        ("CONCENTRATO_PHASE", phase.to_string()),
+        ("CONCENTRATO_NEXT_PHASE", status.phase.to_string()),
+        ("CONCENTRATO_STATE", status.state.to_string()),
+        ("CONCENTRATO_PLANNED", secs(status.planned())),
+        ("CONCENTRATO_ELAPSED", secs(status.elapsed())),
+        // Rounded up, like every countdown.
+        (
+            "CONCENTRATO_REMAINING",
+            status.remaining_ms.div_ceil(1000).to_string(),
+        ),
+        ("CONCENTRATO_COMPLETED", status.completed.to_string()),
+        ("CONCENTRATO_TASK", status.task.clone().unwrap_or_default()),
+        (
+            "CONCENTRATO_PROFILE",
+            status.profile.clone().unwrap_or_default(),
+        ),
+        (
+            "CONCENTRATO_PROTOCOL_VERSION",
+            protocol::VERSION.to_string(),
+        ),
+    ]
+}
+
+/// Waits for `child` to exit, killing it if it takes longer than `timeout`
+/// unless that is zero. Returns `None` if it was killed.
+fn wait(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    if timeout.is_zero() {
+        return child.wait().map(Some);
+    }
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let group = -i32::try_from(child.id()).expect("process ids fit in a pid_t");
+            // SAFETY: kill has no memory safety requirements.
+            if unsafe { libc::kill(group, libc::SIGKILL) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL);
+    }
+}
+
+/// Reads the lines of a pipe on a thread of its own, so that a hook never
+/// blocks on a full pipe while it is being waited for.
+fn drain(pipe: Option<impl Read + Send + 'static>) -> Option<Receiver<String>> {
+    let pipe = pipe?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).lines() {
+            let Ok(line) = line else { return };
+            if sender.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    Some(receiver)
+}
+
+/// The lines a hook printed, up to the point where only something it left
+/// running in the background keeps the pipe open.
+fn output(receiver: Option<Receiver<String>>) -> Vec<String> {
+    let Some(receiver) = receiver else {
+        return Vec::new();
+    };
+    iter::from_fn(|| receiver.recv_timeout(LINGER).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use log::Level;
+
+    use super::*;
+    use crate::daemon::testing::{TempDir, eventually, logged, record_logs};
+
+    /// Where the timer is once a work session with a task has ended.
+    fn status() -> Status {
+        Status {
+            phase: Phase::ShortBreak,
+            state: concentrato_core::Status::Idle,
+            planned_ms: 5 * 60_000,
+            elapsed_ms: 0,
+            remaining_ms: 5 * 60_000 - 999,
+            position: 1,
+            cycle_length: 8,
+            completed: 1,
+            today: 1,
+            today_focus_ms: 25 * 60_000,
+            goal: None,
+            streak: 0,
+            profile: Some("deep".to_owned()),
+            task: Some("write report".to_owned()),
+        }
+    }
+
+    fn run_hook(command: &str, timeout: Duration) {
+        record_logs();
+        run(command, HookEvent::WorkEnd, Phase::Work, &status(), timeout);
+    }
+
+    #[test]
+    fn names_the_hook_of_each_event() {
+        let cases = [
+            (EventKind::Started, Phase::Work, Some(HookEvent::WorkStart)),
+            (
+                EventKind::Started,
+                Phase::LongBreak,
+                Some(HookEvent::BreakStart),
+            ),
+            (EventKind::Completed, Phase::Work, Some(HookEvent::WorkEnd)),
+            (
+                EventKind::Completed,
+                Phase::ShortBreak,
+                Some(HookEvent::BreakEnd),
+            ),
+            (EventKind::Skipped, Phase::Work, Some(HookEvent::Skip)),
+            (EventKind::Stopped, Phase::Work, Some(HookEvent::Stop)),
+            (EventKind::Discarded, Phase::Work, None),
+            (EventKind::TaskChanged, Phase::Work, None),
+        ];
+        for (event, phase, hook) in cases {
+            assert_eq!(hook_event(event, phase), hook, "{event:?} in {phase}");
+        }
+    }
+
+    #[test]
+    fn describes_the_event_in_the_environment() {
+        let environment = environment(HookEvent::WorkEnd, Phase::Work, &status());
+        let value = |name: &str| {
+            environment
+                .iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("CONCENTRATO_EVENT"), Some("work_end"));
+        assert_eq!(value("CONCENTRATO_PHASE"), Some("work"));
+        assert_eq!(value("CONCENTRATO_NEXT_PHASE"), Some("short_break"));
+        assert_eq!(value("CONCENTRATO_STATE"), Some("idle"));
+        assert_eq!(value("CONCENTRATO_PLANNED"), Some("300"));
+        assert_eq!(value("CONCENTRATO_ELAPSED"), Some("0"));
+        assert_eq!(value("CONCENTRATO_REMAINING"), Some("300"));
+        assert_eq!(value("CONCENTRATO_COMPLETED"), Some("1"));
+        assert_eq!(value("CONCENTRATO_TASK"), Some("write report"));
+        assert_eq!(value("CONCENTRATO_PROFILE"), Some("deep"));
+
+        run_hook(
+            "echo \"env-test $CONCENTRATO_EVENT $CONCENTRATO_NEXT_PHASE $CONCENTRATO_TASK\"",
+            Duration::from_secs(10),
+        );
+        assert_eq!(
+            logged("env-test"),
+            [(
+                Level::Info,
+                "work_end hook: env-test work_end short_break write report".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn passes_the_event_as_json_on_stdin() {
+        run_hook(
+            "read -r line; echo \"stdin-test $line\"",
+            Duration::from_secs(10),
+        );
+        let logged = logged("stdin-test");
+        let [(Level::Info, message)] = logged.as_slice() else {
+            panic!("expected one line of output, not {logged:?}");
+        };
+        let json = message
+            .strip_prefix("work_end hook: stdin-test ")
+            .expect("the hook's output is logged");
+        let input: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            input,
+            json!({ "event": "work_end", "phase": "work", "status": status() })
+        );
+    }
+
+    #[test]
+    fn logs_a_failing_hook_and_what_it_said() {
+        let command = "echo failing-test; echo failing-test complaint >&2; exit 3";
+        run_hook(command, Duration::from_secs(10));
+        assert_eq!(
+            logged("failing-test"),
+            [
+                (Level::Info, "work_end hook: failing-test".to_owned()),
+                (
+                    Level::Warn,
+                    format!("work_end hook {command:?} failed: exit status: 3")
+                ),
+                (
+                    Level::Warn,
+                    "work_end hook: failing-test complaint".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_quiet_about_what_a_successful_hook_printed_to_stderr() {
+        run_hook("echo quiet-test >&2", Duration::from_secs(10));
+        assert_eq!(logged("quiet-test"), []);
+    }
+
+    #[test]
+    fn kills_everything_a_hook_started_when_it_times_out() {
+        let dir = TempDir::new();
+        let pid_file = dir.join("pid");
+        let command = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        let started = Instant::now();
+        run_hook(&command, Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            logged(&command),
+            [(
+                Level::Warn,
+                format!("killed work_end hook {command:?} after 0.2s")
+            )]
+        );
+
+        // The sleep in the background went with it.
+        let pid = fs::read_to_string(&pid_file).unwrap();
+        let stat = format!("/proc/{}/stat", pid.trim());
+        eventually("the background sleep to die", || {
+            fs::read_to_string(&stat).map_or(true, |stat| {
+                // A zombie waiting for whoever inherited it is dead too.
+                stat.rsplit_once(") ")
+                    .is_some_and(|(_, rest)| rest.starts_with('Z'))
+            })
+        });
+    }
+}
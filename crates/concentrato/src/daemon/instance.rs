@@ -0,0 +1,164 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use log::info;
+
+/// Proof that this process is the one daemon serving a socket.
+///
+/// The daemon holds an exclusive `flock` on a lock file next to the socket
+/// for as long as it runs, with its PID written inside. The kernel drops the
+/// lock when the process dies, however it dies, so a crashed daemon never
+/// stops the next one from starting.
+#[derive(Debug)]
+pub(crate) struct Instance {
+    _lock: File,
+}
+
+/// The outcome of trying to become the daemon.
+#[derive(Debug)]
+pub(crate) enum Claim {
+    Acquired(Instance),
+    /// Another daemon is already serving the socket.
+    Taken {
+        pid: Option<u32>,
+    },
+}
+
+impl Instance {
+    /// Tries to become the daemon for `socket`, clearing away what a daemon
+    /// that crashed left behind.
+    pub(crate) fn claim(socket: &Path) -> io::Result<Claim> {
+        let instance = match Self::adopt(socket)? {
+            Claim::Acquired(instance) => instance,
+            taken @ Claim::Taken { .. } => return Ok(taken),
+        };
+        if socket.exists() {
+            // Holding the lock means no daemon of ours is serving the socket,
+            // but something that does not take the lock still might.
+            if UnixStream::connect(socket).is_ok() {
+                return Ok(Claim::Taken { pid: None });
+            }
+            info!("removing stale socket {}", socket.display());
+            fs::remove_file(socket)?;
+        }
+        Ok(Claim::Acquired(instance))
+    }
+
+    /// Tries to become the daemon for `socket` when systemd has bound it
+    /// already, which leaves nothing to clear away.
+    pub(crate) fn adopt(socket: &Path) -> io::Result<Claim> {
+        let lock_path = lock_path(socket);
+        let mut lock = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(&lock_path)?;
+        // SAFETY: the descriptor belongs to `lock`, which outlives the call.
+        if unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::WouldBlock {
+                return Err(err);
+            }
+            let mut pid = String::new();
+            lock.read_to_string(&mut pid)?;
+            return Ok(Claim::Taken {
+                pid: pid.trim().parse().ok(),
+            });
+        }
+
+        lock.set_len(0)?;
+        lock.rewind()?;
+        writeln!(lock, "{}", process::id())?;
+        Ok(Claim::Acquired(Instance { _lock: lock }))
+    }
+}
+
+fn lock_path(socket: &Path) -> PathBuf {
+    socket.with_extension("lock")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixListener;
+
+    use super::*;
+    use crate::daemon::testing::TempDir;
+
+    fn acquired(claim: Claim) -> Instance {
+        match claim {
+            Claim::Acquired(instance) => instance,
+            Claim::Taken { pid } => panic!("expected to claim the socket, taken by {pid:?}"),
+        }
+    }
+
+    fn taken(claim: Claim) -> Option<u32> {
+        match claim {
+            Claim::Taken { pid } => pid,
+            Claim::Acquired(_) => panic!("expected the socket to be taken"),
+        }
+    }
+
+    #[test]
+    fn claims_a_socket_once_at_a_time() {
+        let dir = TempDir::new();
+        let socket = dir.join("concentrato.sock");
+        let instance = acquired(Instance::claim(&socket).unwrap());
+        assert_eq!(
+            fs::read_to_string(dir.join("concentrato.lock")).unwrap(),
+            format!("{}\n", process::id())
+        );
+
+        assert_eq!(
+            taken(Instance::claim(&socket).unwrap()),
+            Some(process::id())
+        );
+        assert_eq!(
+            taken(Instance::adopt(&socket).unwrap()),
+            Some(process::id())
+        );
+
+        // The lock goes with the instance.
+        drop(instance);
+        acquired(Instance::claim(&socket).unwrap());
+    }
+
+    #[test]
+    fn removes_a_stale_socket() {
+        let dir = TempDir::new();
+        let socket = dir.join("concentrato.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+
+        let _instance = acquired(Instance::claim(&socket).unwrap());
+        assert!(!socket.exists());
+    }
+
+    #[test]
+    fn leaves_a_socket_someone_else_serves() {
+        let dir = TempDir::new();
+        let socket = dir.join("concentrato.sock");
+        let _listener = UnixListener::bind(&socket).unwrap();
+
+        assert_eq!(taken(Instance::claim(&socket).unwrap()), None);
+        assert!(socket.exists());
+        // The failed claim let go of the lock again.
+        acquired(Instance::adopt(&socket).unwrap());
+    }
+
+    #[test]
+    fn adopts_a_socket_systemd_bound() {
+        let dir = TempDir::new();
+        let socket = dir.join("concentrato.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+
+        let _instance = acquired(Instance::adopt(&socket).unwrap());
+        assert!(socket.exists());
+    }
+}
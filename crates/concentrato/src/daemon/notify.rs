@@ -0,0 +1,531 @@
+//! Desktop notifications through the freedesktop Notifications service.
+//!
+//! The notifier subscribes to the engine like any client and shows a
+//! notification whenever a phase completes, with buttons to start the next
+//! phase, skip it, or give the one that ended five more minutes. Clicks come
+//! back as `ActionInvoked` signals on the session bus. Whatever else happens
+//! to the timer closes the notification, since its buttons are stale by
+//! then. Servers that cannot show buttons get notifications without them.
+//!
+//! Without a session bus there are no notifications, and while no
+//! notification server is running they are dropped; neither stops the
+//! daemon.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use concentrato_core::Phase;
+use log::{debug, info, warn};
+use zbus::blocking::proxy::SignalIterator;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::Value;
+
+use super::{Engine, lock};
+use crate::config::{Expiry, Message, NotificationConfig, Urgency};
+use crate::display;
+use crate::protocol::{EventKind, Notification, Request, Status};
+use crate::template::Template;
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// How much "+5 min" gives the phase that just ended.
+const PROLONG_BY: Duration = Duration::from_secs(5 * 60);
+
+/// The buttons on every notification, as action keys.
+const START: &str = "start";
+const SKIP: &str = "skip";
+const PROLONG: &str = "prolong";
+
+/// Starts showing notifications for `engine` in the background.
+pub(super) fn spawn(engine: Arc<Mutex<Engine>>) {
+    thread::spawn(move || {
+        let connection = match Connection::session() {
+            Ok(connection) => connection,
+            Err(err) => {
+                info!("no desktop notifications without a session bus: {err}");
+                return;
+            }
+        };
+        if let Some((mut notifier, notifications)) = Notifier::new(&connection, &engine) {
+            notifier.run(&engine, notifications);
+        }
+    });
+}
+
+struct Notifier<'a> {
+    proxy: Proxy<'a>,
+    /// The id of the notification on screen, if any.
+    shown: Arc<Mutex<Option<u32>>>,
+    /// Whether the last notification failed, so that a missing server is
+    /// reported once rather than at the end of every phase.
+    failing: bool,
+    /// Whether the last server asked had no buttons, so that it is logged
+    /// once.
+    without_actions: bool,
+}
+
+impl Notifier<'static> {
+    /// Subscribes to `engine` and starts listening for clicks on the
+    /// server at the other end of `connection`, returning the notifier and
+    /// what it is to show.
+    fn new(
+        connection: &Connection,
+        engine: &Arc<Mutex<Engine>>,
+    ) -> Option<(Self, Receiver<Notification>)> {
+        let proxy = match Proxy::new(connection, DESTINATION, PATH, INTERFACE) {
+            Ok(proxy) => proxy,
+            Err(err) => {
+                warn!("no desktop notifications: {err}");
+                return None;
+            }
+        };
+        let shown = Arc::new(Mutex::new(None));
+        // Listening before subscribing means no click on a notification the
+        // notifier has shown can go unheard.
+        match proxy.receive_signal("ActionInvoked") {
+            Ok(signals) => {
+                let (engine, shown) = (Arc::clone(engine), Arc::clone(&shown));
+                thread::spawn(move || handle_actions(signals, &engine, &shown));
+            }
+            Err(err) => warn!("notification buttons will not work: {err}"),
+        }
+        let (_, notifications) = lock(engine).subscribe(false);
+        let notifier = Notifier {
+            proxy,
+            shown,
+            failing: false,
+            without_actions: false,
+        };
+        Some((notifier, notifications))
+    }
+}
+
+impl Notifier<'_> {
+    fn run(&mut self, engine: &Mutex<Engine>, notifications: Receiver<Notification>) {
+        for notification in notifications {
+            let Notification::Changed {
+                event,
+                phase,
+                status,
+            } = notification
+            else {
+                continue;
+            };
+            match event {
+                EventKind::Completed => {
+                    let settings = lock(engine).settings().notifications.clone();
+                    if settings.enabled {
+                        self.show(&settings, phase, &status);
+                    }
+                }
+                // A new label leaves the buttons as good as they were.
+                EventKind::TaskChanged => {}
+                _ => self.close(),
+            }
+        }
+    }
+
+    /// Shows that `phase` ended, leading to `status`.
+    fn show(&mut self, settings: &NotificationConfig, phase: Phase, status: &Status) {
+        let message = match phase {
+            Phase::Work => &settings.work_end,
+            Phase::ShortBreak | Phase::LongBreak => &settings.break_end,
+        };
+        let (summary, body) = text(phase, message, status);
+        let start = match status.phase {
+            Phase::Work => "Start work",
+            Phase::ShortBreak | Phase::LongBreak => "Start break",
+        };
+        let buttons = [START, start, SKIP, "Skip", PROLONG, "+5 min"];
+        let actions = if self.has_actions() {
+            &buttons[..]
+        } else {
+            &[]
+        };
+        let urgency: u8 = match settings.urgency {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        };
+        let hints = HashMap::from([
+            ("urgency", Value::from(urgency)),
+            ("desktop-entry", Value::from("concentrato")),
+        ]);
+        let timeout: i32 = match settings.timeout {
+            Expiry::Default => -1,
+            Expiry::Never => 0,
+            Expiry::After(duration) => duration.as_millis().try_into().unwrap_or(i32::MAX),
+        };
+        let replaces = lock(&self.shown).unwrap_or(0);
+        let result = self.proxy.call::<_, _, u32>(
+            "Notify",
+            &(
+                "concentrato",
+                replaces,
+                settings.icon.as_str(),
+                summary,
+                body,
+                actions,
+                hints,
+                timeout,
+            ),
+        );
+        match result {
+            Ok(id) => {
+                *lock(&self.shown) = Some(id);
+                self.failing = false;
+            }
+            Err(err) if self.failing => debug!("cannot show a notification: {err}"),
+            Err(err) => {
+                warn!("cannot show a notification: {err}");
+                self.failing = true;
+            }
+        }
+    }
+
+    /// Whether the server can show buttons. Those that cannot would show
+    /// their labels as text, if anything, so they are sent none.
+    fn has_actions(&mut self) -> bool {
+        let capabilities = match self.proxy.call::<_, _, Vec<String>>("GetCapabilities", &()) {
+            Ok(capabilities) => capabilities,
+            // Showing the notification will fail the same way.
+            Err(_) => return true,
+        };
+        let has_actions = capabilities.iter().any(|c| c == "actions");
+        if !has_actions && !self.without_actions {
+            info!("the notification server has no buttons; notifications will have none");
+        }
+        self.without_actions = !has_actions;
+        has_actions
+    }
+
+    fn close(&mut self) {
+        let Some(id) = lock(&self.shown).take() else {
+            return;
+        };
+        if let Err(err) = self.proxy.call::<_, _, ()>("CloseNotification", &id) {
+            debug!("cannot close notification {id}: {err}");
+        }
+    }
+}
+
+/// The summary and body for the end of `phase`, from the user's templates
+/// or the built-in ones. The built-in body of a work session also tells how
+/// far today is towards the daily goal.
+fn text(phase: Phase, message: &Message, status: &Status) -> (String, String) {
+    let (summary, body) = match phase {
+        Phase::Work => ("Work session complete", "Next up: {label}, {planned:human}"),
+        Phase::ShortBreak | Phase::LongBreak => {
+            ("Break is over", "Next up: {label}, {planned:human}")
+        }
+    };
+    let render = |template: &Option<Template>, default: &str| match template {
+        Some(template) => template.render(status),
+        None => default
+            .parse::<Template>()
+            .expect("the built-in templates are valid")
+            .render(status),
+    };
+    let mut body = render(&message.body, body);
+    if let (Phase::Work, Some(goal), None) = (phase, status.goal, &message.body) {
+        let today = status.today_totals();
+        if goal.met(today) {
+            let _ = write!(body, "\nDaily goal reached: {}", goal.describe(today));
+            if status.streak > 1 {
+                let _ = write!(body, ", {} in a row", display::days(status.streak));
+            }
+        } else {
+            let _ = write!(body, "\nToday: {}", goal.describe(today));
+        }
+    }
+    (render(&message.summary, summary), body)
+}
+
+/// Carries out the buttons clicked on the notification that is on screen.
+fn handle_actions(signals: SignalIterator<'_>, engine: &Mutex<Engine>, shown: &Mutex<Option<u32>>) {
+    for signal in signals {
+        let (id, action) = match signal.body().deserialize::<(u32, String)>() {
+            Ok(args) => args,
+            Err(err) => {
+                debug!("ignoring a malformed ActionInvoked signal: {err}");
+                continue;
+            }
+        };
+        // The signal goes to every client of the server, and old
+        // notifications may still be around.
+        if *lock(shown) != Some(id) {
+            continue;
+        }
+        let mut engine = lock(engine);
+        match action.as_str() {
+            START => {
+                engine.handle(Request::Start {
+                    profile: None,
+                    task: None,
+                });
+            }
+            SKIP => {
+                engine.handle(Request::Skip);
+            }
+            PROLONG => engine.prolong(PROLONG_BY),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use zbus::blocking::Connection;
+    use zbus::interface;
+    use zbus::zvariant::OwnedValue;
+
+    use super::*;
+    use crate::config::{Config, TimerConfig};
+    use crate::daemon::testing::{Bus, eventually};
+
+    /// A notification as the server was asked to show it.
+    #[derive(Debug, Clone)]
+    struct Shown {
+        replaces: u32,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        timeout: i32,
+    }
+
+    /// What a notification server has been asked to do.
+    #[derive(Debug, Default)]
+    struct Log {
+        shown: Vec<Shown>,
+        closed: Vec<u32>,
+    }
+
+    struct Server {
+        capabilities: Vec<String>,
+        log: Arc<Mutex<Log>>,
+    }
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl Server {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: &str,
+            replaces_id: u32,
+            _app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            expire_timeout: i32,
+        ) -> u32 {
+            let mut log = lock(&self.log);
+            log.shown.push(Shown {
+                replaces: replaces_id,
+                summary: summary.to_owned(),
+                body: body.to_owned(),
+                actions,
+                hints,
+                timeout: expire_timeout,
+            });
+            log.shown.len() as u32
+        }
+
+        fn close_notification(&self, id: u32) {
+            lock(&self.log).closed.push(id);
+        }
+
+        fn get_capabilities(&self) -> Vec<String> {
+            self.capabilities.clone()
+        }
+    }
+
+    /// Serves notifications on `bus` with `capabilities`, returning the
+    /// server's connection, to send signals from, and its log.
+    fn serve(bus: &Bus, capabilities: &[&str]) -> (Connection, Arc<Mutex<Log>>) {
+        let log = Arc::new(Mutex::new(Log::default()));
+        let server = Server {
+            capabilities: capabilities.iter().map(|&c| c.to_owned()).collect(),
+            log: Arc::clone(&log),
+        };
+        let connection = bus
+            .builder()
+            .name(DESTINATION)
+            .and_then(|builder| builder.serve_at(PATH, server))
+            .and_then(|builder| builder.build())
+            .expect("the server starts");
+        (connection, log)
+    }
+
+    /// An engine with one-second work sessions, and a notifier for it on
+    /// `bus`.
+    fn notifier(bus: &Bus) -> Arc<Mutex<Engine>> {
+        let config = Config {
+            timer: TimerConfig {
+                work: Duration::from_secs(1),
+                ..TimerConfig::default()
+            },
+            ..Config::default()
+        };
+        let engine = Arc::new(Mutex::new(Engine::new(config, None, None)));
+        let connection = bus.connect();
+        let (mut notifier, notifications) =
+            Notifier::new(&connection, &engine).expect("the proxy is created");
+        {
+            let engine = Arc::clone(&engine);
+            thread::spawn(move || notifier.run(&engine, notifications));
+        }
+        engine
+    }
+
+    /// Runs a work session to completion.
+    fn complete_work(engine: &Mutex<Engine>) {
+        lock(engine).handle(Request::Start {
+            profile: None,
+            task: None,
+        });
+        thread::sleep(Duration::from_secs(1));
+        lock(engine).tick();
+        assert_eq!(lock(engine).timer.phase(), Phase::ShortBreak);
+    }
+
+    fn shown(log: &Mutex<Log>, count: usize) -> Vec<Shown> {
+        eventually("a notification", || lock(log).shown.len() >= count);
+        lock(log).shown.clone()
+    }
+
+    fn click(server: &Connection, id: u32, action: &str) {
+        server
+            .emit_signal(None::<()>, PATH, INTERFACE, "ActionInvoked", &(id, action))
+            .expect("the signal is sent");
+    }
+
+    #[test]
+    fn shows_and_closes_a_notification() {
+        let Some(bus) = Bus::start() else { return };
+        let (_server, log) = serve(&bus, &["actions", "body"]);
+        let engine = notifier(&bus);
+
+        complete_work(&engine);
+        let [shown] = &shown(&log, 1)[..] else {
+            panic!("more than one notification");
+        };
+        assert_eq!(shown.replaces, 0);
+        assert_eq!(shown.summary, "Work session complete");
+        assert_eq!(shown.body, "Next up: Short break, 5m");
+        assert_eq!(
+            shown.actions,
+            ["start", "Start break", "skip", "Skip", "prolong", "+5 min"]
+        );
+        assert_eq!(shown.hints["urgency"], OwnedValue::from(1u8));
+        assert_eq!(shown.timeout, 10_000);
+
+        lock(&engine).handle(Request::Skip);
+        eventually("the notification to close", || lock(&log).closed == [1]);
+    }
+
+    #[test]
+    fn carries_out_buttons() {
+        let Some(bus) = Bus::start() else { return };
+        let (server, log) = serve(&bus, &["actions"]);
+        let engine = notifier(&bus);
+
+        complete_work(&engine);
+        shown(&log, 1);
+        // Clicks on other notifications are not for this one.
+        click(&server, 7, SKIP);
+        click(&server, 1, START);
+        eventually("the break to start", || {
+            lock(&engine).timer.status() == concentrato_core::Status::Running
+        });
+        assert_eq!(lock(&engine).timer.phase(), Phase::ShortBreak);
+    }
+
+    #[test]
+    fn prolongs_from_a_button() {
+        let Some(bus) = Bus::start() else { return };
+        let (server, log) = serve(&bus, &["actions"]);
+        let engine = notifier(&bus);
+
+        complete_work(&engine);
+        shown(&log, 1);
+        click(&server, 1, PROLONG);
+        eventually("the work session to carry on", || {
+            lock(&engine).timer.phase() == Phase::Work
+        });
+        let engine = lock(&engine);
+        assert_eq!(engine.timer.status(), concentrato_core::Status::Running);
+        assert_eq!(engine.timer.planned(), PROLONG_BY);
+    }
+
+    #[test]
+    fn leaves_out_buttons_the_server_cannot_show() {
+        let Some(bus) = Bus::start() else { return };
+        let (_server, log) = serve(&bus, &["body"]);
+        let engine = notifier(&bus);
+
+        complete_work(&engine);
+        let shown = shown(&log, 1);
+        assert!(shown[0].actions.is_empty());
+        assert_eq!(shown[0].summary, "Work session complete");
+    }
+
+    #[test]
+    fn carries_on_without_a_server() {
+        let Some(bus) = Bus::start() else { return };
+        let engine = notifier(&bus);
+
+        complete_work(&engine);
+        lock(&engine).handle(Request::Reset);
+        // Give the notifier time to fail.
+        thread::sleep(Duration::from_millis(100));
+        let (_server, log) = serve(&bus, &["actions"]);
+        complete_work(&engine);
+        let shown = shown(&log, 1);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].replaces, 0);
+    }
+
+    #[test]
+    fn adds_goal_progress_to_the_built_in_body() {
+        let status = |today| Status {
+            phase: Phase::ShortBreak,
+            state: concentrato_core::Status::Idle,
+            planned_ms: 5 * 60_000,
+            elapsed_ms: 0,
+            remaining_ms: 5 * 60_000,
+            position: 1,
+            cycle_length: 8,
+            completed: 1,
+            today,
+            today_focus_ms: 0,
+            goal: Some(crate::goal::Goal::Pomodoros(4)),
+            streak: 3,
+            profile: None,
+            task: None,
+        };
+        let message = Message::default();
+        assert_eq!(
+            text(Phase::Work, &message, &status(2)).1,
+            "Next up: Short break, 5m\nToday: 2/4 pomodoros"
+        );
+        assert_eq!(
+            text(Phase::Work, &message, &status(4)).1,
+            "Next up: Short break, 5m\nDaily goal reached: 4/4 pomodoros, 3 days in a row"
+        );
+        let message = Message {
+            body: Some("{today} done".parse().unwrap()),
+            ..Message::default()
+        };
+        assert_eq!(text(Phase::Work, &message, &status(2)).1, "2 done");
+    }
+}
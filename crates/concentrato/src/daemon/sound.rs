@@ -0,0 +1,383 @@
+//! Audio cues for the timer's events.
+//!
+//! Sounds are played by whichever of PipeWire's `pw-play`, PulseAudio's
+//! `paplay` or ALSA's `aplay` is installed, or by the player the config
+//! names, so they go through the system's audio stack like any other. The
+//! bundled sounds are synthesized and written to the runtime directory the
+//! first time they are needed, since players only take files.
+//!
+//! `aplay` can neither decode OGG or FLAC nor change the volume, so it is
+//! passed over for such files, and the bundled sounds are written at the
+//! volume it cannot apply. The same goes for a configured player that is
+//! not given the volume through a `{volume}` placeholder.
+//!
+//! Without a player or an output device the daemon stays silent: failures
+//! are only logged, and playing never holds up the timer.
+
+use std::collections::HashSet;
+use std::f64::consts::TAU;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::{env, fmt};
+
+use concentrato_core::{Phase, Status as State};
+use log::{debug, info, warn};
+
+use super::{Engine, lock};
+use crate::config::{BundledSound, Sound, SoundConfig};
+use crate::paths;
+use crate::protocol::{EventKind, Notification};
+
+/// The players tried in turn when the config does not name one.
+const PLAYERS: [&str; 3] = ["pw-play", "paplay", "aplay"];
+
+const SAMPLE_RATE: u32 = 22_050;
+
+/// Starts playing sounds for `engine` in the background.
+pub(super) fn spawn(engine: Arc<Mutex<Engine>>) {
+    thread::spawn(move || {
+        let (_, notifications) = lock(&engine).subscribe(true);
+        let mut speaker = Speaker::default();
+        for notification in notifications {
+            let settings = lock(&engine).settings().sounds.clone();
+            if !settings.enabled {
+                continue;
+            }
+            let sound = match notification {
+                Notification::Changed { event, phase, .. } => match (event, phase.is_break()) {
+                    (EventKind::Started, false) => &settings.work_start,
+                    (EventKind::Completed, false) => &settings.work_end,
+                    (EventKind::Started, true) => &settings.break_start,
+                    (EventKind::Completed, true) => &settings.break_end,
+                    _ => &None,
+                },
+                Notification::Tick { status }
+                    if status.phase == Phase::Work && status.state == State::Running =>
+                {
+                    &settings.ticking
+                }
+                Notification::Tick { .. } => &None,
+            };
+            if let Some(sound) = sound {
+                speaker.play(sound, &settings);
+            }
+        }
+    });
+}
+
+/// Plays sounds, remembering what it found out about the system.
+#[derive(Debug, Default)]
+struct Speaker {
+    /// The problems it has warned about, each of which bears saying once.
+    warned: HashSet<Warning>,
+    /// Whether the last player failed, so that a broken player is reported
+    /// once rather than on every sound.
+    failing: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Warning {
+    NoPlayer,
+    /// No installed player can decode a file.
+    Format,
+    /// The player cannot change the volume of a file.
+    Volume,
+}
+
+impl Speaker {
+    fn play(&mut self, sound: &Sound, settings: &SoundConfig) {
+        let wav = match sound {
+            Sound::Bundled(_) => true,
+            Sound::File(path) => is_wav(path),
+        };
+        // `volume` is what is left for the file itself to carry.
+        let (mut command, volume) = match &settings.player {
+            Some(player) => match configured(player, settings.volume) {
+                Some(played) => played,
+                None => return,
+            },
+            None => match self.installed(wav, settings.volume) {
+                Some(played) => played,
+                None => return,
+            },
+        };
+        let path = match sound {
+            Sound::File(path) => {
+                if volume < 1.0 {
+                    self.warn_once(
+                        Warning::Volume,
+                        format_args!(
+                            "sounds.volume does not apply to {}, since {:?} has no volume control",
+                            path.display(),
+                            command.get_program()
+                        ),
+                    );
+                }
+                path.clone()
+            }
+            Sound::Bundled(sound) => match bundled(*sound, volume) {
+                Ok(path) => path,
+                Err(err) => {
+                    warn!("cannot write the {} sound: {err}", sound.as_str());
+                    return;
+                }
+            },
+        };
+        command
+            .arg(&path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                warn!("cannot run {:?}: {err}", command.get_program());
+                return;
+            }
+        };
+        // Reap the player when it is done, without waiting for it here.
+        let path = path.display().to_string();
+        let failing = Arc::clone(&self.failing);
+        thread::spawn(move || {
+            let failure = match child.wait() {
+                Ok(status) if status.success() => {
+                    failing.store(false, Ordering::Relaxed);
+                    return;
+                }
+                Ok(status) => status.to_string(),
+                Err(err) => err.to_string(),
+            };
+            if failing.swap(true, Ordering::Relaxed) {
+                debug!("playing {path} failed: {failure}");
+            } else {
+                warn!("playing {path} failed: {failure}");
+            }
+        });
+    }
+
+    /// The first of the usual players that is installed and can decode the
+    /// file, which is WAV or not as `wav` says, set to play at `volume`,
+    /// and what is left of the volume for the file to carry.
+    fn installed(&mut self, wav: bool, volume: f64) -> Option<(Command, f64)> {
+        let installed: Vec<_> = PLAYERS.into_iter().filter(|p| on_path(p)).collect();
+        let Some(player) = choose(&installed, wav) else {
+            if installed.is_empty() {
+                self.warn_once(
+                    Warning::NoPlayer,
+                    format_args!(
+                        "no sounds, since none of {} is installed",
+                        PLAYERS.join(", ")
+                    ),
+                );
+            } else {
+                self.warn_once(
+                    Warning::Format,
+                    format_args!(
+                        "aplay only plays WAV files; install pw-play or paplay, or set \
+                         sounds.player, for OGG and FLAC"
+                    ),
+                );
+            }
+            return None;
+        };
+        let mut command = Command::new(player);
+        match player {
+            "pw-play" => {
+                command.arg(format!("--volume={volume}"));
+            }
+            "paplay" => {
+                // paplay counts volume up to 65536 for 100%.
+                command.arg(format!("--volume={}", (volume * 65536.0) as u32));
+            }
+            // aplay has no volume control.
+            _ => return Some((command, volume)),
+        }
+        Some((command, 1.0))
+    }
+
+    fn warn_once(&mut self, warning: Warning, message: fmt::Arguments<'_>) {
+        if self.warned.insert(warning) {
+            match warning {
+                Warning::NoPlayer => info!("{message}"),
+                Warning::Format | Warning::Volume => warn!("{message}"),
+            }
+        }
+    }
+}
+
+/// The command line the config gives for a player, with its placeholders
+/// filled in for `volume`, and what is left of the volume for the file to
+/// carry.
+fn configured(player: &str, volume: f64) -> Option<(Command, f64)> {
+    let percent = (volume * 100.0).round().to_string();
+    let volume_text = volume.to_string();
+    let mut applied = false;
+    let words: Vec<_> = player
+        .split_whitespace()
+        .map(|word| {
+            applied |= word.contains("{volume}") || word.contains("{volume_percent}");
+            word.replace("{volume}", &volume_text)
+                .replace("{volume_percent}", &percent)
+        })
+        .collect();
+    let (program, args) = words.split_first()?;
+    let mut command = Command::new(program);
+    command.args(args);
+    Some((command, if applied { 1.0 } else { volume }))
+}
+
+/// The first of the `installed` players that can decode a file that is WAV
+/// or not as `wav` says.
+fn choose<'a>(installed: &[&'a str], wav: bool) -> Option<&'a str> {
+    // pw-play and paplay read files with libsndfile, aplay only WAV.
+    installed
+        .iter()
+        .copied()
+        .find(|&player| wav || player != "aplay")
+}
+
+fn is_wav(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"))
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// The file holding a bundled sound at `volume`, written the first time it
+/// is asked for.
+fn bundled(sound: BundledSound, volume: f64) -> io::Result<PathBuf> {
+    // Each volume a player cannot apply gets a file of its own.
+    let percent = (volume.clamp(0.0, 1.0) * 100.0).round() as u32;
+    let name = match percent {
+        100 => format!("{}.wav", sound.as_str()),
+        percent => format!("{}-{percent}.wav", sound.as_str()),
+    };
+    let path = paths::runtime_dir().join("sounds").join(name);
+    if !path.exists() {
+        fs::create_dir_all(path.parent().expect("the path has a directory"))?;
+        // Write it whole or not at all, so that a crash leaves no half a
+        // sound behind.
+        let partial = path.with_extension("wav.tmp");
+        let scale = f64::from(percent) / 100.0;
+        let samples: Vec<_> = synthesize(sound).iter().map(|s| s * scale).collect();
+        write_wav(&partial, &samples)?;
+        fs::rename(&partial, &path)?;
+    }
+    Ok(path)
+}
+
+/// Synthesizes a bundled sound as samples between -1 and 1.
+fn synthesize(sound: BundledSound) -> Vec<f64> {
+    match sound {
+        // A struck bell: a fundamental with inharmonic partials, each
+        // fading at its own rate.
+        BundledSound::Bell => tone(
+            1.6,
+            &[
+                (660.0, 0.6, 2.5),
+                (1_320.0, 0.25, 4.0),
+                (1_790.0, 0.15, 6.0),
+            ],
+        ),
+        // Two notes a fifth apart.
+        BundledSound::Chime => {
+            let mut samples = tone(0.35, &[(784.0, 0.5, 6.0), (1_568.0, 0.15, 9.0)]);
+            samples.extend(tone(0.9, &[(1_175.0, 0.5, 4.0), (2_350.0, 0.15, 7.0)]));
+            samples
+        }
+        BundledSound::Ding => tone(0.6, &[(1_320.0, 0.6, 7.0)]),
+        BundledSound::Tick => tone(0.02, &[(2_000.0, 0.4, 200.0)]),
+    }
+}
+
+/// `seconds` of partials, each a frequency, amplitude and decay rate.
+fn tone(seconds: f64, partials: &[(f64, f64, f64)]) -> Vec<f64> {
+    let count = (seconds * f64::from(SAMPLE_RATE)) as usize;
+    (0..count)
+        .map(|i| {
+            let t = i as f64 / f64::from(SAMPLE_RATE);
+            // A few milliseconds of attack keep the start from clicking.
+            let attack = (t / 0.005).min(1.0);
+            let sum: f64 = partials
+                .iter()
+                .map(|&(freq, amp, decay)| amp * (-decay * t).exp() * (TAU * freq * t).sin())
+                .sum();
+            attack * sum
+        })
+        .collect()
+}
+
+/// Writes mono 16-bit PCM samples as a WAV file.
+fn write_wav(path: &Path, samples: &[f64]) -> io::Result<()> {
+    let data_len = u32::try_from(samples.len() * 2).expect("sounds are short");
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_len).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&1u16.to_le_bytes())?; // mono
+    out.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    out.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?; // bytes per second
+    out.write_all(&2u16.to_le_bytes())?; // bytes per frame
+    out.write_all(&16u16.to_le_bytes())?; // bits per sample
+    out.write_all(b"data")?;
+    out.write_all(&data_len.to_le_bytes())?;
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * f64::from(i16::MAX)) as i16;
+        out.write_all(&sample.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn fills_in_the_volume_for_a_configured_player() {
+        let (command, left) = configured("mpv --volume={volume_percent} --no-video", 0.8).unwrap();
+        assert_eq!(command.get_program(), "mpv");
+        assert_eq!(args(&command), ["--volume=80", "--no-video"]);
+        assert_eq!(left, 1.0);
+
+        let (command, left) = configured("play -v {volume}", 0.25).unwrap();
+        assert_eq!(args(&command), ["-v", "0.25"]);
+        assert_eq!(left, 1.0);
+    }
+
+    #[test]
+    fn leaves_the_volume_to_the_file_without_a_placeholder() {
+        let (command, left) = configured("mpv --really-quiet", 0.5).unwrap();
+        assert_eq!(args(&command), ["--really-quiet"]);
+        assert_eq!(left, 0.5);
+        assert!(configured("  ", 0.5).is_none());
+    }
+
+    #[test]
+    fn passes_over_aplay_for_other_formats() {
+        assert_eq!(choose(&["aplay"], true), Some("aplay"));
+        assert_eq!(choose(&["aplay"], false), None);
+        assert_eq!(choose(&["aplay", "paplay"], false), Some("paplay"));
+        assert_eq!(choose(&["pw-play", "aplay"], false), Some("pw-play"));
+        assert_eq!(choose(&[], true), None);
+        assert!(is_wav(Path::new("/sounds/bell.WAV")));
+        assert!(!is_wav(Path::new("/sounds/birds.ogg")));
+        assert!(!is_wav(Path::new("/sounds/wav")));
+    }
+}
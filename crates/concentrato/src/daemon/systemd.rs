@@ -0,0 +1,116 @@
+//! Running as a systemd user service.
+//!
+//! With the socket unit in `contrib/systemd`, systemd binds the daemon's
+//! socket itself and starts the service when the first client connects,
+//! handing the socket over as described in `sd_listen_fds(3)`. The service
+//! is `Type=notify`, so the daemon also says when it is ready to serve, as in
+//! `sd_notify(3)`. Outside systemd neither does anything.
+
+use std::env;
+use std::io;
+use std::mem;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram, UnixListener};
+use std::process;
+
+use log::{debug, warn};
+
+/// The first descriptor systemd passes on.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The socket systemd bound for the daemon, if it started the daemon through
+/// socket activation.
+pub(super) fn listener() -> io::Result<Option<UnixListener>> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    let Some(count) = passed_fds(pid.as_deref(), fds.as_deref(), process::id()) else {
+        return Ok(None);
+    };
+    if count > 1 {
+        warn!("systemd passed {count} sockets; serving only the first");
+    }
+    let fd = LISTEN_FDS_START;
+    // SAFETY: stat is plain data, for which all zeroes is a valid value.
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    // SAFETY: fstat only writes to the buffer it is given.
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "systemd passed something other than a socket",
+        ));
+    }
+    // systemd leaves the descriptor open across exec, which would leak it
+    // into hooks and sound players.
+    // SAFETY: fcntl has no memory safety requirements.
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the descriptor was passed to this process to own, and nothing
+    // else takes it.
+    let listener = unsafe { UnixListener::from_raw_fd(fd) };
+    // Only a Unix socket has a Unix address.
+    listener.local_addr()?;
+    Ok(Some(listener))
+}
+
+/// How many descriptors systemd passed to the process `pid`, going by
+/// `LISTEN_PID` and `LISTEN_FDS`, or `None` if it passed none.
+fn passed_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Option<RawFd> {
+    // The variables are inherited by whatever the daemon runs, which is why
+    // they name the process they are meant for.
+    let for_us = listen_pid
+        .and_then(|listen_pid| listen_pid.parse::<u32>().ok())
+        .is_some_and(|listen_pid| listen_pid == pid);
+    if !for_us {
+        return None;
+    }
+    listen_fds
+        .and_then(|count| count.parse().ok())
+        .filter(|&count| count > 0)
+}
+
+/// Tells systemd that the daemon is serving its socket.
+pub(super) fn notify_ready() {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let path = path.to_string_lossy();
+    let result = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name),
+        None => SocketAddr::from_pathname(&*path),
+    }
+    .and_then(|addr| UnixDatagram::unbound()?.send_to_addr(b"READY=1\n", &addr));
+    match result {
+        Ok(_) => debug!("told systemd the daemon is ready"),
+        Err(err) => warn!("cannot tell systemd the daemon is ready: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_sockets_passed_to_this_process_only() {
+        assert_eq!(passed_fds(Some("1234"), Some("1"), 1234), Some(1));
+        assert_eq!(passed_fds(Some("1234"), Some("3"), 1234), Some(3));
+        // Passed to the process that ran this one.
+        assert_eq!(passed_fds(Some("1233"), Some("1"), 1234), None);
+        assert_eq!(passed_fds(None, Some("1"), 1234), None);
+        assert_eq!(passed_fds(Some("self"), Some("1"), 1234), None);
+        assert_eq!(passed_fds(Some(""), Some("1"), 1234), None);
+    }
+
+    #[test]
+    fn counts_the_sockets_passed() {
+        assert_eq!(passed_fds(Some("1234"), None, 1234), None);
+        assert_eq!(passed_fds(Some("1234"), Some("0"), 1234), None);
+        assert_eq!(passed_fds(Some("1234"), Some("-1"), 1234), None);
+        assert_eq!(passed_fds(Some("1234"), Some("two"), 1234), None);
+        assert_eq!(passed_fds(Some("1234"), Some(" 1"), 1234), None);
+    }
+}
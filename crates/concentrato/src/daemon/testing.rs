@@ -0,0 +1,187 @@
+//! Helpers for testing the daemon: a private session bus for its D-Bus
+//! clients and services, the client end of a socket, scratch directories,
+//! and a record of what it logged.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::Deref;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::de::DeserializeOwned;
+use zbus::blocking::{Connection, connection};
+
+/// A `dbus-daemon` of its own, killed when dropped.
+pub(super) struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    /// Starts a bus, or returns `None` if `dbus-daemon` is not installed.
+    pub(super) fn start() -> Option<Self> {
+        let mut daemon = match Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(daemon) => daemon,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                eprintln!("skipping: dbus-daemon is not installed");
+                return None;
+            }
+            Err(err) => panic!("cannot start dbus-daemon: {err}"),
+        };
+        let mut address = String::new();
+        let stdout = daemon.stdout.take().expect("stdout is piped");
+        BufReader::new(stdout)
+            .read_line(&mut address)
+            .expect("dbus-daemon prints its address");
+        Some(Self {
+            daemon,
+            address: address.trim().to_owned(),
+        })
+    }
+
+    /// A new connection to the bus.
+    pub(super) fn connect(&self) -> Connection {
+        self.builder().build().expect("the bus accepts connections")
+    }
+
+    pub(super) fn builder(&self) -> connection::Builder<'_> {
+        connection::Builder::address(self.address.as_str()).expect("the address is valid")
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// A client's end of a connection to the daemon, speaking lines of JSON.
+pub(super) struct Peer {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Peer {
+    /// Wraps `stream`, giving up on a reply after five seconds.
+    pub(super) fn new(stream: UnixStream) -> Self {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .expect("the timeout is not zero");
+        let writer = stream.try_clone().expect("the socket can be cloned");
+        Self {
+            reader: BufReader::new(stream),
+            writer,
+        }
+    }
+
+    pub(super) fn send(&mut self, line: &str) {
+        writeln!(self.writer, "{line}").expect("the daemon reads the line");
+    }
+
+    /// The next line from the daemon, or `None` once it hangs up.
+    pub(super) fn receive<T: DeserializeOwned>(&mut self) -> Option<T> {
+        let mut line = String::new();
+        let read = self
+            .reader
+            .read_line(&mut line)
+            .expect("the daemon answers in time");
+        (read > 0).then(|| serde_json::from_str(&line).expect("the daemon writes valid JSON"))
+    }
+}
+
+/// Waits up to five seconds for `check` to hold, for what other threads
+/// and processes do in their own time.
+pub(crate) fn eventually(what: &str, mut check: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !check() {
+        assert!(Instant::now() < deadline, "timed out waiting for {what}");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// A directory of its own under the system's temporary directory, removed
+/// with everything in it when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "concentrato-test-{}-{}",
+            process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = env::temp_dir().join(name);
+        fs::create_dir_all(&path).expect("the temporary directory is writable");
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+static LOGGED: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+/// Keeps every message, for [`logged`].
+struct Recorder;
+
+impl Log for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let message = (record.level(), record.args().to_string());
+        LOGGED
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(message);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Starts recording what is logged at info and above, for the rest of the
+/// process.
+pub(crate) fn record_logs() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&Recorder).expect("no other logger is set in tests");
+        log::set_max_level(LevelFilter::Info);
+    });
+}
+
+/// The messages logged since [`record_logs`] that contain `text`, which
+/// should be unique to a test since tests run side by side.
+pub(crate) fn logged(text: &str) -> Vec<(Level, String)> {
+    LOGGED
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .iter()
+        .filter(|(_, message)| message.contains(text))
+        .cloned()
+        .collect()
+}
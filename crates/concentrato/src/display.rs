@@ -0,0 +1,62 @@
+//! How the timer is shown to people.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use concentrato_core::Status as State;
+
+use crate::protocol::Status;
+
+/// Formats a countdown as `m:ss`, or `h:mm:ss` from an hour up.
+///
+/// Partial seconds are rounded up, so a countdown reads `0:00` only once it
+/// has actually run out.
+pub fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{minutes}:{secs:02}")
+    }
+}
+
+/// A one-line description such as `Work, running: 12:34 left`.
+pub fn summary(status: &Status) -> String {
+    let label = status.phase.label();
+    match status.state {
+        State::Idle => format!("{label}, not started: {}", clock(status.planned())),
+        State::Running => format!("{label}, running: {} left", clock(status.remaining())),
+        State::Paused => format!("{label}, paused: {} left", clock(status.remaining())),
+    }
+}
+
+/// The summary followed by everything else worth knowing, one item a line.
+pub fn details(status: &Status) -> String {
+    let mut out = summary(status);
+    if let Some(task) = &status.task {
+        let _ = write!(out, "\nTask: {task}");
+    }
+    if let Some(profile) = &status.profile {
+        let _ = write!(out, "\nProfile: {profile}");
+    }
+    let _ = write!(out, "\nCompleted: {}", status.completed);
+    if let Some(goal) = status.goal {
+        let _ = write!(out, "\nToday: {}", goal.describe(status.today_totals()));
+        let _ = write!(out, "\nStreak: {}", days(status.streak));
+    }
+    out
+}
+
+/// A count of days, as in `1 day` or `3 days`.
+pub fn days(count: u32) -> String {
+    plural(count as usize, "day")
+}
+
+/// A count of things, as in `1 session` or `3 sessions`.
+pub fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {noun}"),
+        count => format!("{count} {noun}s"),
+    }
+}
@@ -0,0 +1,482 @@
+//! The [`history`](crate::history) in formats other tools read.
+//!
+//! CSV and JSON Lines carry every session, a row or line each, with times in
+//! RFC 3339 and durations in whole seconds. iCalendar carries only the work
+//! sessions, an event each, so that they can be laid over a calendar.
+
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use concentrato_core::{Phase, format_duration};
+use serde::Serialize;
+
+use crate::history::Session;
+
+/// A format sessions can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+    Ics,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Jsonl, Format::Ics];
+
+    /// The identifier used for this format on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+            Format::Ics => "ics",
+        }
+    }
+
+    /// Writes `sessions` to `out` in this format.
+    pub fn write(self, sessions: &[Session], out: &mut impl Write) -> io::Result<()> {
+        match self {
+            Format::Csv => write_csv(sessions, out),
+            Format::Jsonl => write_jsonl(sessions, out),
+            Format::Ics => write_ics(sessions, out, SystemTime::now()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown format {0:?}; expected csv, jsonl or ics")]
+pub struct ParseFormatError(String);
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| ParseFormatError(s.to_owned()))
+    }
+}
+
+/// A session the way CSV and JSON Lines write it.
+#[derive(Serialize)]
+struct Record<'a> {
+    started_at: String,
+    ended_at: String,
+    phase: Phase,
+    planned_s: u64,
+    actual_s: u64,
+    paused_s: u64,
+    pauses: u32,
+    outcome: &'a str,
+    task: Option<&'a str>,
+    tags: &'a [String],
+    profile: Option<&'a str>,
+}
+
+impl<'a> From<&'a Session> for Record<'a> {
+    fn from(session: &'a Session) -> Self {
+        Record {
+            started_at: local_time(session.started_at),
+            ended_at: local_time(session.ended_at),
+            phase: session.phase,
+            planned_s: secs(session.planned),
+            actual_s: secs(session.actual),
+            paused_s: secs(session.paused),
+            pauses: session.pauses,
+            outcome: session.outcome.as_str(),
+            task: session.task.as_deref(),
+            tags: &session.tags,
+            profile: session.profile.as_deref(),
+        }
+    }
+}
+
+const CSV_HEADER: &str =
+    "started_at,ended_at,phase,planned_s,actual_s,paused_s,pauses,outcome,task,tags,profile";
+
+/// Writes a header, then a row for each session with its tags separated by
+/// spaces.
+fn write_csv(sessions: &[Session], out: &mut impl Write) -> io::Result<()> {
+    write!(out, "{CSV_HEADER}\r\n")?;
+    for session in sessions {
+        let record = Record::from(session);
+        let fields = [
+            record.started_at,
+            record.ended_at,
+            record.phase.as_str().to_owned(),
+            record.planned_s.to_string(),
+            record.actual_s.to_string(),
+            record.paused_s.to_string(),
+            record.pauses.to_string(),
+            record.outcome.to_owned(),
+            record.task.unwrap_or_default().to_owned(),
+            record.tags.join(" "),
+            record.profile.unwrap_or_default().to_owned(),
+        ];
+        let fields: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+        write!(out, "{}\r\n", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// Quotes `field` if it holds anything that would break up the row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn write_jsonl(sessions: &[Session], out: &mut impl Write) -> io::Result<()> {
+    for session in sessions {
+        serde_json::to_writer(&mut *out, &Record::from(session))?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Writes a calendar with an event for each work session, named after its
+/// task, that lasts from when it started to when it ended. `now` is when the
+/// calendar was made.
+fn write_ics(sessions: &[Session], out: &mut impl Write, now: SystemTime) -> io::Result<()> {
+    let mut ics = Ics { out };
+    let stamp = utc_time(now);
+    ics.line("BEGIN:VCALENDAR")?;
+    ics.line("VERSION:2.0")?;
+    ics.line(&format!(
+        "PRODID:-//concentrato//concentrato {}//EN",
+        env!("CARGO_PKG_VERSION")
+    ))?;
+    ics.line("CALSCALE:GREGORIAN")?;
+    for session in sessions.iter().filter(|s| s.phase == Phase::Work) {
+        let started_at = DateTime::<Utc>::from(session.started_at);
+        ics.line("BEGIN:VEVENT")?;
+        // No two sessions start at the same moment.
+        ics.line(&format!(
+            "UID:{}@concentrato",
+            started_at.timestamp_millis()
+        ))?;
+        ics.line(&format!("DTSTAMP:{stamp}"))?;
+        ics.line(&format!("DTSTART:{}", utc_time(session.started_at)))?;
+        ics.line(&format!("DTEND:{}", utc_time(session.ended_at)))?;
+        let summary = session.task.as_deref().unwrap_or("Focus");
+        ics.line(&format!("SUMMARY:{}", ics_text(summary)))?;
+        ics.line(&format!("DESCRIPTION:{}", ics_text(&description(session))))?;
+        if !session.tags.is_empty() {
+            let tags: Vec<_> = session.tags.iter().map(|tag| ics_text(tag)).collect();
+            ics.line(&format!("CATEGORIES:{}", tags.join(",")))?;
+        }
+        ics.line("TRANSP:OPAQUE")?;
+        ics.line("END:VEVENT")?;
+    }
+    ics.line("END:VCALENDAR")
+}
+
+/// What an event says about its session, as in `Completed: 25m of focus
+/// out of 25m, paused once for 2m`.
+fn description(session: &Session) -> String {
+    let mut description = format!(
+        "{}: {} of focus out of {}",
+        capitalize(session.outcome.as_str()),
+        format_duration(whole_secs(session.actual)),
+        format_duration(whole_secs(session.planned))
+    );
+    let paused = format_duration(whole_secs(session.paused));
+    match session.pauses {
+        0 => {}
+        1 => {
+            let _ = write!(description, ", paused once for {paused}");
+        }
+        pauses => {
+            let _ = write!(description, ", paused {pauses} times for {paused}");
+        }
+    }
+    if let Some(profile) = &session.profile {
+        let _ = write!(description, ", profile {profile}");
+    }
+    description
+}
+
+/// Writes iCalendar content lines, which end in CRLF and are folded to 75
+/// octets.
+struct Ics<'a, W> {
+    out: &'a mut W,
+}
+
+impl<W: Write> Ics<'_, W> {
+    fn line(&mut self, line: &str) -> io::Result<()> {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                self.out.write_all(b"\r\n ")?;
+                // The space that continues the line counts towards it.
+                width = 1;
+            }
+            write!(self.out, "{c}")?;
+            width += c.len_utf8();
+        }
+        self.out.write_all(b"\r\n")
+    }
+}
+
+/// Escapes `text` for an iCalendar text value.
+fn ics_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn local_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+fn utc_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// `duration` rounded to the nearest second.
+fn secs(duration: Duration) -> u64 {
+    whole_secs(duration).as_secs()
+}
+
+fn whole_secs(duration: Duration) -> Duration {
+    Duration::from_secs((duration.as_millis() as u64 + 500) / 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::history::Outcome;
+
+    /// `secs` after 2026-09-21 14:00:00 UTC.
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_789_999_200 + secs)
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn sessions() -> Vec<Session> {
+        vec![
+            Session {
+                started_at: at(0),
+                ended_at: at(27 * 60),
+                phase: Phase::Work,
+                planned: minutes(25),
+                actual: minutes(25) - Duration::from_millis(400),
+                paused: minutes(2),
+                pauses: 1,
+                outcome: Outcome::Completed,
+                task: Some("write \"report\", part 1".to_owned()),
+                tags: vec!["thesis".to_owned(), "writing".to_owned()],
+                profile: Some("deep-work".to_owned()),
+            },
+            Session {
+                started_at: at(27 * 60),
+                ended_at: at(30 * 60),
+                phase: Phase::ShortBreak,
+                planned: minutes(5),
+                actual: minutes(3),
+                paused: Duration::ZERO,
+                pauses: 0,
+                outcome: Outcome::Skipped,
+                task: None,
+                tags: Vec::new(),
+                profile: None,
+            },
+            Session {
+                started_at: at(30 * 60),
+                ended_at: at(40 * 60),
+                phase: Phase::Work,
+                planned: minutes(25),
+                actual: minutes(8),
+                paused: minutes(2),
+                pauses: 3,
+                outcome: Outcome::Interrupted,
+                task: Some("notes; a, b\\c\non the next line".to_owned()),
+                tags: vec!["a,b".to_owned()],
+                profile: None,
+            },
+        ]
+    }
+
+    fn export(format: Format) -> String {
+        let mut out = Vec::new();
+        match format {
+            Format::Ics => write_ics(&sessions(), &mut out, at(3600)).unwrap(),
+            format => format.write(&sessions(), &mut out).unwrap(),
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    fn folded(line: &str) -> String {
+        let mut out = Vec::new();
+        Ics { out: &mut out }.line(line).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_csv() {
+        // Times are local, so they are the one thing not spelled out.
+        let time = |secs| local_time(at(secs));
+        let expected = [
+            CSV_HEADER.to_owned(),
+            format!(
+                "{},{},work,1500,1500,120,1,completed,\"write \"\"report\"\", part 1\",thesis writing,deep-work",
+                time(0),
+                time(27 * 60)
+            ),
+            format!(
+                "{},{},short_break,300,180,0,0,skipped,,,",
+                time(27 * 60),
+                time(30 * 60)
+            ),
+            format!(
+                "{},{},work,1500,480,120,3,interrupted,\"notes; a, b\\c\non the next line\",\"a,b\",",
+                time(30 * 60),
+                time(40 * 60)
+            ),
+        ];
+        assert_eq!(export(Format::Csv), expected.join("\r\n") + "\r\n");
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let time = |secs| local_time(at(secs));
+        let expected = [
+            format!(
+                r#"{{"started_at":"{}","ended_at":"{}","phase":"work","planned_s":1500,"actual_s":1500,"paused_s":120,"pauses":1,"outcome":"completed","task":"write \"report\", part 1","tags":["thesis","writing"],"profile":"deep-work"}}"#,
+                time(0),
+                time(27 * 60)
+            ),
+            format!(
+                r#"{{"started_at":"{}","ended_at":"{}","phase":"short_break","planned_s":300,"actual_s":180,"paused_s":0,"pauses":0,"outcome":"skipped","task":null,"tags":[],"profile":null}}"#,
+                time(27 * 60),
+                time(30 * 60)
+            ),
+            format!(
+                r#"{{"started_at":"{}","ended_at":"{}","phase":"work","planned_s":1500,"actual_s":480,"paused_s":120,"pauses":3,"outcome":"interrupted","task":"notes; a, b\\c\non the next line","tags":["a,b"],"profile":null}}"#,
+                time(30 * 60),
+                time(40 * 60)
+            ),
+        ];
+        assert_eq!(export(Format::Jsonl), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn writes_icalendar() {
+        let expected = [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            &format!(
+                "PRODID:-//concentrato//concentrato {}//EN",
+                env!("CARGO_PKG_VERSION")
+            ),
+            "CALSCALE:GREGORIAN",
+            "BEGIN:VEVENT",
+            "UID:1789999200000@concentrato",
+            "DTSTAMP:20260921T150000Z",
+            "DTSTART:20260921T140000Z",
+            "DTEND:20260921T142700Z",
+            "SUMMARY:write \"report\"\\, part 1",
+            "DESCRIPTION:Completed: 25m of focus out of 25m\\, paused once for 2m\\, profi",
+            " le deep-work",
+            "CATEGORIES:thesis,writing",
+            "TRANSP:OPAQUE",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:1790001000000@concentrato",
+            "DTSTAMP:20260921T150000Z",
+            "DTSTART:20260921T143000Z",
+            "DTEND:20260921T144000Z",
+            "SUMMARY:notes\\; a\\, b\\\\c\\non the next line",
+            "DESCRIPTION:Interrupted: 8m of focus out of 25m\\, paused 3 times for 2m",
+            "CATEGORIES:a\\,b",
+            "TRANSP:OPAQUE",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ];
+        assert_eq!(export(Format::Ics), expected.join("\r\n") + "\r\n");
+    }
+
+    #[test]
+    fn folds_icalendar_lines_at_75_octets() {
+        let line = format!("SUMMARY:{}", "x".repeat(67));
+        assert_eq!(folded(&line), format!("{line}\r\n"));
+
+        let line = format!("SUMMARY:{}", "x".repeat(150));
+        assert_eq!(
+            folded(&line),
+            format!(
+                "SUMMARY:{}\r\n {}\r\n {}\r\n",
+                "x".repeat(67),
+                "x".repeat(74),
+                "x".repeat(9)
+            )
+        );
+    }
+
+    #[test]
+    fn folds_icalendar_lines_between_characters() {
+        // Two octets each, so the first line stops at 74 rather than split
+        // one in half.
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        assert_eq!(
+            folded(&line),
+            format!("SUMMARY:{}\r\n {}\r\n", "é".repeat(33), "é".repeat(7))
+        );
+        for physical in folded(&format!("SUMMARY:{}", "日本語".repeat(30))).split("\r\n") {
+            assert!(physical.len() <= 75, "{physical:?} is too long");
+        }
+    }
+
+    #[test]
+    fn escapes_icalendar_text() {
+        assert_eq!(ics_text("plain"), "plain");
+        assert_eq!(ics_text("a,b;c\\d"), "a\\,b\\;c\\\\d");
+        assert_eq!(ics_text("one\r\ntwo\nthree"), "one\\ntwo\\nthree");
+    }
+}
@@ -0,0 +1,324 @@
+//! Daily goals, and the streaks of days that meet them.
+//!
+//! A goal is a number of pomodoros, meaning work sessions completed, or an
+//! amount of focus time, which counts every minute of work whether its
+//! session was completed or not. Days are local days, and a session counts
+//! towards the day it started on.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, Weekday};
+use concentrato_core::{format_duration, parse_duration};
+use serde::{Deserialize, Serialize};
+
+use crate::config::GoalConfig;
+use crate::protocol;
+
+/// What a day's work should add up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "WireGoal", into = "WireGoal")]
+pub enum Goal {
+    Pomodoros(u32),
+    Focus(Duration),
+}
+
+/// A goal as the protocol writes it: `{"pomodoros": 8}` or
+/// `{"focus_ms": 14400000}`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WireGoal {
+    Pomodoros(u32),
+    FocusMs(u64),
+}
+
+impl From<WireGoal> for Goal {
+    fn from(goal: WireGoal) -> Self {
+        match goal {
+            WireGoal::Pomodoros(count) => Goal::Pomodoros(count),
+            WireGoal::FocusMs(ms) => Goal::Focus(Duration::from_millis(ms)),
+        }
+    }
+}
+
+impl From<Goal> for WireGoal {
+    fn from(goal: Goal) -> Self {
+        match goal {
+            Goal::Pomodoros(count) => WireGoal::Pomodoros(count),
+            Goal::Focus(focus) => WireGoal::FocusMs(protocol::millis(focus)),
+        }
+    }
+}
+
+impl Goal {
+    pub fn met(self, day: Day) -> bool {
+        self.progress(day) >= 1.0
+    }
+
+    /// How far `day` got towards the goal, from 0 up; past 1 once it is
+    /// met.
+    pub fn progress(self, day: Day) -> f64 {
+        match self {
+            Goal::Pomodoros(0) => 1.0,
+            Goal::Pomodoros(goal) => f64::from(day.pomodoros) / f64::from(goal),
+            Goal::Focus(goal) if goal.is_zero() => 1.0,
+            Goal::Focus(goal) => day.focus.as_secs_f64() / goal.as_secs_f64(),
+        }
+    }
+
+    /// How far `day` got, as in `5/8 pomodoros` or `2h30m/4h`.
+    pub fn describe(self, day: Day) -> String {
+        match self {
+            Goal::Pomodoros(goal) => format!("{}/{goal} pomodoros", day.pomodoros),
+            Goal::Focus(goal) => {
+                // Whole minutes are as precise as a day's focus needs to be.
+                let focus = Duration::from_secs(day.focus.as_secs() / 60 * 60);
+                format!("{}/{}", format_duration(focus), format_duration(goal))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Goal::Pomodoros(1) => f.write_str("1 pomodoro"),
+            Goal::Pomodoros(count) => write!(f, "{count} pomodoros"),
+            Goal::Focus(focus) => write!(f, "{} of focus", format_duration(*focus)),
+        }
+    }
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    /// Reads a number of pomodoros, such as `8`, or a duration of focus
+    /// time, such as `4h`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let goal = match s.trim().parse::<u32>() {
+            Ok(count) => Goal::Pomodoros(count),
+            Err(_) => Goal::Focus(parse_duration(s).map_err(|err| err.to_string())?),
+        };
+        match goal {
+            Goal::Pomodoros(0) => Err("a goal of no pomodoros is no goal".to_owned()),
+            Goal::Focus(focus) if focus.is_zero() => {
+                Err("a goal of no focus time is no goal".to_owned())
+            }
+            goal => Ok(goal),
+        }
+    }
+}
+
+/// What the work sessions of a day added up to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Day {
+    /// Work sessions completed.
+    pub pomodoros: u32,
+    /// Time spent working, in sessions completed or not.
+    pub focus: Duration,
+}
+
+impl Day {
+    /// Counts a work session that ran for `focus`.
+    pub fn add(&mut self, focus: Duration, completed: bool) {
+        self.focus += focus;
+        self.pomodoros += u32::from(completed);
+    }
+}
+
+impl GoalConfig {
+    /// How many days in a row have met the goal, up to `today`, given what
+    /// each day before it added up to in `past` and what today has come to
+    /// `so_far`.
+    ///
+    /// A rest day that misses the goal carries the streak over without
+    /// adding to it, and so does today until it is over.
+    pub fn streak(&self, past: &BTreeMap<NaiveDate, Day>, today: NaiveDate, so_far: Day) -> u32 {
+        let Some(goal) = self.daily else {
+            return 0;
+        };
+        let mut streak = u32::from(goal.met(so_far));
+        let Some(&first) = past.keys().next() else {
+            return streak;
+        };
+        let mut day = today;
+        while let Some(previous) = day.pred_opt().filter(|&previous| previous >= first) {
+            day = previous;
+            if goal.met(past.get(&day).copied().unwrap_or_default()) {
+                streak += 1;
+            } else if !self.is_rest_day(day) {
+                break;
+            }
+        }
+        streak
+    }
+
+    pub fn is_rest_day(&self, day: NaiveDate) -> bool {
+        self.rest_days.contains(&day.weekday())
+    }
+}
+
+/// The local day `time` falls on.
+pub fn local_day(time: SystemTime) -> NaiveDate {
+    DateTime::<Local>::from(time).date_naive()
+}
+
+/// Parses a day of the week such as `sat` or `Saturday`.
+pub fn parse_weekday(s: &str) -> Result<Weekday, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("unknown day {s:?}; expected mon, tue, wed, thu, fri, sat or sun"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn pomodoros(pomodoros: u32) -> Day {
+        Day {
+            pomodoros,
+            focus: Duration::from_secs(u64::from(pomodoros) * 25 * 60),
+        }
+    }
+
+    /// Four pomodoros a day, with weekends off.
+    fn config() -> GoalConfig {
+        GoalConfig {
+            daily: Some(Goal::Pomodoros(4)),
+            rest_days: vec![Weekday::Sat, Weekday::Sun],
+        }
+    }
+
+    /// The days from Wednesday the 7th to Sunday the 11th, the streak
+    /// starting on Thursday.
+    fn week() -> BTreeMap<NaiveDate, Day> {
+        BTreeMap::from([
+            (date(7), pomodoros(1)),
+            (date(8), pomodoros(4)),
+            (date(9), pomodoros(5)),
+            (date(10), pomodoros(4)),
+            (date(11), pomodoros(0)),
+        ])
+    }
+
+    #[test]
+    fn carries_a_streak_over_a_rest_day() {
+        // Monday the 12th, after a Sunday that missed it.
+        assert_eq!(config().streak(&week(), date(12), pomodoros(4)), 4);
+    }
+
+    #[test]
+    fn breaks_a_streak_on_a_missed_working_day() {
+        let mut past = week();
+        past.insert(date(9), pomodoros(3));
+        assert_eq!(config().streak(&past, date(12), pomodoros(4)), 2);
+        // A working day with no sessions at all is missed too.
+        past.remove(&date(9));
+        assert_eq!(config().streak(&past, date(12), pomodoros(4)), 2);
+    }
+
+    #[test]
+    fn counts_today_once_it_is_met() {
+        assert_eq!(config().streak(&week(), date(12), pomodoros(0)), 3);
+        assert_eq!(config().streak(&week(), date(12), pomodoros(3)), 3);
+        assert_eq!(config().streak(&week(), date(12), pomodoros(4)), 4);
+    }
+
+    #[test]
+    fn counts_only_today_without_a_past() {
+        let past = BTreeMap::new();
+        assert_eq!(config().streak(&past, date(12), pomodoros(0)), 0);
+        assert_eq!(config().streak(&past, date(12), pomodoros(4)), 1);
+    }
+
+    #[test]
+    fn has_no_streak_without_a_goal() {
+        let config = GoalConfig::default();
+        assert_eq!(config.streak(&week(), date(12), pomodoros(4)), 0);
+    }
+
+    #[test]
+    fn parses_goals() {
+        assert_eq!("8".parse(), Ok(Goal::Pomodoros(8)));
+        assert_eq!(" 1 ".parse(), Ok(Goal::Pomodoros(1)));
+        assert_eq!("4h".parse(), Ok(Goal::Focus(Duration::from_secs(4 * 3600))));
+        assert_eq!(
+            "1h30m".parse(),
+            Ok(Goal::Focus(Duration::from_secs(90 * 60)))
+        );
+        assert!("soon".parse::<Goal>().is_err());
+    }
+
+    #[test]
+    fn rejects_empty_goals() {
+        assert_eq!(
+            "0".parse::<Goal>(),
+            Err("a goal of no pomodoros is no goal".to_owned())
+        );
+        for zero in ["0m", "0s", "0h0m"] {
+            assert_eq!(
+                zero.parse::<Goal>(),
+                Err("a goal of no focus time is no goal".to_owned()),
+                "{zero}"
+            );
+        }
+    }
+
+    #[test]
+    fn measures_progress() {
+        let day = Day {
+            pomodoros: 5,
+            focus: Duration::from_secs(2 * 3600 + 30 * 60 + 59),
+        };
+        let four_hours = Goal::Focus(Duration::from_secs(4 * 3600));
+        assert_eq!(Goal::Pomodoros(8).progress(day), 0.625);
+        assert!(!Goal::Pomodoros(8).met(day));
+        assert!(Goal::Pomodoros(5).met(day));
+        assert_eq!(Goal::Pomodoros(4).progress(day), 1.25);
+        assert!((four_hours.progress(day) - 9059.0 / 14400.0).abs() < 1e-9);
+        assert_eq!(Goal::Pomodoros(8).describe(day), "5/8 pomodoros");
+        assert_eq!(four_hours.describe(day), "2h30m/4h");
+        assert_eq!(four_hours.describe(Day::default()), "0s/4h");
+    }
+
+    #[test]
+    fn describes_goals() {
+        assert_eq!(Goal::Pomodoros(1).to_string(), "1 pomodoro");
+        assert_eq!(Goal::Pomodoros(8).to_string(), "8 pomodoros");
+        assert_eq!(
+            Goal::Focus(Duration::from_secs(4 * 3600)).to_string(),
+            "4h of focus"
+        );
+    }
+
+    #[test]
+    fn adds_up_a_day() {
+        let mut day = Day::default();
+        day.add(Duration::from_secs(25 * 60), true);
+        day.add(Duration::from_secs(10 * 60), false);
+        assert_eq!(
+            day,
+            Day {
+                pomodoros: 1,
+                focus: Duration::from_secs(35 * 60)
+            }
+        );
+    }
+
+    #[test]
+    fn parses_weekdays() {
+        assert_eq!(parse_weekday("sat"), Ok(Weekday::Sat));
+        assert_eq!(parse_weekday(" Saturday "), Ok(Weekday::Sat));
+        assert_eq!(parse_weekday("MON"), Ok(Weekday::Mon));
+        assert_eq!(
+            parse_weekday("funday"),
+            Err("unknown day \"funday\"; expected mon, tue, wed, thu, fri, sat or sun".to_owned())
+        );
+    }
+}
@@ -0,0 +1,725 @@
+//! The record of every session the timer has run, kept in an SQLite
+//! database under `$XDG_DATA_HOME`.
+//!
+//! The daemon adds a [`Session`] each time a phase that was started comes to
+//! an end, whether it ran its course, was skipped or was abandoned. Tags
+//! come from the task, which can carry words such as `#writing` that group
+//! sessions across tasks. Sessions other timers logged can be added too;
+//! see [`import`](crate::import).
+//!
+//! The schema is versioned with SQLite's `user_version`: opening a database
+//! brings it up to date by applying the [`MIGRATIONS`] it has yet to see,
+//! each in a transaction of its own.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::DirBuilder;
+use std::io;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::NaiveDate;
+use concentrato_core::Phase;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+use crate::goal::{self, Day};
+use crate::paths;
+
+/// The changes that make up the schema, in order. The database's
+/// `user_version` counts those already applied; a migration, once released,
+/// never changes.
+const MIGRATIONS: &[&str] = &[
+    // Times are milliseconds since the Unix epoch, and durations
+    // milliseconds.
+    "CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER NOT NULL,
+        phase TEXT NOT NULL,
+        planned_ms INTEGER NOT NULL,
+        actual_ms INTEGER NOT NULL,
+        paused_ms INTEGER NOT NULL,
+        outcome TEXT NOT NULL,
+        task TEXT,
+        profile TEXT
+    );
+    CREATE INDEX sessions_by_start ON sessions (started_at);
+    CREATE TABLE tags (
+        session INTEGER NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (session, tag)
+    );
+    CREATE INDEX tags_by_tag ON tags (tag);",
+    "ALTER TABLE sessions ADD COLUMN pauses INTEGER NOT NULL DEFAULT 0;",
+    // Where an imported session came from, such as `timewarrior`; NULL for
+    // the timer's own.
+    "ALTER TABLE sessions ADD COLUMN source TEXT;",
+];
+
+/// The columns [`session`] reads a session from, tags last.
+const COLUMNS: &str = "started_at, ended_at, phase, planned_ms, actual_ms, paused_ms, pauses,
+    outcome, task, profile,
+    (SELECT group_concat(tag, ' ') FROM tags WHERE tags.session = sessions.id)";
+
+/// How long to wait for another process writing to the database, such as a
+/// report running while the daemon records a session.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("cannot create {}: {source}", path.display())]
+    CreateDir { path: PathBuf, source: io::Error },
+    #[error("cannot open the history in {}: {source}", path.display())]
+    Open {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    #[error(
+        "the history in {} is from a newer concentrato (schema version {version})",
+        path.display()
+    )]
+    TooNew { path: PathBuf, version: usize },
+    #[error("history: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// One phase of the timer, from when it was first started to when it ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub started_at: SystemTime,
+    pub ended_at: SystemTime,
+    pub phase: Phase,
+    /// How long the phase was meant to last, extensions included.
+    pub planned: Duration,
+    /// How long it ran for, not counting pauses.
+    pub actual: Duration,
+    /// How long it spent paused.
+    pub paused: Duration,
+    /// How many times it was paused.
+    pub pauses: u32,
+    pub outcome: Outcome,
+    pub task: Option<String>,
+    pub tags: Vec<String>,
+    pub profile: Option<String>,
+}
+
+/// How a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// It ran for its full length.
+    Completed,
+    /// It was cut short and the timer moved on.
+    Skipped,
+    /// It was abandoned: stopped, reset or discarded after a suspend.
+    Interrupted,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 3] = [Outcome::Completed, Outcome::Skipped, Outcome::Interrupted];
+
+    /// The identifier used for this outcome in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Completed => "completed",
+            Outcome::Skipped => "skipped",
+            Outcome::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown outcome {0:?}, expected one of completed, skipped or interrupted")]
+pub struct ParseOutcomeError(String);
+
+impl FromStr for Outcome {
+    type Err = ParseOutcomeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Outcome::ALL
+            .into_iter()
+            .find(|outcome| outcome.as_str() == s)
+            .ok_or_else(|| ParseOutcomeError(s.to_owned()))
+    }
+}
+
+/// The tags in a task: its words that start with `#`, without the `#` and
+/// any punctuation after them, each once.
+pub fn tags(task: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in task.split_whitespace() {
+        let Some(tag) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag: String = tag
+            .chars()
+            .take_while(|&c| c.is_alphanumeric() || "-_/".contains(c))
+            .collect();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// How many sessions an [`History::import`] added, and how many it left
+/// out as already recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Imported {
+    pub added: usize,
+    pub overlapping: usize,
+}
+
+/// The database sessions are recorded in.
+#[derive(Debug)]
+pub struct History {
+    connection: Connection,
+}
+
+impl History {
+    /// `$XDG_DATA_HOME/concentrato/history.sqlite3`.
+    pub fn default_path() -> Option<PathBuf> {
+        paths::data_dir().map(|dir| dir.join("history.sqlite3"))
+    }
+
+    /// Opens the database at `path`, creating it if it does not exist yet
+    /// and bringing its schema up to date.
+    pub fn open(path: &Path) -> Result<Self, HistoryError> {
+        if let Some(dir) = path.parent() {
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .map_err(|source| HistoryError::CreateDir {
+                    path: dir.to_owned(),
+                    source,
+                })?;
+        }
+        let open_error = |source| HistoryError::Open {
+            path: path.to_owned(),
+            source,
+        };
+        let connection = Connection::open(path).map_err(open_error)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(open_error)?;
+        // Readers then never hold up the daemon's writes, nor it them.
+        connection
+            .pragma_update_and_check(None, "journal_mode", "wal", |_| Ok(()))
+            .map_err(open_error)?;
+        connection
+            .pragma_update(None, "foreign_keys", true)
+            .map_err(open_error)?;
+        let mut history = Self { connection };
+        history.migrate(path)?;
+        Ok(history)
+    }
+
+    fn migrate(&mut self, path: &Path) -> Result<(), HistoryError> {
+        let version: usize = self
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > MIGRATIONS.len() {
+            return Err(HistoryError::TooNew {
+                path: path.to_owned(),
+                version,
+            });
+        }
+        for (applied, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = self.connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", applied + 1)?;
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Records `session`, returning the id it is kept under.
+    pub fn add(&mut self, session: &Session) -> Result<i64, HistoryError> {
+        let transaction = self.connection.transaction()?;
+        let id = insert(&transaction, session, None)?;
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    /// Records the `sessions` another tool kept, naming it as their
+    /// `source`. A session that overlaps one already recorded, or one
+    /// earlier in `sessions`, is taken to be the same time logged twice and
+    /// left out. With `dry_run`, nothing is recorded, but the counts are
+    /// the same.
+    pub fn import(
+        &mut self,
+        sessions: &[Session],
+        source: &str,
+        dry_run: bool,
+    ) -> Result<Imported, HistoryError> {
+        let transaction = self.connection.transaction()?;
+        let mut imported = Imported::default();
+        {
+            let mut overlapping = transaction.prepare(
+                "SELECT EXISTS (SELECT 1 FROM sessions WHERE started_at < ?2 AND ended_at > ?1)",
+            )?;
+            for session in sessions {
+                let (start, end) = (
+                    Millis::from(session.started_at),
+                    Millis::from(session.ended_at),
+                );
+                if overlapping.query_row(params![start, end], |row| row.get(0))? {
+                    imported.overlapping += 1;
+                } else {
+                    insert(&transaction, session, Some(source))?;
+                    imported.added += 1;
+                }
+            }
+        }
+        if !dry_run {
+            transaction.commit()?;
+        }
+        Ok(imported)
+    }
+
+    /// Puts `session` in the place of the one recorded as `id`.
+    pub fn replace(&mut self, id: i64, session: &Session) -> Result<(), HistoryError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "UPDATE sessions SET started_at = ?2, ended_at = ?3, phase = ?4, planned_ms = ?5,
+                actual_ms = ?6, paused_ms = ?7, pauses = ?8, outcome = ?9, task = ?10,
+                profile = ?11
+            WHERE id = ?1",
+            params![
+                id,
+                Millis::from(session.started_at),
+                Millis::from(session.ended_at),
+                session.phase.as_str(),
+                Millis::from(session.planned),
+                Millis::from(session.actual),
+                Millis::from(session.paused),
+                session.pauses,
+                session.outcome.as_str(),
+                session.task,
+                session.profile,
+            ],
+        )?;
+        transaction.execute("DELETE FROM tags WHERE session = ?1", [id])?;
+        insert_tags(&transaction, id, &session.tags)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Records `session` as the rest of the one recorded as `id`, which
+    /// ended and was then taken up again: the two become one session, from
+    /// the start of the first to the end of `session`. If there is no
+    /// session `id` any more, `session` is added on its own.
+    pub fn carry_on(&mut self, id: i64, session: &Session) -> Result<i64, HistoryError> {
+        let Some(earlier) = self.get(id)? else {
+            return self.add(session);
+        };
+        let whole = Session {
+            started_at: earlier.started_at,
+            planned: earlier.planned + session.planned,
+            actual: earlier.actual + session.actual,
+            paused: earlier.paused + session.paused,
+            pauses: earlier.pauses + session.pauses,
+            ..session.clone()
+        };
+        self.replace(id, &whole)?;
+        Ok(id)
+    }
+
+    /// The session recorded as `id`, if there is one.
+    pub fn get(&self, id: i64) -> Result<Option<Session>, HistoryError> {
+        let session = self
+            .connection
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM sessions WHERE id = ?1"),
+                [id],
+                session,
+            )
+            .optional()?;
+        Ok(session)
+    }
+
+    /// What the work sessions of each day added up to.
+    pub fn days(&self) -> Result<BTreeMap<NaiveDate, Day>, HistoryError> {
+        let mut select = self
+            .connection
+            .prepare("SELECT started_at, actual_ms, outcome FROM sessions WHERE phase = 'work'")?;
+        let rows = select.query_map([], |row| {
+            Ok((
+                row.get::<_, Millis>(0)?,
+                row.get::<_, Millis>(1)?,
+                row.get::<_, Parsed<Outcome>>(2)?.0,
+            ))
+        })?;
+        let mut days: BTreeMap<NaiveDate, Day> = BTreeMap::new();
+        for row in rows {
+            let (started_at, actual, outcome) = row?;
+            days.entry(goal::local_day(started_at.into()))
+                .or_default()
+                .add(actual.into(), outcome == Outcome::Completed);
+        }
+        Ok(days)
+    }
+
+    /// The sessions that started from `from` until `to`, earliest first.
+    pub fn sessions(&self, from: SystemTime, to: SystemTime) -> Result<Vec<Session>, HistoryError> {
+        let mut select = self.connection.prepare(&format!(
+            "SELECT {COLUMNS} FROM sessions WHERE started_at >= ?1 AND started_at < ?2
+            ORDER BY started_at"
+        ))?;
+        let sessions = select
+            .query_map(params![Millis::from(from), Millis::from(to)], session)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(sessions)
+    }
+}
+
+fn insert(
+    connection: &Connection,
+    session: &Session,
+    source: Option<&str>,
+) -> rusqlite::Result<i64> {
+    connection.execute(
+        "INSERT INTO sessions (started_at, ended_at, phase, planned_ms, actual_ms,
+            paused_ms, pauses, outcome, task, profile, source)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            Millis::from(session.started_at),
+            Millis::from(session.ended_at),
+            session.phase.as_str(),
+            Millis::from(session.planned),
+            Millis::from(session.actual),
+            Millis::from(session.paused),
+            session.pauses,
+            session.outcome.as_str(),
+            session.task,
+            session.profile,
+            source,
+        ],
+    )?;
+    let id = connection.last_insert_rowid();
+    insert_tags(connection, id, &session.tags)?;
+    Ok(id)
+}
+
+fn insert_tags(connection: &Connection, id: i64, tags: &[String]) -> rusqlite::Result<()> {
+    let mut insert =
+        connection.prepare_cached("INSERT INTO tags (session, tag) VALUES (?1, ?2)")?;
+    for tag in tags {
+        insert.execute(params![id, tag])?;
+    }
+    Ok(())
+}
+
+/// Reads a session from the [`COLUMNS`].
+fn session(row: &Row<'_>) -> rusqlite::Result<Session> {
+    let tags: Option<String> = row.get(10)?;
+    Ok(Session {
+        started_at: row.get::<_, Millis>(0)?.into(),
+        ended_at: row.get::<_, Millis>(1)?.into(),
+        phase: row.get::<_, Parsed<Phase>>(2)?.0,
+        planned: row.get::<_, Millis>(3)?.into(),
+        actual: row.get::<_, Millis>(4)?.into(),
+        paused: row.get::<_, Millis>(5)?.into(),
+        pauses: row.get(6)?,
+        outcome: row.get::<_, Parsed<Outcome>>(7)?.0,
+        task: row.get(8)?,
+        tags: tags
+            .map(|tags| tags.split(' ').map(str::to_owned).collect())
+            .unwrap_or_default(),
+        profile: row.get(9)?,
+    })
+}
+
+/// A time or duration as the database keeps it, in milliseconds.
+#[derive(Debug, Clone, Copy)]
+struct Millis(i64);
+
+impl From<SystemTime> for Millis {
+    /// Times before the epoch are taken as the epoch; no session starts
+    /// there.
+    fn from(time: SystemTime) -> Self {
+        Self::from(time.duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+}
+
+impl From<Duration> for Millis {
+    fn from(duration: Duration) -> Self {
+        Self(i64::try_from(duration.as_millis()).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<Millis> for Duration {
+    fn from(millis: Millis) -> Self {
+        Duration::from_millis(u64::try_from(millis.0).unwrap_or_default())
+    }
+}
+
+impl From<Millis> for SystemTime {
+    fn from(millis: Millis) -> Self {
+        UNIX_EPOCH + Duration::from(millis)
+    }
+}
+
+impl ToSql for Millis {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        self.0.to_sql()
+    }
+}
+
+impl FromSql for Millis {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Self)
+    }
+}
+
+/// A column holding the text form of `T`.
+struct Parsed<T>(T);
+
+impl<T: FromStr> FromSql for Parsed<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map(Parsed)
+            .map_err(|err| FromSqlError::Other(Box::new(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_790_000_000 + secs)
+    }
+
+    fn work(start: u64, end: u64) -> Session {
+        let actual = Duration::from_secs(end - start);
+        Session {
+            started_at: at(start),
+            ended_at: at(end),
+            phase: Phase::Work,
+            planned: actual,
+            actual,
+            paused: Duration::ZERO,
+            pauses: 0,
+            outcome: Outcome::Completed,
+            task: None,
+            tags: Vec::new(),
+            profile: None,
+        }
+    }
+
+    /// An in-memory database with only the first `version` migrations
+    /// applied, as an older concentrato left it.
+    fn history_at(version: usize) -> History {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "foreign_keys", true)
+            .unwrap();
+        for migration in &MIGRATIONS[..version] {
+            connection.execute_batch(migration).unwrap();
+        }
+        connection
+            .pragma_update(None, "user_version", version)
+            .unwrap();
+        History { connection }
+    }
+
+    fn migrated(mut history: History) -> History {
+        history.migrate(Path::new(":memory:")).unwrap();
+        history
+    }
+
+    fn version(history: &History) -> usize {
+        history
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    fn source(history: &History, id: i64) -> Option<String> {
+        history
+            .connection
+            .query_row("SELECT source FROM sessions WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    /// The session each version's test rows stand for.
+    fn recorded() -> Session {
+        Session {
+            planned: Duration::from_secs(25 * 60),
+            paused: Duration::from_secs(60),
+            task: Some("draft #thesis".to_owned()),
+            tags: vec!["thesis".to_owned()],
+            profile: Some("deep".to_owned()),
+            ..work(0, 20 * 60)
+        }
+    }
+
+    #[test]
+    fn creates_the_schema_from_nothing() {
+        let mut history = migrated(history_at(0));
+        assert_eq!(version(&history), MIGRATIONS.len());
+        let id = history.add(&recorded()).unwrap();
+        assert_eq!(history.get(id).unwrap(), Some(recorded()));
+        assert_eq!(source(&history, id), None);
+    }
+
+    #[test]
+    fn keeps_the_data_of_each_version() {
+        let row = "1790000000000, 1790001200000, 'work', 1500000, 1200000, 60000";
+        let rest = "'completed', 'draft #thesis', 'deep'";
+        let inserts = [
+            format!(
+                "INSERT INTO sessions (started_at, ended_at, phase, planned_ms, actual_ms,
+                    paused_ms, outcome, task, profile)
+                VALUES ({row}, {rest})"
+            ),
+            format!(
+                "INSERT INTO sessions (started_at, ended_at, phase, planned_ms, actual_ms,
+                    paused_ms, pauses, outcome, task, profile)
+                VALUES ({row}, 2, {rest})"
+            ),
+            format!(
+                "INSERT INTO sessions (started_at, ended_at, phase, planned_ms, actual_ms,
+                    paused_ms, pauses, outcome, task, profile, source)
+                VALUES ({row}, 2, {rest}, 'csv')"
+            ),
+        ];
+        assert_eq!(inserts.len(), MIGRATIONS.len(), "a version has no test row");
+        for (applied, insert) in (1..).zip(&inserts) {
+            let history = history_at(applied);
+            history.connection.execute_batch(insert).unwrap();
+            let id = history.connection.last_insert_rowid();
+            history
+                .connection
+                .execute(
+                    "INSERT INTO tags (session, tag) VALUES (?1, 'thesis')",
+                    [id],
+                )
+                .unwrap();
+
+            let history = migrated(history);
+            assert_eq!(version(&history), MIGRATIONS.len());
+            let pauses = if applied < 2 { 0 } else { 2 };
+            assert_eq!(
+                history.get(id).unwrap(),
+                Some(Session {
+                    pauses,
+                    ..recorded()
+                }),
+                "from version {applied}"
+            );
+            let expected = if applied < 3 { None } else { Some("csv") };
+            assert_eq!(source(&history, id).as_deref(), expected);
+        }
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let mut history = migrated(history_at(0));
+        let id = history.add(&recorded()).unwrap();
+        let history = migrated(history);
+        assert_eq!(version(&history), MIGRATIONS.len());
+        assert_eq!(history.get(id).unwrap(), Some(recorded()));
+    }
+
+    fn all(history: &History) -> Vec<Session> {
+        history.sessions(at(0), at(24 * 3600)).unwrap()
+    }
+
+    #[test]
+    fn imports_only_what_does_not_overlap() {
+        let mut history = migrated(history_at(0));
+        history.add(&work(3600, 5100)).unwrap();
+        let sessions = [
+            work(0, 1500),
+            // Overlapping the recorded session by a minute at either end.
+            work(2700, 3660),
+            work(5040, 6000),
+            // Just touching it.
+            work(5100, 6600),
+            // Overlapping one earlier in the same file.
+            work(6000, 7000),
+        ];
+        let imported = history.import(&sessions, "csv", false).unwrap();
+        assert_eq!(
+            imported,
+            Imported {
+                added: 2,
+                overlapping: 3
+            }
+        );
+        assert_eq!(
+            all(&history),
+            [work(0, 1500), work(3600, 5100), work(5100, 6600)]
+        );
+        let ids: Vec<i64> = history
+            .connection
+            .prepare("SELECT id FROM sessions ORDER BY started_at")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let sources: Vec<_> = ids.iter().map(|&id| source(&history, id)).collect();
+        assert_eq!(
+            sources,
+            [Some("csv".to_owned()), None, Some("csv".to_owned())]
+        );
+
+        // The same file a second time adds nothing.
+        let again = history.import(&sessions, "csv", false).unwrap();
+        assert_eq!(
+            again,
+            Imported {
+                added: 0,
+                overlapping: 5
+            }
+        );
+        assert_eq!(all(&history).len(), 3);
+    }
+
+    #[test]
+    fn a_dry_run_counts_without_recording() {
+        let mut history = migrated(history_at(0));
+        history.add(&work(3600, 5100)).unwrap();
+        let sessions = [work(0, 1500), work(1500, 3000), work(3000, 4000)];
+        let imported = history.import(&sessions, "csv", true).unwrap();
+        assert_eq!(
+            imported,
+            Imported {
+                added: 2,
+                overlapping: 1
+            }
+        );
+        assert_eq!(all(&history), [work(3600, 5100)]);
+        assert_eq!(history.import(&sessions, "csv", true).unwrap(), imported);
+    }
+
+    #[test]
+    fn refuses_a_newer_schema() {
+        let mut history = history_at(MIGRATIONS.len());
+        history
+            .connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        let err = history.migrate(Path::new("history.sqlite3")).unwrap_err();
+        assert!(
+            matches!(err, HistoryError::TooNew { version, .. } if version == MIGRATIONS.len() + 1)
+        );
+    }
+}
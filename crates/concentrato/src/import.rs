@@ -0,0 +1,159 @@
+//! Sessions logged by other timers, read into [`Session`]s for the
+//! [`history`](crate::history).
+//!
+//! Two kinds of file are understood. CSV covers the exports of most
+//! pomodoro apps, and concentrato's own: columns are recognised by their
+//! headers, such as `start`, `end`, `duration`, `task` and `tags`.
+//! Timewarrior's intervals come from its data directory or from
+//! `timew export`. What another timer cannot say, such as how long a session
+//! was paused, is left at nothing.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use std::{fs, io};
+
+use concentrato_core::Phase;
+
+use crate::history::{Outcome, Session};
+
+mod csv;
+mod timewarrior;
+
+/// A kind of file sessions can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Csv,
+    Timewarrior,
+}
+
+impl Source {
+    pub const ALL: [Source; 2] = [Source::Csv, Source::Timewarrior];
+
+    /// The identifier used for this source on the command line and in the
+    /// history.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Csv => "csv",
+            Source::Timewarrior => "timewarrior",
+        }
+    }
+
+    /// Reads the sessions in `path`, which for Timewarrior can also be its
+    /// data directory.
+    pub fn read(self, path: &Path) -> Result<Vec<Session>, ImportError> {
+        match self {
+            Source::Csv => csv::parse(&read(path)?).map_err(|err| err.at(path)),
+            Source::Timewarrior => timewarrior::read(path),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown source {0:?}; expected csv or timewarrior")]
+pub struct ParseSourceError(String);
+
+impl FromStr for Source {
+    type Err = ParseSourceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Source::ALL
+            .into_iter()
+            .find(|source| source.as_str() == s)
+            .ok_or_else(|| ParseSourceError(s.to_owned()))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("{}, line {line}: {message}", path.display())]
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// What is wrong with a line of a file, before it is known which file.
+#[derive(Debug)]
+struct LineError {
+    line: usize,
+    message: String,
+}
+
+impl LineError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+
+    fn at(self, path: &Path) -> ImportError {
+        ImportError::Syntax {
+            path: path.to_owned(),
+            line: self.line,
+            message: self.message,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, ImportError> {
+    fs::read_to_string(path).map_err(|source| ImportError::Read {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// A completed work session from `start` to `end`, as other timers log
+/// their time.
+fn focus(start: SystemTime, end: SystemTime, task: Option<String>, tags: Vec<String>) -> Session {
+    let actual = end.duration_since(start).unwrap_or_default();
+    Session {
+        started_at: start,
+        ended_at: end,
+        phase: Phase::Work,
+        planned: actual,
+        actual,
+        paused: Duration::ZERO,
+        pauses: 0,
+        outcome: Outcome::Completed,
+        task,
+        tags,
+        profile: None,
+    }
+}
+
+/// `words` as tags, which like those in a task are made of letters, digits,
+/// `-`, `_` and `/`: anything else becomes a dash, as in `client-work`.
+fn tags<'a>(words: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in words {
+        let tag: String = word
+            .trim()
+            .trim_start_matches('#')
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || "-_/".contains(c) {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let tag = tag.trim_matches('-');
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
+}
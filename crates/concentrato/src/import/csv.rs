@@ -0,0 +1,731 @@
+//! CSV exports, with a header naming the columns.
+//!
+//! Pomodoro apps agree on little beyond a row per session, so columns are
+//! found by a list of the names each is known by: a start time is needed,
+//! and either an end time or a duration. Fields may be separated by commas,
+//! semicolons or tabs, whichever the header uses most.
+
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use concentrato_core::{Phase, parse_duration};
+
+use super::{LineError, tags};
+use crate::history::{self, Outcome, Session};
+
+const STARTS: &[&str] = &[
+    "started_at",
+    "start",
+    "started",
+    "begin",
+    "start_datetime",
+    "start_time",
+    "begin_time",
+    "time",
+    "from",
+];
+const START_DATES: &[&str] = &["start_date", "date", "day"];
+const ENDS: &[&str] = &[
+    "ended_at",
+    "end",
+    "ended",
+    "stop",
+    "stopped",
+    "finish",
+    "finished_at",
+    "end_datetime",
+    "end_time",
+    "stop_time",
+    "to",
+];
+const END_DATES: &[&str] = &["end_date", "stop_date"];
+const DURATIONS: &[(&[&str], Unit)] = &[
+    (&["actual_ms", "duration_ms"], Unit::Millis),
+    (
+        &[
+            "actual_s",
+            "duration_s",
+            "duration_sec",
+            "duration_secs",
+            "duration_seconds",
+            "seconds",
+            "secs",
+        ],
+        Unit::Seconds,
+    ),
+    (
+        &[
+            "duration_m",
+            "duration_min",
+            "duration_mins",
+            "duration_minutes",
+            "minutes",
+            "mins",
+        ],
+        Unit::Minutes,
+    ),
+    (
+        &["duration", "length", "time_spent", "elapsed", "actual"],
+        Unit::Any,
+    ),
+];
+const PLANNED: &[(&[&str], Unit)] = &[
+    (&["planned_ms"], Unit::Millis),
+    (&["planned_s"], Unit::Seconds),
+    (&["planned_min", "planned_minutes"], Unit::Minutes),
+    (&["planned", "planned_duration", "target"], Unit::Any),
+];
+const PAUSED: &[(&[&str], Unit)] = &[
+    (&["paused_ms"], Unit::Millis),
+    (&["paused_s"], Unit::Seconds),
+    (&["paused"], Unit::Any),
+];
+const PAUSES: &[&str] = &["pauses", "interruptions"];
+const PHASES: &[&str] = &["phase", "type", "kind", "session_type", "mode"];
+const OUTCOMES: &[&str] = &["outcome", "status", "result", "completed", "done"];
+const TASKS: &[&str] = &[
+    "task",
+    "title",
+    "name",
+    "description",
+    "activity",
+    "label",
+    "subject",
+    "summary",
+    "note",
+    "notes",
+];
+/// Columns of tags, several to a field.
+const TAG_LISTS: &[&str] = &["tags", "tag", "categories", "labels"];
+/// Columns that each make one tag, such as a project's name.
+const TAG_NAMES: &[&str] = &["project", "category", "client"];
+const PROFILES: &[&str] = &["profile"];
+
+/// What a column of durations counts in.
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Millis,
+    Seconds,
+    Minutes,
+    /// Whatever the field says, as in `1:25:00`, `25:00`, `1h25m` or `25`
+    /// (minutes).
+    Any,
+}
+
+/// Where each part of a session is in a row.
+#[derive(Debug)]
+struct Columns {
+    start: usize,
+    start_date: Option<usize>,
+    end: Option<usize>,
+    end_date: Option<usize>,
+    duration: Option<(usize, Unit)>,
+    planned: Option<(usize, Unit)>,
+    paused: Option<(usize, Unit)>,
+    pauses: Option<usize>,
+    phase: Option<usize>,
+    outcome: Option<usize>,
+    task: Option<usize>,
+    tag_lists: Vec<usize>,
+    tag_names: Vec<usize>,
+    profile: Option<usize>,
+}
+
+impl Columns {
+    fn find(header: &[String]) -> Result<Self, LineError> {
+        let find = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| header.iter().position(|column| column == name))
+        };
+        let find_duration = |kinds: &[(&[&str], Unit)]| {
+            kinds
+                .iter()
+                .find_map(|(names, unit)| find(names).map(|column| (column, *unit)))
+        };
+        let all = |names: &[&str]| {
+            (0..header.len())
+                .filter(|&column| names.contains(&header[column].as_str()))
+                .collect()
+        };
+        let start = find(STARTS).ok_or_else(|| {
+            LineError::new(
+                1,
+                "no column of start times; expected one headed start or started_at",
+            )
+        })?;
+        let columns = Self {
+            start,
+            start_date: find(START_DATES).filter(|&column| column != start),
+            end: find(ENDS),
+            end_date: find(END_DATES),
+            duration: find_duration(DURATIONS),
+            planned: find_duration(PLANNED),
+            paused: find_duration(PAUSED),
+            pauses: find(PAUSES),
+            phase: find(PHASES),
+            outcome: find(OUTCOMES),
+            task: find(TASKS),
+            tag_lists: all(TAG_LISTS),
+            tag_names: all(TAG_NAMES),
+            profile: find(PROFILES),
+        };
+        if columns.end.is_none() && columns.duration.is_none() {
+            return Err(LineError::new(
+                1,
+                "no column of end times or durations; expected one headed end or duration",
+            ));
+        }
+        Ok(columns)
+    }
+}
+
+/// Reads the sessions in a CSV file's `text`.
+pub(super) fn parse(text: &str) -> Result<Vec<Session>, LineError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = records(text, delimiter(text))?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = header.iter().map(|name| normalize(name)).collect();
+    let columns = Columns::find(&header)?;
+    records
+        .map(|(line, fields)| session(&columns, &fields).map_err(|err| LineError::new(line, err)))
+        .collect()
+}
+
+/// Reads the session in one row.
+fn session(columns: &Columns, fields: &[String]) -> Result<Session, String> {
+    let field = |column: Option<usize>| {
+        column
+            .and_then(|column| fields.get(column))
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+    };
+    let duration = |column: Option<(usize, Unit)>| {
+        column
+            .and_then(|(column, unit)| field(Some(column)).map(|field| duration(field, unit)))
+            .transpose()
+    };
+
+    let start_date = field(columns.start_date).map(date).transpose()?;
+    let start = field(Some(columns.start)).ok_or("no start time")?;
+    let started_at = match time(start)? {
+        Time::At(time) => time,
+        Time::OfDay(of_day) => {
+            let day = start_date.ok_or_else(|| format!("no date for the start time {start:?}"))?;
+            local(day.and_time(of_day))
+        }
+    };
+    let paused = duration(columns.paused)?.unwrap_or_default();
+    let ended_at = match field(columns.end) {
+        Some(end) => Some(match time(end)? {
+            Time::At(time) => time,
+            Time::OfDay(of_day) => {
+                let day = match field(columns.end_date) {
+                    Some(end_date) => date(end_date)?,
+                    None => DateTime::<Local>::from(started_at).date_naive(),
+                };
+                let mut ended_at = local(day.and_time(of_day));
+                // A session that ran past midnight ends on the next day.
+                if ended_at < started_at && field(columns.end_date).is_none() {
+                    ended_at = local(day.succ_opt().unwrap_or(day).and_time(of_day));
+                }
+                ended_at
+            }
+        }),
+        None => None,
+    };
+    let (ended_at, actual) = match (ended_at, duration(columns.duration)?) {
+        (Some(ended_at), Some(actual)) => (ended_at, actual),
+        (Some(ended_at), None) => {
+            let wall = ended_at.duration_since(started_at).unwrap_or_default();
+            (ended_at, wall.saturating_sub(paused))
+        }
+        (None, Some(actual)) => {
+            let ended_at = started_at
+                .checked_add(actual)
+                .and_then(|ended_at| ended_at.checked_add(paused))
+                .ok_or("it ends too far in the future to record")?;
+            (ended_at, actual)
+        }
+        (None, None) => return Err("no end time or duration".to_owned()),
+    };
+    if ended_at < started_at {
+        return Err("it ends before it starts".to_owned());
+    }
+
+    let task = field(columns.task).map(str::to_owned);
+    let mut words: Vec<&str> = Vec::new();
+    let task_tags = task.as_deref().map(history::tags).unwrap_or_default();
+    words.extend(task_tags.iter().map(String::as_str));
+    for &column in &columns.tag_lists {
+        if let Some(list) = field(Some(column)) {
+            words.extend(list.split([',', ';', ' ', '\t']));
+        }
+    }
+    for &column in &columns.tag_names {
+        words.extend(field(Some(column)));
+    }
+
+    Ok(Session {
+        started_at,
+        ended_at,
+        phase: field(columns.phase)
+            .map(phase)
+            .transpose()?
+            .unwrap_or(Phase::Work),
+        planned: duration(columns.planned)?.unwrap_or(actual),
+        actual,
+        paused,
+        pauses: field(columns.pauses)
+            .map(|pauses| {
+                pauses
+                    .parse()
+                    .map_err(|_| format!("{pauses:?} is not a number of pauses"))
+            })
+            .transpose()?
+            .unwrap_or_default(),
+        outcome: field(columns.outcome)
+            .map(outcome)
+            .transpose()?
+            .unwrap_or(Outcome::Completed),
+        task,
+        tags: tags(words),
+        profile: field(columns.profile).map(str::to_owned),
+    })
+}
+
+/// A header as in the lists of names above: `Start Time` becomes
+/// `start_time`, and `Duration (minutes)` becomes `duration_minutes`.
+fn normalize(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Whichever of a comma, semicolon or tab the first line has most of.
+fn delimiter(text: &str) -> char {
+    let header = text.lines().next().unwrap_or_default();
+    [',', ';', '\t']
+        .into_iter()
+        .rev()
+        .max_by_key(|&delimiter| header.matches(delimiter).count())
+        .unwrap_or(',')
+}
+
+/// Splits `text` into rows of fields, each with the line it starts on.
+/// Fields may be quoted, with `""` for a quote inside one; blank lines are
+/// skipped.
+fn records(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, LineError> {
+    let mut records = Vec::new();
+    let (mut fields, mut field) = (Vec::new(), String::new());
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\n' if quoted => {
+                field.push(c);
+                line += 1;
+            }
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                if fields.iter().any(|field| !field.trim().is_empty()) {
+                    records.push((start, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                start = line;
+            }
+            '\r' if !quoted => {}
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(LineError::new(start, "a quoted field is never closed"));
+    }
+    fields.push(field);
+    if fields.iter().any(|field| !field.trim().is_empty()) {
+        records.push((start, fields));
+    }
+    Ok(records)
+}
+
+/// A time as a field gives it.
+enum Time {
+    At(SystemTime),
+    /// A time of day, whose date is in another column.
+    OfDay(NaiveTime),
+}
+
+/// Reads a time such as `2026-10-14T10:00:00+02:00`, `2026-10-14 10:00` in
+/// local time, `10:00`, or seconds or milliseconds since the epoch.
+fn time(s: &str) -> Result<Time, String> {
+    const LOCAL: &[&str] = &[
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+    ];
+    const OF_DAY: &[&str] = &["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        let n: u64 = s
+            .parse()
+            .map_err(|_| format!("{s:?} is too large a time"))?;
+        // Seconds would not reach twelve digits for thousands of years.
+        let since_epoch = if s.len() >= 12 {
+            Duration::from_millis(n)
+        } else {
+            Duration::from_secs(n)
+        };
+        return Ok(Time::At(SystemTime::UNIX_EPOCH + since_epoch));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S %z"))
+    {
+        return Ok(Time::At(time.into()));
+    }
+    if let Some(time) = LOCAL
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    {
+        return Ok(Time::At(local(time)));
+    }
+    OF_DAY
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(s, format).ok())
+        .map(Time::OfDay)
+        .ok_or_else(|| format!("{s:?} is not a time such as 2026-10-14 10:00"))
+}
+
+fn date(s: &str) -> Result<NaiveDate, String> {
+    ["%Y-%m-%d", "%Y/%m/%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+        .ok_or_else(|| format!("{s:?} is not a date such as 2026-10-14"))
+}
+
+/// `time` in the local time zone; a time skipped by a change of clocks is
+/// taken as UTC.
+fn local(time: NaiveDateTime) -> SystemTime {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&time))
+        .into()
+}
+
+fn duration(s: &str, unit: Unit) -> Result<Duration, String> {
+    let number = || {
+        s.parse::<f64>()
+            .map_err(|_| format!("{s:?} is not a duration"))
+    };
+    // Negative, not a number, or too long to keep.
+    let secs = |secs: f64| {
+        Duration::try_from_secs_f64(secs).map_err(|_| format!("{s:?} is not a valid duration"))
+    };
+    match unit {
+        Unit::Millis => secs(number()? / 1000.0),
+        Unit::Seconds => secs(number()?),
+        Unit::Minutes => secs(number()? * 60.0),
+        Unit::Any if s.contains(':') => {
+            let parts: Option<Vec<u64>> = s.split(':').map(|part| part.parse().ok()).collect();
+            let secs = match parts.as_deref() {
+                Some(&[minutes, secs]) => minutes.checked_mul(60).and_then(|m| m.checked_add(secs)),
+                Some(&[hours, minutes, secs]) => hours
+                    .checked_mul(3600)
+                    .zip(minutes.checked_mul(60))
+                    .and_then(|(h, m)| h.checked_add(m))
+                    .and_then(|hm| hm.checked_add(secs)),
+                _ => return Err(format!("{s:?} is not a duration such as 25:00")),
+            };
+            secs.map(Duration::from_secs)
+                .ok_or_else(|| format!("{s:?} is too long a duration"))
+        }
+        Unit::Any => parse_duration(s).map_err(|err| err.to_string()),
+    }
+}
+
+fn phase(s: &str) -> Result<Phase, String> {
+    match normalize(s).as_str() {
+        "work" | "focus" | "pomodoro" | "session" | "work_session" => Ok(Phase::Work),
+        "short_break" | "break" | "short" => Ok(Phase::ShortBreak),
+        "long_break" | "long" => Ok(Phase::LongBreak),
+        _ => Err(format!("{s:?} is not a phase such as work or break")),
+    }
+}
+
+fn outcome(s: &str) -> Result<Outcome, String> {
+    match normalize(s).as_str() {
+        "completed" | "complete" | "done" | "finished" | "success" | "true" | "yes" | "1" => {
+            Ok(Outcome::Completed)
+        }
+        "skipped" | "skip" => Ok(Outcome::Skipped),
+        "interrupted" | "abandoned" | "cancelled" | "canceled" | "stopped" | "incomplete"
+        | "failed" | "false" | "no" | "0" => Ok(Outcome::Interrupted),
+        _ => Err(format!(
+            "{s:?} is not an outcome such as completed or abandoned"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::export::Format;
+
+    /// `secs` after 2026-10-14 08:00:00 UTC.
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_791_964_800 + secs)
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn one(text: &str) -> Session {
+        let mut sessions = parse(text).unwrap();
+        assert_eq!(sessions.len(), 1, "{sessions:?}");
+        sessions.remove(0)
+    }
+
+    fn invalid(text: &str) -> (usize, String) {
+        let err = parse(text).unwrap_err();
+        (err.line, err.message)
+    }
+
+    #[test]
+    fn reads_back_its_own_export() {
+        let sessions = vec![
+            Session {
+                started_at: at(0),
+                ended_at: at(27 * 60),
+                phase: Phase::Work,
+                planned: minutes(25),
+                actual: minutes(25),
+                paused: minutes(2),
+                pauses: 1,
+                outcome: Outcome::Completed,
+                task: Some("write \"report\", part 1 #thesis".to_owned()),
+                tags: vec!["thesis".to_owned(), "writing".to_owned()],
+                profile: Some("deep-work".to_owned()),
+            },
+            Session {
+                started_at: at(27 * 60),
+                ended_at: at(30 * 60),
+                phase: Phase::ShortBreak,
+                planned: minutes(5),
+                actual: minutes(3),
+                paused: Duration::ZERO,
+                pauses: 0,
+                outcome: Outcome::Skipped,
+                task: None,
+                tags: Vec::new(),
+                profile: None,
+            },
+        ];
+        let mut out = Vec::new();
+        Format::Csv.write(&sessions, &mut out).unwrap();
+        assert_eq!(parse(&String::from_utf8(out).unwrap()).unwrap(), sessions);
+    }
+
+    #[test]
+    fn finds_columns_by_their_headers() {
+        let session = one(
+            "\u{feff}Start Time;Duration (minutes);Project;Title;Type;Status\n\
+             2026-10-14T08:00:00Z;25;Client Work;chapter 3 #draft;pomodoro;abandoned\n",
+        );
+        assert_eq!(session.started_at, at(0));
+        assert_eq!(session.ended_at, at(25 * 60));
+        assert_eq!(session.actual, minutes(25));
+        assert_eq!(session.planned, minutes(25));
+        assert_eq!(session.phase, Phase::Work);
+        assert_eq!(session.outcome, Outcome::Interrupted);
+        assert_eq!(session.task.as_deref(), Some("chapter 3 #draft"));
+        assert_eq!(session.tags, ["draft", "Client-Work"]);
+    }
+
+    #[test]
+    fn picks_the_delimiter_the_header_uses_most() {
+        assert_eq!(delimiter("start,end\n"), ',');
+        assert_eq!(delimiter("start;end;task, with comma\n"), ';');
+        assert_eq!(delimiter("start\tend\n"), '\t');
+        assert_eq!(delimiter("start\n"), ',');
+    }
+
+    #[test]
+    fn reads_durations_in_the_unit_of_their_column() {
+        let actual = |header: &str, field: &str| {
+            one(&format!("start,{header}\n2026-10-14T08:00:00Z,{field}\n")).actual
+        };
+        assert_eq!(actual("Duration (ms)", "1500000"), minutes(25));
+        assert_eq!(actual("duration_ms", "1500"), Duration::from_millis(1500));
+        assert_eq!(actual("seconds", "90.5"), Duration::from_millis(90_500));
+        assert_eq!(actual("mins", "25"), minutes(25));
+        assert_eq!(actual("duration", "1:25:00"), minutes(85));
+        assert_eq!(
+            actual("duration", "25:30"),
+            minutes(25) + Duration::from_secs(30)
+        );
+        assert_eq!(actual("duration", "1h25m"), minutes(85));
+        assert_eq!(actual("duration", "25"), minutes(25));
+    }
+
+    #[test]
+    fn rejects_durations_out_of_range() {
+        let duration = |header: &str, field: &str| {
+            invalid(&format!(
+                "start,{header}\n2026-10-14T08:00:00Z,25\n2026-10-14T09:00:00Z,{field}\n"
+            ))
+        };
+        let not_valid = |field: &str| (3, format!("{field:?} is not a valid duration"));
+        assert_eq!(duration("seconds", "-5"), not_valid("-5"));
+        assert_eq!(duration("seconds", "NaN"), not_valid("NaN"));
+        assert_eq!(duration("seconds", "inf"), not_valid("inf"));
+        assert_eq!(duration("seconds", "1e300"), not_valid("1e300"));
+        assert_eq!(duration("minutes", "1e18"), not_valid("1e18"));
+        assert_eq!(
+            duration("seconds", "soon"),
+            (3, "\"soon\" is not a duration".to_owned())
+        );
+        for field in ["18446744073709551615:00", "5124095576030432:00:00"] {
+            assert_eq!(
+                duration("duration", field),
+                (3, format!("{field:?} is too long a duration"))
+            );
+        }
+        assert_eq!(
+            duration("duration", "1:2:3:4"),
+            (3, "\"1:2:3:4\" is not a duration such as 25:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_a_session_that_would_end_out_of_time() {
+        assert_eq!(
+            invalid("start,seconds\n2026-10-14T08:00:00Z,1.8e19\n"),
+            (2, "it ends too far in the future to record".to_owned())
+        );
+    }
+
+    #[test]
+    fn takes_the_date_from_its_own_column() {
+        let session = one("date,start,end\n2026-10-14,23:45,00:15\n");
+        let start = NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(23, 45, 0)
+            .unwrap();
+        assert_eq!(session.started_at, local(start));
+        // It ran past midnight.
+        assert_eq!(session.actual, minutes(30));
+        assert_eq!(
+            invalid("start,end\n23:45,00:15\n"),
+            (2, "no date for the start time \"23:45\"".to_owned())
+        );
+    }
+
+    #[test]
+    fn reads_times_in_every_form() {
+        let started_at = |start: &str| one(&format!("start,minutes\n{start},25\n")).started_at;
+        assert_eq!(started_at("2026-10-14T10:00:00+02:00"), at(0));
+        assert_eq!(started_at("2026-10-14 08:00:00 +0000"), at(0));
+        assert_eq!(started_at("1791964800"), at(0));
+        assert_eq!(started_at("1791964800000"), at(0));
+        let local_start = NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        assert_eq!(started_at("2026-10-14 08:00"), local(local_start));
+        assert_eq!(started_at("2026/10/14 08:00:00"), local(local_start));
+    }
+
+    #[test]
+    fn keeps_quoted_fields_whole() {
+        let sessions = parse(
+            "start,minutes,task\n\
+             2026-10-14T08:00:00Z,25,\"two\nlines, \"\"quoted\"\"\"\n\
+             \n\
+             2026-10-14T09:00:00Z,25,plain\n",
+        )
+        .unwrap();
+        let tasks: Vec<_> = sessions.iter().map(|s| s.task.as_deref()).collect();
+        assert_eq!(tasks, [Some("two\nlines, \"quoted\""), Some("plain")]);
+    }
+
+    #[test]
+    fn reports_the_line_a_row_starts_on() {
+        let text = "start,minutes,task\n\
+                    2026-10-14T08:00:00Z,25,\"two\nlines\"\n\
+                    2026-10-14T09:00:00Z,25,plain\n\
+                    yesterday,25,bad\n";
+        assert_eq!(
+            invalid(text),
+            (
+                5,
+                "\"yesterday\" is not a time such as 2026-10-14 10:00".to_owned()
+            )
+        );
+        assert_eq!(
+            invalid("start,minutes,task\n2026-10-14T08:00:00Z,25,\"open\n"),
+            (2, "a quoted field is never closed".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_headers_without_times() {
+        assert_eq!(
+            invalid("task,minutes\nwrite,25\n"),
+            (
+                1,
+                "no column of start times; expected one headed start or started_at".to_owned()
+            )
+        );
+        assert_eq!(
+            invalid("start,task\n2026-10-14T08:00:00Z,write\n"),
+            (
+                1,
+                "no column of end times or durations; expected one headed end or duration"
+                    .to_owned()
+            )
+        );
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_phases_and_outcomes() {
+        assert_eq!(
+            invalid("start,minutes,phase\n2026-10-14T08:00:00Z,25,nap\n"),
+            (2, "\"nap\" is not a phase such as work or break".to_owned())
+        );
+        assert_eq!(
+            invalid("start,minutes,outcome\n2026-10-14T08:00:00Z,25,maybe\n"),
+            (
+                2,
+                "\"maybe\" is not an outcome such as completed or abandoned".to_owned()
+            )
+        );
+    }
+}
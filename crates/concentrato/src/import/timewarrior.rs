@@ -0,0 +1,318 @@
+//! Timewarrior's intervals, each of which becomes a completed work session
+//! tagged with the interval's tags.
+//!
+//! Timewarrior keeps a file a month in its data directory, such as
+//! `~/.timewarrior/data/2026-10.data`, with a line an interval:
+//!
+//! ```text
+//! inc 20261014T080000Z - 20261014T082500Z # thesis "chapter 3" # "first draft"
+//! ```
+//!
+//! where the second `#` starts an annotation. `timew export` writes the same
+//! intervals as a JSON array. An interval still being tracked has no end and
+//! is left out.
+
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::Deserialize;
+use serde::de::{self, Deserializer};
+
+use super::{ImportError, LineError, focus, read as read_file, tags};
+use crate::history::Session;
+
+/// An interval as `timew export` writes it.
+#[derive(Deserialize)]
+struct Interval {
+    start: Stamp,
+    end: Option<Stamp>,
+    #[serde(default)]
+    tags: Vec<String>,
+    annotation: Option<String>,
+}
+
+/// Reads the intervals in the data file or `timew export` output at `path`,
+/// or in every month's file if `path` is the data directory.
+pub(super) fn read(path: &Path) -> Result<Vec<Session>, ImportError> {
+    if !path.is_dir() {
+        return parse(&read_file(path)?).map_err(|err| err.at(path));
+    }
+    let entries = fs::read_dir(path).map_err(|source| ImportError::Read {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mut months: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_month)
+        })
+        .collect();
+    months.sort();
+    let mut sessions = Vec::new();
+    for month in months {
+        sessions.extend(parse(&read_file(&month)?).map_err(|err| err.at(&month))?);
+    }
+    Ok(sessions)
+}
+
+/// Whether `name` is that of a month's data file, such as `2026-10.data`,
+/// rather than one of the other files in the directory.
+fn is_month(name: &str) -> bool {
+    name.strip_suffix(".data").is_some_and(|month| {
+        month.len() == 7
+            && month
+                .char_indices()
+                .all(|(i, c)| if i == 4 { c == '-' } else { c.is_ascii_digit() })
+    })
+}
+
+fn parse(text: &str) -> Result<Vec<Session>, LineError> {
+    if text.trim_start().starts_with('[') {
+        return parse_export(text);
+    }
+    let mut sessions = Vec::new();
+    for (line, text) in (1..).zip(text.lines()) {
+        if let Some(session) = parse_line(text).map_err(|err| LineError::new(line, err))? {
+            sessions.push(session);
+        }
+    }
+    Ok(sessions)
+}
+
+fn parse_export(text: &str) -> Result<Vec<Session>, LineError> {
+    let intervals: Vec<Interval> = serde_json::from_str(text).map_err(|err| {
+        // The line is given as for other files, rather than in the message.
+        let message = err.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(&*message, |(message, _)| message);
+        LineError::new(err.line(), message)
+    })?;
+    let mut sessions = Vec::new();
+    for interval in intervals {
+        let Some(Stamp(end)) = interval.end else {
+            continue;
+        };
+        let words = interval.tags.iter().map(String::as_str);
+        sessions.push(focus(
+            interval.start.0,
+            end,
+            interval.annotation,
+            tags(words),
+        ));
+    }
+    Ok(sessions)
+}
+
+/// Reads one line of a data file: an interval, or nothing for a blank line
+/// or one still being tracked.
+fn parse_line(line: &str) -> Result<Option<Session>, String> {
+    let words = words(line)?;
+    let mut words = words.iter().map(|(word, quoted)| (word.as_str(), *quoted));
+    match words.next() {
+        None => return Ok(None),
+        Some(("inc", false)) => {}
+        Some((word, _)) => return Err(format!("expected an interval, not {word:?}")),
+    }
+    let start = match words.next() {
+        Some((start, false)) => time(start)?,
+        _ => return Err("the interval has no start".to_owned()),
+    };
+    let end = match words.next() {
+        Some(("-", false)) => match words.next() {
+            Some((end, false)) => time(end)?,
+            _ => return Err("the interval has no end after the dash".to_owned()),
+        },
+        _ => return Ok(None),
+    };
+    let mut tag_words = Vec::new();
+    let mut annotation = None;
+    if let Some(("#", false)) = words.next() {
+        for (word, quoted) in words.by_ref() {
+            if word == "#" && !quoted {
+                break;
+            }
+            tag_words.push(word);
+        }
+        annotation = words.next().map(|(word, _)| word.to_owned());
+    }
+    Ok(Some(focus(start, end, annotation, tags(tag_words))))
+}
+
+/// Splits `line` at spaces into words, each with whether it was quoted.
+/// Quoted words keep their spaces, and a backslash escapes the character
+/// after it.
+fn words(line: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars();
+    let mut word = None;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => quoted.extend(chars.next()),
+                        Some(c) => quoted.push(c),
+                        None => return Err("a quoted word is never closed".to_owned()),
+                    }
+                }
+                words.push((quoted, true));
+            }
+            c if c.is_whitespace() => words.extend(word.take().map(|word| (word, false))),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word.map(|word| (word, false)));
+    Ok(words)
+}
+
+/// Reads a time such as `20261014T080000Z`.
+fn time(s: &str) -> Result<SystemTime, String> {
+    NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ")
+        .map(|time| Utc.from_utc_datetime(&time).into())
+        .map_err(|_| format!("{s:?} is not a time such as 20261014T080000Z"))
+}
+
+/// A time in an export, written as in the data files.
+struct Stamp(SystemTime);
+
+impl<'de> Deserialize<'de> for Stamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        time(&s).map(Stamp).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::history::Outcome;
+
+    /// `secs` after 2026-10-14 08:00:00 UTC.
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_791_964_800 + secs)
+    }
+
+    fn spans(sessions: &[Session]) -> Vec<(SystemTime, SystemTime)> {
+        sessions
+            .iter()
+            .map(|session| (session.started_at, session.ended_at))
+            .collect()
+    }
+
+    #[test]
+    fn reads_a_data_file() {
+        let sessions = parse(
+            "inc 20261014T080000Z - 20261014T082500Z # thesis \"chapter 3\" # \"first draft\"\n\
+             \n\
+             inc 20261014T090000Z - 20261014T093000Z\n\
+             inc 20261014T100000Z - 20261014T101000Z # \"a \\\"quoted\\\" tag\"\n\
+             inc 20261014T110000Z\n",
+        )
+        .unwrap();
+        assert_eq!(
+            spans(&sessions),
+            [
+                (at(0), at(25 * 60)),
+                (at(3600), at(5400)),
+                (at(7200), at(7800))
+            ]
+        );
+        let first = &sessions[0];
+        assert_eq!(first.tags, ["thesis", "chapter-3"]);
+        assert_eq!(first.task.as_deref(), Some("first draft"));
+        assert_eq!(first.actual, Duration::from_secs(25 * 60));
+        assert_eq!(first.planned, first.actual);
+        assert_eq!(first.outcome, Outcome::Completed);
+        assert!(sessions[1].tags.is_empty());
+        assert_eq!(sessions[1].task, None);
+        assert_eq!(sessions[2].tags, ["a--quoted--tag"]);
+    }
+
+    #[test]
+    fn reads_an_export() {
+        let sessions = parse(
+            r#"[
+{"id":3,"start":"20261014T080000Z","end":"20261014T082500Z","tags":["thesis","chapter 3"],"annotation":"first draft"},
+{"id":2,"start":"20261014T090000Z","end":"20261014T093000Z"},
+{"id":1,"start":"20261014T110000Z","tags":["open"]}
+]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            spans(&sessions),
+            [(at(0), at(25 * 60)), (at(3600), at(5400))]
+        );
+        assert_eq!(sessions[0].tags, ["thesis", "chapter-3"]);
+        assert_eq!(sessions[0].task.as_deref(), Some("first draft"));
+        assert!(sessions[1].tags.is_empty());
+    }
+
+    #[test]
+    fn reports_the_line_of_a_bad_interval() {
+        let invalid = |text: &str| {
+            let err = parse(text).unwrap_err();
+            (err.line, err.message)
+        };
+        assert_eq!(
+            invalid("inc 20261014T080000Z - 20261014T082500Z\nexc monday\n"),
+            (2, "expected an interval, not \"exc\"".to_owned())
+        );
+        assert_eq!(
+            invalid("inc 2026-10-14 - 20261014T082500Z\n"),
+            (
+                1,
+                "\"2026-10-14\" is not a time such as 20261014T080000Z".to_owned()
+            )
+        );
+        assert_eq!(
+            invalid("inc\n"),
+            (1, "the interval has no start".to_owned())
+        );
+        assert_eq!(
+            invalid("inc 20261014T080000Z -\n"),
+            (1, "the interval has no end after the dash".to_owned())
+        );
+        assert_eq!(
+            invalid("\ninc 20261014T080000Z - 20261014T082500Z # \"open\n"),
+            (2, "a quoted word is never closed".to_owned())
+        );
+        assert_eq!(
+            invalid("[\n{\"start\":\"20261014T080000Z\",\"end\":\"later\"}\n]").0,
+            2
+        );
+    }
+
+    #[test]
+    fn splits_words_at_spaces_outside_quotes() {
+        assert_eq!(
+            words(r#"inc  a "b c" "d \" e" f\g"#).unwrap(),
+            [
+                ("inc".to_owned(), false),
+                ("a".to_owned(), false),
+                ("b c".to_owned(), true),
+                ("d \" e".to_owned(), true),
+                ("f\\g".to_owned(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn recognises_the_months_data_files() {
+        assert!(is_month("2026-10.data"));
+        assert!(!is_month("2026-1.data"));
+        assert!(!is_month("tags.data"));
+        assert!(!is_month("undo.data"));
+        assert!(!is_month("2026-10.data.bak"));
+        assert!(!is_month("2026_10.data"));
+    }
+}
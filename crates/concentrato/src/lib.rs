@@ -0,0 +1,19 @@
+//! The concentrato application: configuration and everything that runs the
+//! [`concentrato_core`] timer for a person at a Linux desktop.
+
+pub mod bar;
+pub mod client;
+pub mod config;
+pub mod daemon;
+pub mod display;
+pub mod export;
+pub mod goal;
+pub mod history;
+pub mod import;
+pub mod logging;
+pub mod paths;
+pub mod protocol;
+pub mod report;
+pub mod template;
+pub mod tray;
+pub mod tui;
@@ -0,0 +1,250 @@
+//! Diagnostics for people running concentrato, through the `log` macros.
+//!
+//! Messages go to standard error, unless that is connected to the journal,
+//! as it is for a systemd service. Then they are sent to journald directly,
+//! with their priority and any key-values of the record as fields of their
+//! own, such as `CONCENTRATO_EVENT` for the timer's events.
+
+use std::env;
+use std::io::{self, Write};
+use std::mem;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixDatagram;
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Overrides the level passed to [`init`], as in `CONCENTRATO_LOG=debug`.
+pub const LOG_VAR: &str = "CONCENTRATO_LOG";
+
+/// Where journald takes native messages.
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Sends log messages at `level` and above to standard error, or to the
+/// journal when standard error goes there.
+pub fn init(level: LevelFilter) {
+    let level = env::var(LOG_VAR)
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(level);
+    let logger: Box<dyn Log> = match JournalLogger::connect() {
+        Some(logger) => Box::new(logger),
+        None => Box::new(StderrLogger),
+    };
+    if log::set_boxed_logger(logger).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            let level = record.level().as_str().to_lowercase();
+            let _ = writeln!(io::stderr(), "{level}: {}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sends messages to journald in its native protocol, described in
+/// `systemd.journal-fields(7)` and on systemd's site.
+struct JournalLogger {
+    socket: UnixDatagram,
+}
+
+impl JournalLogger {
+    /// A logger for the journal, if standard error is the journal's stream
+    /// that systemd names in `JOURNAL_STREAM`.
+    fn connect() -> Option<Self> {
+        let stream = env::var("JOURNAL_STREAM").ok()?;
+        let (device, inode) = stream.split_once(':')?;
+        let (device, inode): (u64, u64) = (device.parse().ok()?, inode.parse().ok()?);
+        // SAFETY: stat is plain data, for which all zeroes is a valid value.
+        let mut stat: libc::stat = unsafe { mem::zeroed() };
+        // SAFETY: fstat only writes to the buffer it is given.
+        if unsafe { libc::fstat(io::stderr().as_raw_fd(), &mut stat) } != 0 {
+            return None;
+        }
+        if stat.st_dev != device || stat.st_ino != inode {
+            return None;
+        }
+        let socket = UnixDatagram::unbound().ok()?;
+        socket.connect(JOURNAL_SOCKET).ok()?;
+        Some(Self { socket })
+    }
+}
+
+impl Log for JournalLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let priority = match record.level() {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        };
+        let message = record.args().to_string();
+        let mut entry = Entry::default();
+        entry.field("PRIORITY", &priority.to_string());
+        entry.field("MESSAGE", &message);
+        entry.field("SYSLOG_IDENTIFIER", "concentrato");
+        if let Some(module) = record.module_path() {
+            entry.field("CODE_MODULE", module);
+        }
+        if let Some(file) = record.file() {
+            entry.field("CODE_FILE", file);
+        }
+        if let Some(line) = record.line() {
+            entry.field("CODE_LINE", &line.to_string());
+        }
+        let _ = record.key_values().visit(&mut entry);
+        if self.socket.send(&entry.0).is_err() {
+            // The stream still reaches the journal, which reads the priority
+            // from the prefix.
+            let _ = writeln!(io::stderr(), "<{priority}>{message}");
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// A journal entry being put together.
+#[derive(Default)]
+struct Entry(Vec<u8>);
+
+impl Entry {
+    fn field(&mut self, name: &str, value: &str) {
+        self.0.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            // Values spanning lines are given with their length instead.
+            self.0.push(b'\n');
+            self.0
+                .extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            self.0.push(b'=');
+        }
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(b'\n');
+    }
+}
+
+impl<'kvs> VisitSource<'kvs> for Entry {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        // Field names may only have capitals, digits and underscores.
+        let name: String = key
+            .as_str()
+            .chars()
+            .map(|c| match c.to_ascii_uppercase() {
+                c @ ('A'..='Z' | '0'..='9') => c,
+                _ => '_',
+            })
+            .collect();
+        self.field(&format!("CONCENTRATO_{name}"), &value.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::testing::record_logs;
+
+    fn entry(fields: &[(&str, &str)]) -> Vec<u8> {
+        let mut entry = Entry::default();
+        for (name, value) in fields {
+            entry.field(name, value);
+        }
+        entry.0
+    }
+
+    #[test]
+    fn writes_single_line_fields_with_an_equals_sign() {
+        assert_eq!(
+            entry(&[("PRIORITY", "6"), ("MESSAGE", "work completed")]),
+            b"PRIORITY=6\nMESSAGE=work completed\n"
+        );
+        assert_eq!(entry(&[("CONCENTRATO_TASK", "")]), b"CONCENTRATO_TASK=\n");
+        // Only a newline calls for the binary form; `=` in a value is fine.
+        assert_eq!(entry(&[("MESSAGE", "a=b")]), b"MESSAGE=a=b\n");
+    }
+
+    #[test]
+    fn writes_multi_line_fields_with_their_length() {
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&[12, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(b"first\nsecond\n");
+        expected.extend_from_slice(b"PRIORITY=4\n");
+        assert_eq!(
+            entry(&[("MESSAGE", "first\nsecond"), ("PRIORITY", "4")]),
+            expected
+        );
+
+        // The length counts bytes, in little-endian order.
+        let long = "é\n".repeat(200);
+        let encoded = entry(&[("M", &long)]);
+        assert_eq!(&encoded[..2], b"M\n");
+        assert_eq!(&encoded[2..10], &600u64.to_le_bytes());
+        assert_eq!(&encoded[2..4], &[0x58, 0x02]);
+        assert_eq!(&encoded[10..610], long.as_bytes());
+        assert_eq!(&encoded[610..], b"\n");
+    }
+
+    #[test]
+    fn sends_records_with_their_key_values_as_fields() {
+        // Sets the level the logger goes by.
+        record_logs();
+        let (socket, journal) = UnixDatagram::pair().unwrap();
+        let logger = JournalLogger { socket };
+        logger.log(
+            &Record::builder()
+                .args(format_args!("work completed"))
+                .level(Level::Warn)
+                .module_path(Some("concentrato::daemon"))
+                .file(Some("src/daemon.rs"))
+                .line(Some(42))
+                .key_values(&[("event", "completed"), ("next-phase", "short\nbreak")])
+                .build(),
+        );
+
+        let mut buffer = [0; 1024];
+        let length = journal.recv(&mut buffer).unwrap();
+        let mut expected = b"PRIORITY=4\n\
+            MESSAGE=work completed\n\
+            SYSLOG_IDENTIFIER=concentrato\n\
+            CODE_MODULE=concentrato::daemon\n\
+            CODE_FILE=src/daemon.rs\n\
+            CODE_LINE=42\n\
+            CONCENTRATO_EVENT=completed\n\
+            CONCENTRATO_NEXT_PHASE\n"
+            .to_vec();
+        expected.extend_from_slice(&11u64.to_le_bytes());
+        expected.extend_from_slice(b"short\nbreak\n");
+        assert_eq!(&buffer[..length], expected);
+
+        // Below the level, nothing is sent.
+        logger.log(
+            &Record::builder()
+                .args(format_args!("details"))
+                .level(Level::Debug)
+                .build(),
+        );
+        journal.set_nonblocking(true).unwrap();
+        assert_eq!(
+            journal.recv(&mut buffer).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+    }
+}
@@ -0,0 +1,516 @@
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::{Local, NaiveDate};
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+
+use concentrato::bar::{Bar, ClickEvent, Module};
+use concentrato::client::{Client, ClientError};
+use concentrato::config::{self, Config, ConfigError, Override};
+use concentrato::daemon::{self, DaemonError};
+use concentrato::export::Format;
+use concentrato::history::{History, HistoryError};
+use concentrato::import::{ImportError, Source};
+use concentrato::protocol::{ErrorKind, Request, Status};
+use concentrato::report::{self, Period, Range, Report};
+use concentrato::template::Template;
+use concentrato::tray::{self, TrayError};
+use concentrato::tui::{self, TuiError};
+use concentrato::{display, logging, paths};
+use concentrato_core::parse_duration;
+
+const EXIT_STATUSES: &str = "\
+Exit status:
+  0  success
+  1  any other error
+  2  invalid command line
+  3  the daemon is not running
+  4  the timer cannot do that right now, e.g. pause while paused
+  5  another daemon is already running";
+
+/// A pomodoro timer for Linux.
+#[derive(Debug, Parser)]
+#[command(version, after_help = EXIT_STATUSES)]
+struct Cli {
+    /// Read settings from this file instead of the default one.
+    #[arg(long, global = true, value_name = "PATH", env = config::CONFIG_VAR)]
+    config: Option<PathBuf>,
+    /// Override a setting from the config file, as in `--set timer.work=50`.
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+    /// The daemon's socket.
+    #[arg(long, global = true, value_name = "PATH", env = "CONCENTRATO_SOCKET")]
+    socket: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Start the current phase.
+    Start {
+        /// Switch to this profile first.
+        #[arg(short, long)]
+        profile: Option<String>,
+        /// What the session is for; an empty label clears the last one.
+        #[arg(short, long)]
+        task: Option<String>,
+    },
+    /// Pause the running phase.
+    Pause,
+    /// Resume the paused phase.
+    Resume,
+    /// Pause the running phase, or else resume or start it.
+    Toggle,
+    /// End the current phase early and move on to the next.
+    Skip,
+    /// Make the current phase longer.
+    Extend {
+        /// How much longer, as in `5m` or `90s`; bare numbers are minutes.
+        #[arg(default_value = "5m", value_parser = parse_duration)]
+        by: Duration,
+    },
+    /// Abandon the current phase, leaving it ready to start over.
+    Stop,
+    /// Show what the timer is doing.
+    Status(StatusArgs),
+    /// Go back to the first phase of the cycle.
+    Reset,
+    /// Label the current session without starting or stopping anything.
+    Task {
+        /// What the session is for; leave it out to clear the label.
+        task: Option<String>,
+    },
+    /// Print the timer for a status bar.
+    Bar(BarArgs),
+    /// Show the timer full-screen in the terminal, with keys to drive it.
+    Tui {
+        /// How much the extend key makes the phase longer by.
+        #[arg(long, default_value = "1m", value_parser = parse_duration)]
+        step: Duration,
+    },
+    /// Show the timer in the system tray.
+    Tray {
+        /// How much scrolling up on the icon extends the phase by.
+        #[arg(long, default_value = "1m", value_parser = parse_duration)]
+        step: Duration,
+    },
+    /// Summarize the sessions in the history.
+    Report(ReportArgs),
+    /// Write the sessions in the history out for other tools.
+    Export(ExportArgs),
+    /// Add the sessions another timer logged to the history.
+    Import(ImportArgs),
+    /// Run the daemon that keeps time, in the foreground.
+    Daemon,
+}
+
+#[derive(Debug, clap::Args)]
+struct StatusArgs {
+    /// Print the status as JSON, in the daemon's protocol format.
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
+    /// Print the status through a template such as
+    /// `{phase} {remaining:mm:ss}`.
+    ///
+    /// Fields: phase, label, state, remaining, elapsed, planned, percent,
+    /// task, profile, completed, today, today_focus, goal, goal_percent,
+    /// streak, position, cycle_length. Durations take a format after a
+    /// colon: clock (the default), mm:ss, hh:mm:ss, m, s or human. Write
+    /// `{{` and `}}` for literal braces.
+    #[arg(long, value_name = "TEMPLATE")]
+    format: Option<Template>,
+    /// Keep printing a line every second, and whenever the timer changes.
+    #[arg(long)]
+    watch: bool,
+    /// With --watch, print only when the timer changes.
+    #[arg(long, requires = "watch")]
+    changes: bool,
+}
+
+impl StatusArgs {
+    /// The status the way these options ask for it; `detailed` picks the
+    /// longer plain-text form when neither JSON nor a template is wanted.
+    fn render(&self, status: &Status, detailed: bool) -> String {
+        if self.json {
+            serde_json::to_string(status).expect("a status always serializes")
+        } else if let Some(template) = &self.format {
+            template.render(status)
+        } else if detailed {
+            display::details(status)
+        } else {
+            display::summary(status)
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct BarArgs {
+    /// waybar, i3bar, polybar, i3blocks or i3status-rust.
+    bar: Bar,
+    /// The module's text, as a template like status's --format.
+    #[arg(long, value_name = "TEMPLATE", default_value = Module::DEFAULT_TEMPLATE)]
+    format: Template,
+    /// Keep printing a line every second, and whenever the timer changes.
+    #[arg(long)]
+    watch: bool,
+    /// How much scrolling up on the module extends the phase by.
+    #[arg(long, default_value = "1m", value_parser = parse_duration)]
+    step: Duration,
+}
+
+#[derive(Debug, clap::Args)]
+struct ReportArgs {
+    /// today, yesterday, week, last-week, month or last-month.
+    #[arg(default_value = "today", conflicts_with = "from")]
+    period: Period,
+    /// The first day of a range to report on instead, as in 2026-10-01.
+    #[arg(long, value_name = "DATE", value_parser = report::parse_date)]
+    from: Option<NaiveDate>,
+    /// The last day of the range; today unless given.
+    #[arg(long, value_name = "DATE", value_parser = report::parse_date, requires = "from")]
+    to: Option<NaiveDate>,
+    /// Only count sessions with this tag.
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct ExportArgs {
+    /// csv or jsonl for every session, or ics for an event per work
+    /// session.
+    #[arg(long, default_value = "csv")]
+    format: Format,
+    /// today, yesterday, week, last-week, month or last-month; everything
+    /// unless given.
+    #[arg(conflicts_with = "from")]
+    period: Option<Period>,
+    /// The first day of a range to export instead, as in 2026-10-01.
+    #[arg(long, value_name = "DATE", value_parser = report::parse_date)]
+    from: Option<NaiveDate>,
+    /// The last day of the range; today unless given.
+    #[arg(long, value_name = "DATE", value_parser = report::parse_date, requires = "from")]
+    to: Option<NaiveDate>,
+    /// Only export sessions with this tag.
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct ImportArgs {
+    /// csv for a pomodoro app's CSV export, or timewarrior for its data
+    /// directory, a month's data file or `timew export` output.
+    source: Source,
+    /// The files to import.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Count what would be imported without importing it.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Daemon(#[from] DaemonError),
+    #[error(transparent)]
+    Tui(#[from] TuiError),
+    #[error(transparent)]
+    Tray(#[from] TrayError),
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error("there is no history without a home directory or $XDG_DATA_HOME")]
+    NoHistory,
+    #[error(transparent)]
+    Import(#[from] ImportError),
+    #[error("cannot write the export: {0}")]
+    Export(io::Error),
+}
+
+impl Error {
+    /// The exit status for this error, as listed in [`EXIT_STATUSES`].
+    fn exit_code(&self) -> u8 {
+        match self {
+            Error::Client(ClientError::NotRunning { .. })
+            | Error::Tui(TuiError::Client(ClientError::NotRunning { .. }))
+            | Error::Tray(TrayError::Client(ClientError::NotRunning { .. })) => 3,
+            Error::Client(ClientError::Refused {
+                kind: ErrorKind::InvalidTransition | ErrorKind::UnknownProfile,
+                ..
+            }) => 4,
+            Error::Daemon(DaemonError::AlreadyRunning { .. }) => 5,
+            _ => 1,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let socket = cli.socket.clone().unwrap_or_else(paths::socket_path);
+    let result = match cli.command {
+        Command::Daemon => run_daemon(cli.config, &cli.overrides, socket),
+        Command::Start { profile, task } => send(&socket, Request::Start { profile, task }),
+        Command::Pause => send(&socket, Request::Pause),
+        Command::Resume => send(&socket, Request::Resume),
+        Command::Toggle => send(&socket, Request::Toggle),
+        Command::Skip => send(&socket, Request::Skip),
+        Command::Extend { by } => send(&socket, Request::extend(by)),
+        Command::Stop => send(&socket, Request::Stop),
+        Command::Reset => send(&socket, Request::Reset),
+        Command::Task { task } => send(
+            &socket,
+            Request::SetTask {
+                task: task.unwrap_or_default(),
+            },
+        ),
+        Command::Status(args) => status(&socket, &args),
+        Command::Bar(args) => bar(&socket, cli.socket.as_deref(), args),
+        Command::Tui { step } => tui::run(&socket, step).map_err(Error::from),
+        Command::Tray { step } => tray(cli.config, &cli.overrides, &socket, step),
+        Command::Report(args) => report(cli.config, &cli.overrides, &args),
+        Command::Export(args) => export(&args),
+        Command::Import(args) => import(&args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("concentrato: {err}");
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+/// Sends `request` to the daemon and shows the timer after it.
+fn send(socket: &Path, request: Request) -> Result<(), Error> {
+    let status = Client::connect(socket)?.request(&request)?;
+    println!("{}", display::summary(&status));
+    Ok(())
+}
+
+fn status(socket: &Path, args: &StatusArgs) -> Result<(), Error> {
+    let mut client = Client::connect(socket)?;
+    if !args.watch {
+        let status = client.request(&Request::Status)?;
+        println!("{}", args.render(&status, true));
+        return Ok(());
+    }
+    let (status, notifications) = client.subscribe(!args.changes)?;
+    let mut out = io::stdout().lock();
+    // Whoever reads the output may stop at any time, as `head` does; that
+    // is no reason to complain.
+    if writeln!(out, "{}", args.render(&status, false)).is_err() {
+        return Ok(());
+    }
+    for notification in notifications {
+        if writeln!(out, "{}", args.render(notification?.status(), false)).is_err() {
+            return Ok(());
+        }
+    }
+    Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into()).into())
+}
+
+fn bar(socket: &Path, socket_arg: Option<&Path>, args: BarArgs) -> Result<(), Error> {
+    let mut command = env::current_exe()
+        .map(|exe| shell_quote(&exe.to_string_lossy()))
+        .unwrap_or_else(|_| "concentrato".to_owned());
+    if let Some(path) = socket_arg {
+        command = format!(
+            "{command} --socket {}",
+            shell_quote(&path.to_string_lossy())
+        );
+    }
+    let mut module = Module::new(args.bar, args.format, args.step, command);
+    let mut client = Client::connect(socket)?;
+
+    if !args.watch {
+        // i3blocks runs the block again for each click, saying which button
+        // it was; older versions spell the variable differently.
+        let button = env::var("button").or_else(|_| env::var("BLOCK_BUTTON"));
+        let click = button.ok().and_then(|button| button.parse().ok());
+        let request = click
+            .and_then(|button| module.click(ClickEvent { button }))
+            .unwrap_or(Request::Status);
+        let status = match client.request(&request) {
+            // A click the timer cannot act on should still show the module.
+            Err(ClientError::Refused { .. }) => client.request(&Request::Status)?,
+            result => result?,
+        };
+        println!("{}", module.render(&status));
+        return Ok(());
+    }
+
+    if args.bar.reports_clicks() {
+        let module = module.clone();
+        let socket = socket.to_owned();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { return };
+                let Some(request) = ClickEvent::parse(&line).and_then(|click| module.click(click))
+                else {
+                    continue;
+                };
+                if let Err(err) = Client::connect(&socket).and_then(|mut c| c.request(&request)) {
+                    eprintln!("concentrato: {err}");
+                }
+            }
+        });
+    }
+    let (status, notifications) = client.subscribe(true)?;
+    let mut out = io::stdout().lock();
+    let mut lines = module.header().into_iter().chain([module.render(&status)]);
+    if lines.try_for_each(|line| writeln!(out, "{line}")).is_err() {
+        return Ok(());
+    }
+    for notification in notifications {
+        if writeln!(out, "{}", module.render(notification?.status())).is_err() {
+            return Ok(());
+        }
+    }
+    Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into()).into())
+}
+
+/// Quotes `word` for the shell, unless it is plain enough not to need it.
+fn shell_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "/._-+=@,".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_owned()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// The overrides from the environment, then those from `--set`.
+fn overrides(args: &[String]) -> Result<Vec<Override>, Error> {
+    let mut all = Override::from_env();
+    for arg in args {
+        all.push(Override::from_arg(arg)?);
+    }
+    Ok(all)
+}
+
+fn report(
+    config_path: Option<PathBuf>,
+    overrides_args: &[String],
+    args: &ReportArgs,
+) -> Result<(), Error> {
+    let today = Local::now().date_naive();
+    let (title, range) = match args.from {
+        Some(from) => {
+            let range = Range::new(from, args.to.unwrap_or(today));
+            (range.to_string(), range)
+        }
+        None => {
+            let range = args.period.range(today);
+            (format!("{}, {range}", args.period.label()), range)
+        }
+    };
+    let title = match &args.tag {
+        Some(tag) => format!("{title}, tagged #{tag}"),
+        None => title,
+    };
+    let path = History::default_path().ok_or(Error::NoHistory)?;
+    let (from, to) = range.bounds();
+    let history = History::open(&path)?;
+    let sessions = history.sessions(from, to)?;
+    let mut report = Report::new(title, range, sessions, args.tag.as_deref());
+    let config_path = config_path.or_else(config::default_path);
+    let config = Config::load(config_path.as_deref(), &overrides(overrides_args)?)?;
+    if config.goal.daily.is_some() {
+        report = report.with_goal(&config.goal, history.days()?, today);
+    }
+    print!("{}", report.render());
+    Ok(())
+}
+
+fn export(args: &ExportArgs) -> Result<(), Error> {
+    let today = Local::now().date_naive();
+    let range = match (args.from, args.period) {
+        (Some(from), _) => Some(Range::new(from, args.to.unwrap_or(today))),
+        (None, Some(period)) => Some(period.range(today)),
+        (None, None) => None,
+    };
+    let (from, to) = range.map_or((SystemTime::UNIX_EPOCH, SystemTime::now()), |range| {
+        range.bounds()
+    });
+    let path = History::default_path().ok_or(Error::NoHistory)?;
+    let mut sessions = History::open(&path)?.sessions(from, to)?;
+    if let Some(tag) = &args.tag {
+        sessions.retain(|session| session.tags.contains(tag));
+    }
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    match args
+        .format
+        .write(&sessions, &mut out)
+        .and_then(|()| out.flush())
+    {
+        // Whatever reads the export has all it wanted.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(Error::Export),
+    }
+}
+
+fn import(args: &ImportArgs) -> Result<(), Error> {
+    let mut sessions = Vec::new();
+    for path in &args.paths {
+        sessions.extend(args.source.read(path)?);
+    }
+    // Of sessions that overlap, the earliest is kept.
+    sessions.sort_by_key(|session| session.started_at);
+    let path = History::default_path().ok_or(Error::NoHistory)?;
+    let imported = History::open(&path)?.import(&sessions, args.source.as_str(), args.dry_run)?;
+    let verb = if args.dry_run {
+        "Would import"
+    } else {
+        "Imported"
+    };
+    print!("{verb} {}", display::plural(imported.added, "session"));
+    match imported.overlapping {
+        0 => println!("."),
+        overlapping => println!(
+            ", leaving out {} that overlapped ones already recorded.",
+            display::plural(overlapping, "session")
+        ),
+    }
+    Ok(())
+}
+
+fn tray(
+    config_path: Option<PathBuf>,
+    overrides_args: &[String],
+    socket: &Path,
+    step: Duration,
+) -> Result<(), Error> {
+    logging::init(LevelFilter::Warn);
+    // The daemon keeps the time, but the profiles to offer come from the
+    // config it reads.
+    let config_path = config_path.or_else(config::default_path);
+    let config = Config::load(config_path.as_deref(), &overrides(overrides_args)?)?;
+    let profiles = config.profiles.into_keys().collect();
+    tray::run(socket, profiles, step)?;
+    Ok(())
+}
+
+fn run_daemon(
+    config_path: Option<PathBuf>,
+    overrides_args: &[String],
+    socket: PathBuf,
+) -> Result<(), Error> {
+    logging::init(LevelFilter::Info);
+    daemon::run(daemon::Options {
+        socket,
+        config_path: config_path.or_else(config::default_path),
+        overrides: overrides(overrides_args)?,
+        checkpoint: paths::state_dir().map(|dir| dir.join("session.json")),
+        history: History::default_path(),
+    })?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Where concentrato keeps its files, following the XDG Base Directory
+//! specification.
+
+use std::env;
+use std::path::PathBuf;
+
+const APP: &str = "concentrato";
+
+/// `$XDG_CONFIG_HOME/concentrato`, by default `~/.config/concentrato`.
+pub fn config_dir() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join(APP))
+}
+
+/// `$XDG_STATE_HOME/concentrato`, by default `~/.local/state/concentrato`.
+pub fn state_dir() -> Option<PathBuf> {
+    base_dir("XDG_STATE_HOME", ".local/state").map(|dir| dir.join(APP))
+}
+
+/// `$XDG_DATA_HOME/concentrato`, by default `~/.local/share/concentrato`.
+pub fn data_dir() -> Option<PathBuf> {
+    base_dir("XDG_DATA_HOME", ".local/share").map(|dir| dir.join(APP))
+}
+
+/// `$XDG_RUNTIME_DIR/concentrato`, where the daemon's socket lives.
+///
+/// Without a runtime directory this falls back to a per-user directory under
+/// the system's temporary directory, as the specification suggests.
+pub fn runtime_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join(APP))
+        .unwrap_or_else(|| {
+            // SAFETY: getuid cannot fail.
+            let uid = unsafe { libc::getuid() };
+            env::temp_dir().join(format!("{APP}-{uid}"))
+        })
+}
+
+/// The socket clients use to reach the daemon.
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join("daemon.sock")
+}
+
+/// Expands a leading `~/` in a path from the user to their home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn base_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    // The specification says relative paths are invalid and must be ignored.
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home().map(|home| home.join(fallback)))
+}
+
+fn home() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .filter(|home| home.is_absolute())
+}
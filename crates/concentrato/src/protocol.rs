@@ -0,0 +1,263 @@
+//! The messages clients and the daemon exchange over its Unix socket.
+//!
+//! Each message is a single line of JSON. A client writes a [`Request`] and
+//! the daemon answers it with exactly one [`Response`]; a connection can carry
+//! any number of these exchanges in turn. Both are wrapped in an [`Envelope`]
+//! carrying the protocol [`VERSION`], which the daemon checks before looking at
+//! anything else. `docs/protocol.md` describes the format for clients written
+//! in other languages.
+//!
+//! A [`Request::Subscribe`] turns the connection around: after its response,
+//! the daemon sends a [`Notification`] whenever the timer changes, and every
+//! second as well if the client asked for ticks.
+
+use std::time::Duration;
+
+use concentrato_core::{Event, Phase};
+use serde::{Deserialize, Serialize};
+
+use crate::goal::{Day, Goal};
+
+/// The protocol version this build speaks.
+///
+/// It changes whenever a message changes in a way an older client or daemon
+/// could misread. Adding optional fields does not count.
+pub const VERSION: u32 = 1;
+
+/// A message together with the protocol version it was written for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    #[serde(flatten)]
+    pub message: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(message: T) -> Self {
+        Self {
+            version: VERSION,
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Reports the timer's status without changing it.
+    Status,
+    /// Starts the current phase, optionally switching to another profile
+    /// first.
+    Start {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+        /// What the session is for. An empty label clears the current one,
+        /// and leaving it out keeps it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task: Option<String>,
+    },
+    Pause,
+    Resume,
+    /// Pauses the running phase, or else resumes or starts it.
+    Toggle,
+    /// Makes the current phase longer.
+    Extend {
+        by_ms: u64,
+    },
+    /// Abandons the current phase, which goes back to idle.
+    Stop,
+    /// Ends the current phase early and moves on to the next.
+    Skip,
+    /// Goes back to the start of the cycle.
+    Reset,
+    /// Labels the current session, whatever the timer is doing. An empty
+    /// label clears it.
+    SetTask {
+        task: String,
+    },
+    /// Asks for notifications of changes, and of every tick if `ticks` is
+    /// set. The connection carries nothing else from then on.
+    Subscribe {
+        #[serde(default)]
+        ticks: bool,
+    },
+}
+
+impl Request {
+    pub fn extend(by: Duration) -> Self {
+        Request::Extend { by_ms: millis(by) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    /// The request succeeded; `status` is the timer after it.
+    Ok {
+        status: Status,
+    },
+    Error {
+        kind: ErrorKind,
+        message: String,
+    },
+}
+
+/// A message the daemon sends a subscribed client unprompted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "notification", rename_all = "snake_case")]
+pub enum Notification {
+    /// The timer changed because of `event`, which happened in `phase`.
+    Changed {
+        event: EventKind,
+        phase: Phase,
+        status: Status,
+    },
+    /// Another second went by.
+    Tick { status: Status },
+}
+
+impl Notification {
+    pub fn status(&self) -> &Status {
+        match self {
+            Notification::Changed { status, .. } | Notification::Tick { status } => status,
+        }
+    }
+}
+
+/// The kinds of [`Event`] the timer reports, and the changes the daemon
+/// makes to the session around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Started,
+    Paused,
+    Resumed,
+    Extended,
+    Stopped,
+    Completed,
+    Skipped,
+    Discarded,
+    Reset,
+    /// A client labelled the session differently.
+    TaskChanged,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Started => "started",
+            EventKind::Paused => "paused",
+            EventKind::Resumed => "resumed",
+            EventKind::Extended => "extended",
+            EventKind::Stopped => "stopped",
+            EventKind::Completed => "completed",
+            EventKind::Skipped => "skipped",
+            EventKind::Discarded => "discarded",
+            EventKind::Reset => "reset",
+            EventKind::TaskChanged => "task_changed",
+        }
+    }
+}
+
+impl From<&Event> for EventKind {
+    fn from(event: &Event) -> Self {
+        match event {
+            Event::Started { .. } => EventKind::Started,
+            Event::Paused { .. } => EventKind::Paused,
+            Event::Resumed { .. } => EventKind::Resumed,
+            Event::Extended { .. } => EventKind::Extended,
+            Event::Stopped { .. } => EventKind::Stopped,
+            Event::Completed { .. } => EventKind::Completed,
+            Event::Skipped { .. } => EventKind::Skipped,
+            Event::Discarded { .. } => EventKind::Discarded,
+            Event::Reset { .. } => EventKind::Reset,
+        }
+    }
+}
+
+/// Why the daemon refused a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The request was not valid JSON or not a known command.
+    BadRequest,
+    /// The client speaks a different protocol version.
+    UnsupportedVersion,
+    /// The timer cannot make that transition in its current state.
+    InvalidTransition,
+    UnknownProfile,
+}
+
+/// A snapshot of the timer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub phase: Phase,
+    pub state: concentrato_core::Status,
+    /// How long the phase is meant to last.
+    pub planned_ms: u64,
+    /// How long the phase has been running, excluding pauses.
+    pub elapsed_ms: u64,
+    pub remaining_ms: u64,
+    /// Index of the phase within the cycle.
+    pub position: usize,
+    /// Number of phases in the cycle.
+    pub cycle_length: usize,
+    /// Work sessions completed since the daemon started or was reset.
+    pub completed: u32,
+    /// Work sessions completed since midnight, local time.
+    #[serde(default)]
+    pub today: u32,
+    /// Time spent in work sessions since midnight, completed or not.
+    #[serde(default)]
+    pub today_focus_ms: u64,
+    /// The daily goal, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal: Option<Goal>,
+    /// Days in a row the goal was met, today included once it is.
+    #[serde(default)]
+    pub streak: u32,
+    /// The profile in use, if not the default settings.
+    pub profile: Option<String>,
+    /// What the current session is for.
+    #[serde(default)]
+    pub task: Option<String>,
+}
+
+impl Status {
+    pub fn planned(&self) -> Duration {
+        Duration::from_millis(self.planned_ms)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_ms)
+    }
+
+    pub fn remaining(&self) -> Duration {
+        Duration::from_millis(self.remaining_ms)
+    }
+
+    pub fn today_focus(&self) -> Duration {
+        Duration::from_millis(self.today_focus_ms)
+    }
+
+    /// What today's work has added up to.
+    pub fn today_totals(&self) -> Day {
+        Day {
+            pomodoros: self.today,
+            focus: self.today_focus(),
+        }
+    }
+
+    /// How much of the phase has elapsed, as a whole percentage.
+    pub fn percent(&self) -> u64 {
+        match self.planned_ms {
+            0 => 100,
+            planned => self.elapsed_ms.min(planned) * 100 / planned,
+        }
+    }
+}
+
+/// Converts a duration to whole milliseconds for the wire.
+pub(crate) fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,1028 @@
+//! Summaries of the [`history`](crate::history) for the terminal.
+//!
+//! A report covers whole days in the local time zone: the focus time of the
+//! work sessions that started on them, how many of those were completed
+//! and how many abandoned, how often they were interrupted, and charts of
+//! when the work happened, drawn with block characters. Each session counts
+//! towards the day it started on, the hours it ran in and each of its tags.
+//! With a daily goal, the report also tells which days met it.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeDelta, TimeZone, Timelike, Weekday};
+use concentrato_core::Phase;
+
+use crate::config::GoalConfig;
+use crate::display::plural;
+use crate::goal::{self, Day, Goal};
+use crate::history::{Outcome, Session};
+
+/// How wide the bars of a chart grow.
+const BAR_WIDTH: usize = 30;
+/// Beyond this many days, days are shown as a calendar rather than a bar
+/// each.
+const MAX_DAY_BARS: usize = 31;
+
+/// A span of days a report can cover, relative to today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Today,
+    Yesterday,
+    /// Monday to Sunday.
+    Week,
+    LastWeek,
+    Month,
+    LastMonth,
+}
+
+impl Period {
+    pub const ALL: [Period; 6] = [
+        Period::Today,
+        Period::Yesterday,
+        Period::Week,
+        Period::LastWeek,
+        Period::Month,
+        Period::LastMonth,
+    ];
+
+    /// The identifier used for this period on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Period::Today => "today",
+            Period::Yesterday => "yesterday",
+            Period::Week => "week",
+            Period::LastWeek => "last-week",
+            Period::Month => "month",
+            Period::LastMonth => "last-month",
+        }
+    }
+
+    /// A capitalised name suitable for a heading.
+    pub fn label(self) -> &'static str {
+        match self {
+            Period::Today => "Today",
+            Period::Yesterday => "Yesterday",
+            Period::Week => "This week",
+            Period::LastWeek => "Last week",
+            Period::Month => "This month",
+            Period::LastMonth => "Last month",
+        }
+    }
+
+    /// The days the period covers when it is `today`.
+    pub fn range(self, today: NaiveDate) -> Range {
+        let yesterday = today.pred_opt().unwrap_or(today);
+        let week = today.week(Weekday::Mon);
+        let month = today.with_day(1).unwrap_or(today);
+        let (first, last) = match self {
+            Period::Today => (today, today),
+            Period::Yesterday => (yesterday, yesterday),
+            Period::Week => (week.first_day(), week.last_day()),
+            Period::LastWeek => {
+                let last = week.first_day().pred_opt().unwrap_or(today);
+                (last.week(Weekday::Mon).first_day(), last)
+            }
+            Period::Month => (month, end_of_month(month)),
+            Period::LastMonth => {
+                let last = month.pred_opt().unwrap_or(today);
+                (last.with_day(1).unwrap_or(last), last)
+            }
+        };
+        Range { first, last }
+    }
+}
+
+fn end_of_month(first: NaiveDate) -> NaiveDate {
+    first
+        .checked_add_months(chrono::Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(first)
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown period {0:?}; expected today, yesterday, week, last-week, month or last-month")]
+pub struct ParsePeriodError(String);
+
+impl FromStr for Period {
+    type Err = ParsePeriodError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Period::ALL
+            .into_iter()
+            .find(|period| period.as_str() == s.replace('_', "-"))
+            .ok_or_else(|| ParsePeriodError(s.to_owned()))
+    }
+}
+
+/// Parses a date such as `2026-10-17`.
+pub fn parse_date(s: &str) -> Result<NaiveDate, chrono::ParseError> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+}
+
+/// The days from `first` to `last`, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl Range {
+    /// The days between `a` and `b`, in either order.
+    pub fn new(a: NaiveDate, b: NaiveDate) -> Self {
+        Self {
+            first: a.min(b),
+            last: a.max(b),
+        }
+    }
+
+    /// From the first moment of the range up to, not including, the first
+    /// moment after it.
+    pub fn bounds(&self) -> (SystemTime, SystemTime) {
+        let after = self.last.succ_opt().unwrap_or(self.last);
+        (midnight(self.first).into(), midnight(after).into())
+    }
+
+    fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        self.first.iter_days().take(self.len())
+    }
+
+    fn len(&self) -> usize {
+        usize::try_from((self.last - self.first).num_days() + 1).unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first.format("%a %-d %b %Y"))
+        } else if self.first.year() == self.last.year() {
+            write!(
+                f,
+                "{} to {}",
+                self.first.format("%a %-d %b"),
+                self.last.format("%a %-d %b %Y")
+            )
+        } else {
+            write!(
+                f,
+                "{} to {}",
+                self.first.format("%a %-d %b %Y"),
+                self.last.format("%a %-d %b %Y")
+            )
+        }
+    }
+}
+
+/// The first moment of `date`, or of the first hour it has when a change
+/// of clocks skips its midnight.
+fn midnight(date: NaiveDate) -> DateTime<Local> {
+    let start = date.and_time(Default::default());
+    Local
+        .from_local_datetime(&start)
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&(start + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .unwrap_or_else(|| Local.from_utc_datetime(&start))
+}
+
+/// A summary of the sessions over a range of days.
+#[derive(Debug, Clone)]
+pub struct Report {
+    title: String,
+    range: Range,
+    sessions: Vec<Session>,
+    goal: Option<Goals>,
+}
+
+/// How the days of a report fared against the daily goal.
+#[derive(Debug, Clone)]
+struct Goals {
+    goal: Goal,
+    config: GoalConfig,
+    /// Every day's work, whatever its tags.
+    days: BTreeMap<NaiveDate, Day>,
+    today: NaiveDate,
+}
+
+impl Goals {
+    fn met(&self, day: NaiveDate) -> bool {
+        self.goal
+            .met(self.days.get(&day).copied().unwrap_or_default())
+    }
+}
+
+impl Report {
+    /// A report headed `title` on `sessions`, which are those that started
+    /// within `range`; with a `tag`, only the sessions tagged with it count.
+    pub fn new(title: String, range: Range, sessions: Vec<Session>, tag: Option<&str>) -> Self {
+        let sessions = sessions
+            .into_iter()
+            .filter(|session| tag.is_none_or(|tag| session.tags.iter().any(|t| t == tag)))
+            .collect();
+        Self {
+            title,
+            range,
+            sessions,
+            goal: None,
+        }
+    }
+
+    /// Tells which days met the goal in `config`, if it sets one, given
+    /// what every day added up to in `days`.
+    pub fn with_goal(
+        mut self,
+        config: &GoalConfig,
+        days: BTreeMap<NaiveDate, Day>,
+        today: NaiveDate,
+    ) -> Self {
+        self.goal = config.daily.map(|goal| Goals {
+            goal,
+            config: config.clone(),
+            days,
+            today,
+        });
+        self
+    }
+
+    fn work(&self) -> impl Iterator<Item = &Session> {
+        self.sessions
+            .iter()
+            .filter(|session| session.phase == Phase::Work)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{}", self.title)?;
+        if self.sessions.is_empty() {
+            return writeln!(out, "\nNo sessions recorded.");
+        }
+        writeln!(out)?;
+        self.write_summary(out)?;
+        writeln!(out, "\nBy day")?;
+        if self.range.len() <= MAX_DAY_BARS {
+            self.write_days(out)?;
+        } else {
+            self.write_calendar(out)?;
+        }
+        writeln!(out, "\nBy hour")?;
+        self.write_hours(out)?;
+        if self.sessions.iter().any(|session| !session.tags.is_empty()) {
+            writeln!(out, "\nBy tag")?;
+            self.write_tags(out)?;
+        }
+        Ok(())
+    }
+
+    fn write_summary(&self, out: &mut String) -> fmt::Result {
+        let work: Vec<&Session> = self.work().collect();
+        let focus: Duration = work.iter().map(|session| session.actual).sum();
+        let count = |outcome| work.iter().filter(|s| s.outcome == outcome).count();
+        let (completed, skipped, interrupted) = (
+            count(Outcome::Completed),
+            count(Outcome::Skipped),
+            count(Outcome::Interrupted),
+        );
+        let pauses: u32 = work.iter().map(|session| session.pauses).sum();
+        let paused: Duration = work.iter().map(|session| session.paused).sum();
+        let breaks: Vec<&Session> = self
+            .sessions
+            .iter()
+            .filter(|session| session.phase.is_break())
+            .collect();
+        let rest: Duration = breaks.iter().map(|session| session.actual).sum();
+
+        writeln!(
+            out,
+            "Focus time      {} in {}",
+            hours(focus),
+            plural(work.len(), "work session")
+        )?;
+        writeln!(
+            out,
+            "Pomodoros       {completed} completed, {} abandoned ({skipped} skipped, {interrupted} interrupted)",
+            skipped + interrupted
+        )?;
+        if let Some(average) = u32::try_from(work.len())
+            .ok()
+            .and_then(|n| focus.checked_div(n))
+        {
+            writeln!(out, "Average session {}", hours(average))?;
+        }
+        writeln!(
+            out,
+            "Interruptions   {}, {} paused",
+            plural(pauses as usize, "pause"),
+            hours(paused)
+        )?;
+        writeln!(
+            out,
+            "Breaks          {} in {}",
+            hours(rest),
+            plural(breaks.len(), "break")
+        )?;
+        if let Some(goals) = &self.goal {
+            self.write_goal(goals, out)?;
+        }
+        Ok(())
+    }
+
+    /// On how many days the goal was met, not counting the days yet to
+    /// come or rest days that missed it, and the streak as of today.
+    fn write_goal(&self, goals: &Goals, out: &mut String) -> fmt::Result {
+        let (mut met, mut counted) = (0, 0);
+        for day in self.range.days().filter(|&day| day <= goals.today) {
+            if goals.met(day) {
+                met += 1;
+                counted += 1;
+            } else if !goals.config.is_rest_day(day) && day != goals.today {
+                counted += 1;
+            }
+        }
+        let today = goals.days.get(&goals.today).copied().unwrap_or_default();
+        let streak = goals.config.streak(&goals.days, goals.today, today);
+        writeln!(
+            out,
+            "Daily goal      {}, met on {met} of {}; {} in a row now",
+            goals.goal,
+            plural(counted, "day"),
+            plural(streak as usize, "day")
+        )
+    }
+
+    /// A bar of focus time for each day, a dot for each completed pomodoro,
+    /// and a tick for each day that met the goal.
+    fn write_days(&self, out: &mut String) -> fmt::Result {
+        let mut days: BTreeMap<NaiveDate, (Duration, usize)> = self
+            .range
+            .days()
+            .map(|day| (day, Default::default()))
+            .collect();
+        for session in self.work() {
+            let day = goal::local_day(session.started_at);
+            if let Some((focus, completed)) = days.get_mut(&day) {
+                *focus += session.actual;
+                *completed += usize::from(session.outcome == Outcome::Completed);
+            }
+        }
+        let most = days
+            .values()
+            .map(|(focus, _)| *focus)
+            .max()
+            .unwrap_or_default();
+        for (day, (focus, completed)) in days {
+            let met = match &self.goal {
+                Some(goals) if goals.met(day) => "✓ ",
+                Some(_) => "  ",
+                None => "",
+            };
+            let line = format!(
+                "{:<10}  {:<BAR_WIDTH$}  {:>7}  {met}{}",
+                day.format("%a %-d %b").to_string(),
+                bar(focus, most),
+                hours(focus),
+                "●".repeat(completed)
+            );
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+
+    /// The focus time of each day as a shade, a column for each week.
+    fn write_calendar(&self, out: &mut String) -> fmt::Result {
+        let mut days: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+        for session in self.work() {
+            let day = goal::local_day(session.started_at);
+            *days.entry(day).or_default() += session.actual;
+        }
+        let most = days.values().copied().max().unwrap_or_default();
+        let monday = self.range.first.week(Weekday::Mon).first_day();
+        let weeks = (self.range.last - monday).num_weeks() + 1;
+        // Each month's name goes above the week it starts in, and the first
+        // week's month above that; unless the next month starts too soon
+        // after to leave it room.
+        let mut labels: Vec<(usize, String)> = Vec::new();
+        for week in 0..weeks {
+            let sunday = monday + TimeDelta::weeks(week) + Days::new(6);
+            if week == 0 || sunday.day() <= 7 {
+                let column = 5 + 2 * week as usize;
+                if labels
+                    .last()
+                    .is_some_and(|(last, name)| last + name.chars().count() >= column)
+                {
+                    labels.pop();
+                }
+                labels.push((column, sunday.format("%b").to_string()));
+            }
+        }
+        let mut months = String::new();
+        for (column, name) in labels {
+            let pad = column - months.chars().count();
+            months.extend(std::iter::repeat_n(' ', pad));
+            months.push_str(&name);
+        }
+        writeln!(out, "{months}")?;
+        for (weekday, name) in (0..).zip(WEEKDAYS) {
+            let cells = (0..weeks).map(|week| {
+                let day = monday + TimeDelta::weeks(week) + Days::new(weekday);
+                if day < self.range.first || day > self.range.last {
+                    ' '
+                } else {
+                    shade(days.get(&day).copied().unwrap_or_default(), most)
+                }
+            });
+            writeln!(out, "{name}  {}", spaced(cells).trim_end())?;
+        }
+        legend(out, most, "a day")
+    }
+
+    /// The focus time of each hour as a shade, a row for each day of a
+    /// week, or a single row for a single day.
+    fn write_hours(&self, out: &mut String) -> fmt::Result {
+        let mut grid = [[Duration::ZERO; 24]; 7];
+        for session in self.work() {
+            for (time, focus) in by_hour(session) {
+                let weekday = time.weekday().num_days_from_monday() as usize;
+                grid[weekday][time.hour() as usize] += focus;
+            }
+        }
+        let most = grid.iter().flatten().copied().max().unwrap_or_default();
+        writeln!(out, "     0     3     6     9     12    15    18    21")?;
+        let single = (self.range.first == self.range.last)
+            .then(|| self.range.first.weekday().num_days_from_monday() as usize);
+        for (weekday, hours) in grid.iter().enumerate() {
+            if single.is_some_and(|day| day != weekday) {
+                continue;
+            }
+            let cells = hours.iter().map(|focus| shade(*focus, most));
+            writeln!(out, "{}  {}", WEEKDAYS[weekday], spaced(cells))?;
+        }
+        legend(out, most, "an hour")
+    }
+
+    /// A bar of focus time for each tag, most first.
+    fn write_tags(&self, out: &mut String) -> fmt::Result {
+        let mut tags: BTreeMap<&str, (Duration, usize)> = BTreeMap::new();
+        for session in self.work() {
+            let names = if session.tags.is_empty() {
+                vec![UNTAGGED]
+            } else {
+                session.tags.iter().map(String::as_str).collect()
+            };
+            for name in names {
+                let (focus, count) = tags.entry(name).or_default();
+                *focus += session.actual;
+                *count += 1;
+            }
+        }
+        let mut tags: Vec<_> = tags.into_iter().collect();
+        tags.sort_by(|(a, (a_focus, _)), (b, (b_focus, _))| b_focus.cmp(a_focus).then(a.cmp(b)));
+        let most = tags
+            .first()
+            .map(|(_, (focus, _))| *focus)
+            .unwrap_or_default();
+        let width = tags
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (name, (focus, count)) in tags {
+            writeln!(
+                out,
+                "{name:<width$}  {:<BAR_WIDTH$}  {:>7}  {}",
+                bar(focus, most),
+                hours(focus),
+                plural(count, "session")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const UNTAGGED: &str = "(untagged)";
+
+/// The focus of `session` split into the local hours it ran in, each with
+/// the time it started there. Pauses are spread evenly over the session,
+/// since when they happened is not recorded.
+fn by_hour(session: &Session) -> Vec<(DateTime<Local>, Duration)> {
+    let start = DateTime::<Local>::from(session.started_at);
+    let end = DateTime::<Local>::from(session.ended_at);
+    let wall = (end - start).to_std().unwrap_or_default();
+    if wall.is_zero() {
+        return vec![(start, session.actual)];
+    }
+    let share = session.actual.as_secs_f64() / wall.as_secs_f64();
+    let mut hours = Vec::new();
+    let mut time = start;
+    while time < end {
+        let hour = time
+            .with_minute(0)
+            .and_then(|time| time.with_second(0))
+            .and_then(|time| time.with_nanosecond(0))
+            .unwrap_or(time);
+        let next = (hour + TimeDelta::hours(1)).min(end);
+        let span = (next - time).to_std().unwrap_or_default();
+        hours.push((time, span.mul_f64(share)));
+        if next <= time {
+            break;
+        }
+        time = next;
+    }
+    hours
+}
+
+/// A bar `value` long when `most` fills [`BAR_WIDTH`], in eighths of a
+/// character.
+fn bar(value: Duration, most: Duration) -> String {
+    const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+    if most.is_zero() || value.is_zero() {
+        return String::new();
+    }
+    let eighths = (value.as_secs_f64() / most.as_secs_f64() * (BAR_WIDTH * 8) as f64).round();
+    let eighths = (eighths as usize).max(1);
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths % 8 > 0 {
+        bar.push(EIGHTHS[eighths % 8 - 1]);
+    }
+    bar
+}
+
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// A shade for `value` out of `most`, from a dot for nothing up to a full
+/// block.
+fn shade(value: Duration, most: Duration) -> char {
+    if most.is_zero() || value.is_zero() {
+        return SHADES[0];
+    }
+    let level = (value.as_secs_f64() / most.as_secs_f64() * 4.0).ceil() as usize;
+    SHADES[level.clamp(1, 4)]
+}
+
+/// `cells` with a space between each.
+fn spaced(cells: impl Iterator<Item = char>) -> String {
+    let mut line = String::new();
+    for cell in cells {
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push(cell);
+    }
+    line
+}
+
+fn legend(out: &mut String, most: Duration, cell: &str) -> fmt::Result {
+    writeln!(
+        out,
+        "     {} none  {} {} {} {} up to {} {cell}",
+        SHADES[0],
+        SHADES[1],
+        SHADES[2],
+        SHADES[3],
+        SHADES[4],
+        hours(most)
+    )
+}
+
+/// A duration in hours and minutes, such as `2h 05m` or `45m`.
+fn hours(duration: Duration) -> String {
+    let minutes = (duration.as_secs() + 30) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, minutes) => format!("{hours}h {minutes:02}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    /// A moment in local time, so that sessions land on the same days and
+    /// hours wherever the tests run.
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> SystemTime {
+        let time = date(month, day).and_hms_opt(hour, minute, 0).unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap().into()
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    /// A session from `start` lasting `length` minutes without a pause.
+    fn session(start: SystemTime, length: u64, phase: Phase, outcome: Outcome) -> Session {
+        Session {
+            started_at: start,
+            ended_at: start + minutes(length),
+            phase,
+            planned: minutes(25),
+            actual: minutes(length),
+            paused: Duration::ZERO,
+            pauses: 0,
+            outcome,
+            task: None,
+            tags: Vec::new(),
+            profile: None,
+        }
+    }
+
+    fn tagged(session: Session, tags: &[&str]) -> Session {
+        Session {
+            tags: tags.iter().map(|tag| (*tag).to_owned()).collect(),
+            ..session
+        }
+    }
+
+    /// A Wednesday's sessions: two pomodoros, one paused, a break, a
+    /// skipped session and an interrupted one running over the hour.
+    fn wednesday() -> Vec<Session> {
+        vec![
+            tagged(
+                Session {
+                    ended_at: at(10, 14, 9, 27),
+                    paused: minutes(2),
+                    pauses: 1,
+                    ..session(at(10, 14, 9, 0), 25, Phase::Work, Outcome::Completed)
+                },
+                &["thesis"],
+            ),
+            session(at(10, 14, 9, 27), 5, Phase::ShortBreak, Outcome::Completed),
+            tagged(
+                session(at(10, 14, 9, 32), 25, Phase::Work, Outcome::Completed),
+                &["thesis", "writing"],
+            ),
+            session(at(10, 14, 10, 0), 10, Phase::Work, Outcome::Skipped),
+            session(at(10, 14, 13, 50), 20, Phase::Work, Outcome::Interrupted),
+        ]
+    }
+
+    fn render(range: Range, sessions: Vec<Session>) -> String {
+        Report::new("Title".to_owned(), range, sessions, None).render()
+    }
+
+    /// The week of Monday the 12th: Wednesday's sessions, two pomodoros
+    /// on Monday and one on Saturday.
+    fn week() -> Vec<Session> {
+        let mut sessions = vec![
+            session(at(10, 12, 8, 0), 25, Phase::Work, Outcome::Completed),
+            session(at(10, 12, 8, 30), 25, Phase::Work, Outcome::Completed),
+        ];
+        sessions.extend(wednesday());
+        sessions.push(tagged(
+            session(at(10, 17, 22, 0), 25, Phase::Work, Outcome::Completed),
+            &["reading"],
+        ));
+        sessions
+    }
+
+    /// Two pomodoros a day, and weekends off.
+    fn goal() -> GoalConfig {
+        GoalConfig {
+            daily: Some(Goal::Pomodoros(2)),
+            rest_days: vec![Weekday::Sat, Weekday::Sun],
+        }
+    }
+
+    /// What each day of `sessions` added up to.
+    fn days(sessions: &[Session]) -> BTreeMap<NaiveDate, Day> {
+        let mut days: BTreeMap<NaiveDate, Day> = BTreeMap::new();
+        for session in sessions.iter().filter(|s| s.phase == Phase::Work) {
+            days.entry(goal::local_day(session.started_at))
+                .or_default()
+                .add(session.actual, session.outcome == Outcome::Completed);
+        }
+        days
+    }
+
+    /// The lines from `heading` to the blank line after it.
+    fn section<'a>(report: &'a str, heading: &str) -> Vec<&'a str> {
+        report
+            .lines()
+            .skip_while(|line| *line != heading)
+            .take_while(|line| !line.is_empty())
+            .collect()
+    }
+
+    fn golden(lines: &[&str]) -> String {
+        lines.join("\n") + "\n"
+    }
+
+    #[test]
+    fn reports_an_empty_range() {
+        assert_eq!(
+            render(Period::Week.range(date(10, 16)), Vec::new()),
+            "Title\n\nNo sessions recorded.\n"
+        );
+    }
+
+    #[test]
+    fn reports_a_day() {
+        let expected = [
+            "Title",
+            "",
+            "Focus time      1h 20m in 4 work sessions",
+            "Pomodoros       2 completed, 2 abandoned (1 skipped, 1 interrupted)",
+            "Average session 20m",
+            "Interruptions   1 pause, 2m paused",
+            "Breaks          5m in 1 break",
+            "",
+            "By day",
+            "Wed 14 Oct  ██████████████████████████████   1h 20m  ●●",
+            "",
+            "By hour",
+            "     0     3     6     9     12    15    18    21",
+            "Wed  · · · · · · · · · █ ░ · · ░ ░ · · · · · · · · ·",
+            "     · none  ░ ▒ ▓ █ up to 50m an hour",
+            "",
+            "By tag",
+            "thesis      ██████████████████████████████      50m  2 sessions",
+            "(untagged)  ██████████████████                  30m  2 sessions",
+            "writing     ███████████████                     25m  1 session",
+        ];
+        assert_eq!(
+            render(Period::Today.range(date(10, 14)), wednesday()),
+            golden(&expected)
+        );
+    }
+
+    #[test]
+    fn reports_a_week_against_the_goal() {
+        // On Friday, which has yet to meet the goal and so is not counted
+        // against it, after a Thursday that missed it.
+        let report = Report::new(
+            "Title".to_owned(),
+            Period::Week.range(date(10, 16)),
+            week(),
+            None,
+        )
+        .with_goal(&goal(), days(&week()), date(10, 16));
+        let expected = [
+            "Title",
+            "",
+            "Focus time      2h 35m in 7 work sessions",
+            "Pomodoros       5 completed, 2 abandoned (1 skipped, 1 interrupted)",
+            "Average session 22m",
+            "Interruptions   1 pause, 2m paused",
+            "Breaks          5m in 1 break",
+            "Daily goal      2 pomodoros, met on 2 of 4 days; 0 days in a row now",
+            "",
+            "By day",
+            "Mon 12 Oct  ██████████████████▊                 50m  ✓ ●●",
+            "Tue 13 Oct                                       0m",
+            "Wed 14 Oct  ██████████████████████████████   1h 20m  ✓ ●●",
+            "Thu 15 Oct                                       0m",
+            "Fri 16 Oct                                       0m",
+            "Sat 17 Oct  █████████▍                          25m    ●",
+            "Sun 18 Oct                                       0m",
+            "",
+            "By hour",
+            "     0     3     6     9     12    15    18    21",
+            "Mon  · · · · · · · · █ · · · · · · · · · · · · · · ·",
+            "Tue  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Wed  · · · · · · · · · █ ░ · · ░ ░ · · · · · · · · ·",
+            "Thu  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Fri  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Sat  · · · · · · · · · · · · · · · · · · · · · · ▒ ·",
+            "Sun  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "     · none  ░ ▒ ▓ █ up to 50m an hour",
+            "",
+            "By tag",
+            "(untagged)  ██████████████████████████████   1h 20m  4 sessions",
+            "thesis      ██████████████████▊                 50m  2 sessions",
+            "reading     █████████▍                          25m  1 session",
+            "writing     █████████▍                          25m  1 session",
+        ];
+        assert_eq!(report.render(), golden(&expected));
+    }
+
+    #[test]
+    fn reports_a_month_a_bar_a_day() {
+        let report = render(Period::Month.range(date(10, 16)), week());
+        assert_eq!(
+            section(&report, "By day"),
+            [
+                "By day",
+                "Thu 1 Oct                                        0m",
+                "Fri 2 Oct                                        0m",
+                "Sat 3 Oct                                        0m",
+                "Sun 4 Oct                                        0m",
+                "Mon 5 Oct                                        0m",
+                "Tue 6 Oct                                        0m",
+                "Wed 7 Oct                                        0m",
+                "Thu 8 Oct                                        0m",
+                "Fri 9 Oct                                        0m",
+                "Sat 10 Oct                                       0m",
+                "Sun 11 Oct                                       0m",
+                "Mon 12 Oct  ██████████████████▊                 50m  ●●",
+                "Tue 13 Oct                                       0m",
+                "Wed 14 Oct  ██████████████████████████████   1h 20m  ●●",
+                "Thu 15 Oct                                       0m",
+                "Fri 16 Oct                                       0m",
+                "Sat 17 Oct  █████████▍                          25m  ●",
+                "Sun 18 Oct                                       0m",
+                "Mon 19 Oct                                       0m",
+                "Tue 20 Oct                                       0m",
+                "Wed 21 Oct                                       0m",
+                "Thu 22 Oct                                       0m",
+                "Fri 23 Oct                                       0m",
+                "Sat 24 Oct                                       0m",
+                "Sun 25 Oct                                       0m",
+                "Mon 26 Oct                                       0m",
+                "Tue 27 Oct                                       0m",
+                "Wed 28 Oct                                       0m",
+                "Thu 29 Oct                                       0m",
+                "Fri 30 Oct                                       0m",
+                "Sat 31 Oct                                       0m",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_longer_ranges_as_a_calendar() {
+        // From a Wednesday, so the first week is partly outside the range,
+        // and October starts in the week after.
+        let report = render(Range::new(date(9, 23), date(11, 8)), week());
+        assert_eq!(
+            section(&report, "By day"),
+            [
+                "By day",
+                "       Oct     Nov",
+                "Mon    · · ▓ · · ·",
+                "Tue    · · · · · ·",
+                "Wed  · · · █ · · ·",
+                "Thu  · · · · · · ·",
+                "Fri  · · · · · · ·",
+                "Sat  · · · ▒ · · ·",
+                "Sun  · · · · · · ·",
+                "     · none  ░ ▒ ▓ █ up to 1h 20m a day",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_only_the_sessions_with_a_tag() {
+        let report = Report::new(
+            "Title".to_owned(),
+            Period::Week.range(date(10, 16)),
+            week(),
+            Some("thesis"),
+        )
+        .render();
+        let expected = [
+            "Title",
+            "",
+            "Focus time      50m in 2 work sessions",
+            "Pomodoros       2 completed, 0 abandoned (0 skipped, 0 interrupted)",
+            "Average session 25m",
+            "Interruptions   1 pause, 2m paused",
+            "Breaks          0m in 0 breaks",
+            "",
+            "By day",
+            "Mon 12 Oct                                       0m",
+            "Tue 13 Oct                                       0m",
+            "Wed 14 Oct  ██████████████████████████████      50m  ●●",
+            "Thu 15 Oct                                       0m",
+            "Fri 16 Oct                                       0m",
+            "Sat 17 Oct                                       0m",
+            "Sun 18 Oct                                       0m",
+            "",
+            "By hour",
+            "     0     3     6     9     12    15    18    21",
+            "Mon  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Tue  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Wed  · · · · · · · · · █ · · · · · · · · · · · · · ·",
+            "Thu  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Fri  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Sat  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "Sun  · · · · · · · · · · · · · · · · · · · · · · · ·",
+            "     · none  ░ ▒ ▓ █ up to 50m an hour",
+            "",
+            "By tag",
+            "thesis   ██████████████████████████████      50m  2 sessions",
+            "writing  ███████████████                     25m  1 session",
+        ];
+        assert_eq!(report, golden(&expected));
+    }
+
+    #[test]
+    fn names_each_month_above_the_week_it_starts_in() {
+        let months = |first, last| {
+            let report = render(Range::new(first, last), week());
+            section(&report, "By day")[1].to_owned()
+        };
+        // The first week's month has room before the next one starts.
+        assert_eq!(months(date(8, 3), date(10, 25)), "     Aug     Sep     Oct");
+        // A year's turn.
+        let report = render(
+            Range::new(date(11, 30), NaiveDate::from_ymd_opt(2027, 1, 10).unwrap()),
+            vec![session(
+                at(12, 2, 9, 0),
+                25,
+                Phase::Work,
+                Outcome::Completed,
+            )],
+        );
+        assert_eq!(section(&report, "By day")[1], "     Dec     Jan");
+    }
+
+    #[test]
+    fn spans_each_period() {
+        let friday = date(10, 16);
+        let range = |period: Period| {
+            let range = period.range(friday);
+            (range.first, range.last)
+        };
+        assert_eq!(range(Period::Today), (friday, friday));
+        assert_eq!(range(Period::Yesterday), (date(10, 15), date(10, 15)));
+        assert_eq!(range(Period::Week), (date(10, 12), date(10, 18)));
+        assert_eq!(range(Period::LastWeek), (date(10, 5), date(10, 11)));
+        assert_eq!(range(Period::Month), (date(10, 1), date(10, 31)));
+        assert_eq!(range(Period::LastMonth), (date(9, 1), date(9, 30)));
+        // Across the turn of a year, from a Thursday the 1st.
+        let new_year = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let last_year = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let last_month = Period::LastMonth.range(new_year);
+        assert_eq!(
+            (last_month.first, last_month.last),
+            (last_year(12, 1), last_year(12, 31))
+        );
+        let week = Period::Week.range(new_year);
+        assert_eq!((week.first, week.last), (last_year(12, 29), date(1, 4)));
+    }
+
+    #[test]
+    fn titles_ranges() {
+        assert_eq!(
+            Range::new(date(10, 16), date(10, 16)).to_string(),
+            "Fri 16 Oct 2026"
+        );
+        assert_eq!(
+            Range::new(date(10, 18), date(10, 12)).to_string(),
+            "Mon 12 Oct to Sun 18 Oct 2026"
+        );
+        let last_year = NaiveDate::from_ymd_opt(2025, 12, 29).unwrap();
+        assert_eq!(
+            Range::new(last_year, date(1, 4)).to_string(),
+            "Mon 29 Dec 2025 to Sun 4 Jan 2026"
+        );
+    }
+
+    #[test]
+    fn splits_sessions_by_the_hour() {
+        let session = Session {
+            ended_at: at(10, 14, 11, 30),
+            actual: minutes(60),
+            ..session(at(10, 14, 9, 30), 60, Phase::Work, Outcome::Completed)
+        };
+        let hours: Vec<_> = by_hour(&session)
+            .into_iter()
+            .map(|(time, focus)| (time.hour(), time.minute(), focus))
+            .collect();
+        // Half the time was spent paused, so each hour gets half its span.
+        assert_eq!(
+            hours,
+            [
+                (9, 30, minutes(15)),
+                (10, 0, minutes(30)),
+                (11, 0, minutes(15))
+            ]
+        );
+    }
+
+    #[test]
+    fn draws_bars_and_shades() {
+        assert_eq!(bar(Duration::ZERO, minutes(60)), "");
+        assert_eq!(bar(minutes(60), minutes(60)), "█".repeat(BAR_WIDTH));
+        assert_eq!(bar(minutes(30), minutes(60)), "█".repeat(BAR_WIDTH / 2));
+        // Never less than an eighth, however little.
+        assert_eq!(bar(Duration::from_secs(1), minutes(60)), "▏");
+        assert_eq!(shade(Duration::ZERO, minutes(60)), '·');
+        assert_eq!(shade(Duration::from_secs(1), minutes(60)), '░');
+        assert_eq!(shade(minutes(30), minutes(60)), '▒');
+        assert_eq!(shade(minutes(31), minutes(60)), '▓');
+        assert_eq!(shade(minutes(60), minutes(60)), '█');
+        assert_eq!(hours(Duration::from_secs(29)), "0m");
+        assert_eq!(hours(minutes(45)), "45m");
+        assert_eq!(hours(minutes(125)), "2h 05m");
+    }
+}
@@ -0,0 +1,434 @@
+//! Templates for printing the timer's status the way a script wants it.
+//!
+//! A template is text with fields in braces, such as
+//! `{phase} {remaining:mm:ss}`. Durations take an optional format after a
+//! colon; `{{` and `}}` stand for literal braces. Templates are checked when
+//! they are parsed, so a typo is reported once rather than printed on every
+//! tick.
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+use concentrato_core::format_duration;
+
+use crate::display;
+use crate::protocol::Status;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TemplateError {
+    #[error("unclosed {{ at position {0}")]
+    Unclosed(usize),
+    #[error("unmatched }} at position {0} (write }}}} for a literal brace)")]
+    Unmatched(usize),
+    #[error("unknown field {{{name}}}; expected one of {}", FIELDS.join(", "))]
+    UnknownField { name: String },
+    #[error("{{{field}}} does not take a format")]
+    UnexpectedFormat { field: String },
+    #[error(
+        "unknown duration format {format:?} for {{{field}}}; expected clock, mm:ss, hh:mm:ss, m, s or human"
+    )]
+    UnknownFormat { field: String, format: String },
+}
+
+/// The names a template can use, for error messages.
+const FIELDS: [&str; 17] = [
+    "phase",
+    "label",
+    "state",
+    "remaining",
+    "elapsed",
+    "planned",
+    "percent",
+    "task",
+    "profile",
+    "completed",
+    "today",
+    "today_focus",
+    "goal",
+    "goal_percent",
+    "streak",
+    "position",
+    "cycle_length",
+];
+
+/// A parsed `--format` template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// The phase's name, such as `short_break`.
+    Phase,
+    /// The phase's name for people, such as `Short break`.
+    Label,
+    State,
+    Remaining(DurationFormat),
+    Elapsed(DurationFormat),
+    Planned(DurationFormat),
+    /// How much of the phase has elapsed, from 0 to 100.
+    Percent,
+    /// The task, or nothing.
+    Task,
+    /// The profile, or nothing.
+    Profile,
+    Completed,
+    Today,
+    TodayFocus(DurationFormat),
+    /// Today's progress towards the daily goal, such as `5/8 pomodoros`, or
+    /// nothing.
+    Goal,
+    /// Today's progress towards the daily goal, from 0 to 100, or nothing.
+    GoalPercent,
+    Streak,
+    /// The phase's place in the cycle, counting from 1.
+    Position,
+    CycleLength,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DurationFormat {
+    /// `m:ss`, or `h:mm:ss` from an hour up.
+    Clock,
+    /// Minutes and seconds, as in `05:00` or `90:00`.
+    MinutesSeconds,
+    /// `hh:mm:ss`.
+    HoursMinutesSeconds,
+    /// Whole minutes, rounded up.
+    Minutes,
+    /// Whole seconds.
+    Seconds,
+    /// As in `1h30m`.
+    Human,
+}
+
+impl Template {
+    pub fn render(&self, status: &Status) -> String {
+        let mut out = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Field(field) => {
+                    let _ = field.render(status, &mut out);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.char_indices().peekable();
+        while let Some((at, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+                '}' => return Err(TemplateError::Unmatched(at)),
+                '{' => {
+                    let end = s[at..]
+                        .find('}')
+                        .map(|len| at + len)
+                        .ok_or(TemplateError::Unclosed(at))?;
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(s[at + 1..end].parse()?));
+                    while chars.next_if(|&(i, _)| i <= end).is_some() {}
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Self { pieces })
+    }
+}
+
+impl FromStr for Field {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, format) = match s.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format.trim())),
+            None => (s.trim(), None),
+        };
+        let duration = |field: fn(DurationFormat) -> Field| {
+            let format = match format {
+                None | Some("clock") => DurationFormat::Clock,
+                Some("mm:ss") => DurationFormat::MinutesSeconds,
+                Some("hh:mm:ss") => DurationFormat::HoursMinutesSeconds,
+                Some("m") => DurationFormat::Minutes,
+                Some("s") => DurationFormat::Seconds,
+                Some("human") => DurationFormat::Human,
+                Some(format) => {
+                    return Err(TemplateError::UnknownFormat {
+                        field: name.to_owned(),
+                        format: format.to_owned(),
+                    });
+                }
+            };
+            Ok(field(format))
+        };
+        let field = match name {
+            "remaining" => return duration(Field::Remaining),
+            "elapsed" => return duration(Field::Elapsed),
+            "planned" => return duration(Field::Planned),
+            "today_focus" => return duration(Field::TodayFocus),
+            "phase" => Field::Phase,
+            "label" => Field::Label,
+            "state" => Field::State,
+            "percent" => Field::Percent,
+            "task" => Field::Task,
+            "profile" => Field::Profile,
+            "completed" => Field::Completed,
+            "today" => Field::Today,
+            "goal" => Field::Goal,
+            "goal_percent" => Field::GoalPercent,
+            "streak" => Field::Streak,
+            "position" => Field::Position,
+            "cycle_length" => Field::CycleLength,
+            _ => {
+                return Err(TemplateError::UnknownField {
+                    name: name.to_owned(),
+                });
+            }
+        };
+        match format {
+            Some(_) => Err(TemplateError::UnexpectedFormat {
+                field: name.to_owned(),
+            }),
+            None => Ok(field),
+        }
+    }
+}
+
+impl Field {
+    fn render(self, status: &Status, out: &mut String) -> fmt::Result {
+        match self {
+            Field::Phase => write!(out, "{}", status.phase),
+            Field::Label => write!(out, "{}", status.phase.label()),
+            Field::State => write!(out, "{}", status.state),
+            // Countdowns round up, so that they read zero only once the phase
+            // is over, and elapsed time rounds down to match.
+            Field::Remaining(format) => format.render(ceil_secs(status.remaining()), out),
+            Field::Elapsed(format) => format.render(status.elapsed().as_secs(), out),
+            Field::Planned(format) => format.render(ceil_secs(status.planned()), out),
+            Field::Percent => write!(out, "{}", status.percent()),
+            Field::Task => write!(out, "{}", status.task.as_deref().unwrap_or_default()),
+            Field::Profile => write!(out, "{}", status.profile.as_deref().unwrap_or_default()),
+            Field::Completed => write!(out, "{}", status.completed),
+            Field::Today => write!(out, "{}", status.today),
+            Field::TodayFocus(format) => format.render(status.today_focus().as_secs(), out),
+            Field::Goal => match status.goal {
+                Some(goal) => out.write_str(&goal.describe(status.today_totals())),
+                None => Ok(()),
+            },
+            Field::GoalPercent => match status.goal {
+                Some(goal) => {
+                    let progress = goal.progress(status.today_totals()).min(1.0);
+                    write!(out, "{}", (progress * 100.0) as u64)
+                }
+                None => Ok(()),
+            },
+            Field::Streak => write!(out, "{}", status.streak),
+            Field::Position => write!(out, "{}", status.position + 1),
+            Field::CycleLength => write!(out, "{}", status.cycle_length),
+        }
+    }
+}
+
+impl DurationFormat {
+    fn render(self, secs: u64, out: &mut String) -> fmt::Result {
+        match self {
+            DurationFormat::Clock => out.write_str(&display::clock(Duration::from_secs(secs))),
+            DurationFormat::MinutesSeconds => write!(out, "{:02}:{:02}", secs / 60, secs % 60),
+            DurationFormat::HoursMinutesSeconds => write!(
+                out,
+                "{:02}:{:02}:{:02}",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60
+            ),
+            DurationFormat::Minutes => write!(out, "{}", secs.div_ceil(60)),
+            DurationFormat::Seconds => write!(out, "{secs}"),
+            DurationFormat::Human => out.write_str(&format_duration(Duration::from_secs(secs))),
+        }
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use concentrato_core::Phase;
+
+    use super::*;
+    use crate::goal::Goal;
+
+    fn status() -> Status {
+        Status {
+            phase: Phase::Work,
+            state: concentrato_core::Status::Running,
+            planned_ms: 25 * 60_000,
+            elapsed_ms: 20 * 60_000 + 500,
+            remaining_ms: 5 * 60_000 - 500,
+            position: 2,
+            cycle_length: 8,
+            completed: 1,
+            today: 3,
+            today_focus_ms: 5_400_000,
+            goal: None,
+            streak: 2,
+            profile: None,
+            task: Some("write".to_owned()),
+        }
+    }
+
+    fn render(template: &str, status: &Status) -> String {
+        template.parse::<Template>().unwrap().render(status)
+    }
+
+    #[test]
+    fn renders_fields_and_text() {
+        assert_eq!(
+            render(
+                "{label} ({state}) {position}/{cycle_length}: {task}{profile}",
+                &status()
+            ),
+            "Work (running) 3/8: write"
+        );
+        assert_eq!(render("{percent}% {{done}}", &status()), "80% {done}");
+        assert_eq!(render("", &status()), "");
+        assert_eq!(render("{ phase }", &status()), "work");
+    }
+
+    #[test]
+    fn renders_each_duration_format() {
+        let cases = [
+            ("{remaining}", "5:00"),
+            ("{remaining:clock}", "5:00"),
+            ("{remaining:mm:ss}", "05:00"),
+            ("{remaining:hh:mm:ss}", "00:05:00"),
+            ("{remaining:m}", "5"),
+            ("{remaining:s}", "300"),
+            ("{remaining:human}", "5m"),
+            ("{elapsed:s}", "1200"),
+            ("{elapsed:m}", "20"),
+            ("{planned:mm:ss}", "25:00"),
+            ("{today_focus}", "1:30:00"),
+            ("{today_focus:mm:ss}", "90:00"),
+            ("{today_focus:hh:mm:ss}", "01:30:00"),
+            ("{today_focus:human}", "1h30m"),
+            ("{today_focus: m }", "90"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(render(template, &status()), expected, "{template}");
+        }
+    }
+
+    #[test]
+    fn rounds_countdowns_up_and_elapsed_time_down() {
+        let status = Status {
+            elapsed_ms: 61_999,
+            remaining_ms: 1_001,
+            ..status()
+        };
+        assert_eq!(render("{remaining:s} {elapsed:s}", &status), "2 61");
+        assert_eq!(render("{remaining:m} {elapsed:m}", &status), "1 2");
+    }
+
+    #[test]
+    fn leaves_out_a_missing_goal() {
+        assert_eq!(render("[{goal}|{goal_percent}]", &status()), "[|]");
+        let status = Status {
+            goal: Some(Goal::Pomodoros(4)),
+            ..status()
+        };
+        assert_eq!(render("{goal_percent}", &status), "75");
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert_eq!(
+            "{phase} {remainig}".parse::<Template>(),
+            Err(TemplateError::UnknownField {
+                name: "remainig".to_owned()
+            })
+        );
+        assert_eq!(
+            "{}".parse::<Template>(),
+            Err(TemplateError::UnknownField {
+                name: String::new()
+            })
+        );
+        let message = "{x}".parse::<Template>().unwrap_err().to_string();
+        assert!(message.starts_with("unknown field {x}; expected one of phase, label,"));
+    }
+
+    #[test]
+    fn rejects_unbalanced_braces() {
+        assert_eq!(
+            "{phase".parse::<Template>(),
+            Err(TemplateError::Unclosed(0))
+        );
+        assert_eq!(
+            "{phase} {remaining".parse::<Template>(),
+            Err(TemplateError::Unclosed(8))
+        );
+        assert_eq!(
+            "phase}".parse::<Template>(),
+            Err(TemplateError::Unmatched(5))
+        );
+        assert_eq!(
+            "{{phase}".parse::<Template>(),
+            Err(TemplateError::Unmatched(7))
+        );
+        assert_eq!(
+            TemplateError::Unmatched(5).to_string(),
+            "unmatched } at position 5 (write }} for a literal brace)"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_formats() {
+        assert_eq!(
+            "{remaining:ms}".parse::<Template>(),
+            Err(TemplateError::UnknownFormat {
+                field: "remaining".to_owned(),
+                format: "ms".to_owned(),
+            })
+        );
+        assert_eq!(
+            "{phase:upper}".parse::<Template>(),
+            Err(TemplateError::UnexpectedFormat {
+                field: "phase".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn knows_every_field_it_lists() {
+        for field in FIELDS {
+            assert!(
+                format!("{{{field}}}").parse::<Template>().is_ok(),
+                "{field}"
+            );
+        }
+    }
+}
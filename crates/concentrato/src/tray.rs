@@ -0,0 +1,327 @@
+//! A system tray icon, through the StatusNotifierItem protocol that KDE,
+//! GNOME's AppIndicator extension, waybar and most other trays speak.
+//!
+//! The tray is a client of the daemon like any other: it subscribes to the
+//! timer, draws its icon as a ring of the time left, and turns clicks into
+//! requests. Clicking the icon toggles the timer and scrolling up extends
+//! it; the [`menu`] has the rest. Trays that come and go, as when a panel
+//! restarts, are picked up again as they appear.
+
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+use std::{io, process};
+
+use log::{debug, info, warn};
+use zbus::blocking::connection;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::object_server::InterfaceRef;
+use zbus::blocking::{Connection, Proxy};
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+
+use crate::client::{Client, ClientError};
+use crate::display;
+use crate::protocol::{Request, Status};
+
+mod icon;
+mod menu;
+
+use icon::Pixmap;
+use menu::{Entry, Menu};
+
+const PATH: &str = "/StatusNotifierItem";
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrayError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("cannot put an icon in the tray: {0}")]
+    Bus(#[from] zbus::Error),
+}
+
+/// Shows the timer the daemon at `socket` keeps in the tray until the user
+/// quits it. `profiles` are offered in the menu, and scrolling extends the
+/// phase by `step`.
+pub fn run(socket: &Path, profiles: Vec<String>, step: Duration) -> Result<(), TrayError> {
+    let client = Client::connect(socket)?;
+    let (status, subscription) = Client::connect(socket)?.subscribe(true)?;
+    let (sender, inputs) = mpsc::channel();
+    {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for notification in subscription {
+                let input = notification.map(|notification| notification.status().clone());
+                if sender.send(Input::Status(input)).is_err() {
+                    return;
+                }
+            }
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            let _ = sender.send(Input::Status(Err(eof.into())));
+        });
+    }
+
+    let menu = menu::build(&status, &profiles, step);
+    let tray = Arc::new(Mutex::new(Tray {
+        client,
+        status,
+        profiles,
+        step,
+        menu,
+        revision: 1,
+        quit: sender,
+    }));
+    // The specification asks for a name like this one, unique to the
+    // process.
+    let name = format!("org.kde.StatusNotifierItem-{}-1", process::id());
+    let connection = connection::Builder::session()?
+        .name(name.as_str())?
+        .serve_at(
+            PATH,
+            Item {
+                tray: Arc::clone(&tray),
+            },
+        )?
+        .serve_at(
+            menu::PATH,
+            Menu {
+                tray: Arc::clone(&tray),
+            },
+        )?
+        .build()?;
+    {
+        let connection = connection.clone();
+        thread::spawn(move || register(&connection, &name));
+    }
+
+    let server = connection.object_server();
+    let item = server.interface::<_, Item>(PATH)?;
+    let menu = server.interface::<_, Menu>(menu::PATH)?;
+    for input in inputs {
+        let status = match input {
+            Input::Status(status) => status?,
+            Input::Quit => return Ok(()),
+        };
+        if let Err(err) = update(&tray, status, &item, &menu) {
+            debug!("cannot update the tray: {err}");
+        }
+    }
+    Ok(())
+}
+
+enum Input {
+    /// The timer's status after a change or tick, or why there are no more.
+    Status(Result<Status, ClientError>),
+    Quit,
+}
+
+/// What the icon and menu show, and what they need to act.
+struct Tray {
+    /// The connection requests go over, next to the subscription.
+    client: Client,
+    status: Status,
+    profiles: Vec<String>,
+    step: Duration,
+    menu: Entry,
+    /// Counts the versions of the menu, as dbusmenu asks.
+    revision: u32,
+    quit: Sender<Input>,
+}
+
+/// Something the tray's icon or menu was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Toggle,
+    Skip,
+    Extend,
+    Profile(String),
+    Quit,
+}
+
+fn act(tray: &Mutex<Tray>, action: Action) {
+    let mut tray = lock(tray);
+    let request = match action {
+        Action::Toggle => Request::Toggle,
+        Action::Skip => Request::Skip,
+        Action::Extend => Request::extend(tray.step),
+        Action::Profile(profile) => Request::Start {
+            profile: Some(profile),
+            task: None,
+        },
+        Action::Quit => {
+            let _ = tray.quit.send(Input::Quit);
+            return;
+        }
+    };
+    // The subscription brings the new status; all that matters here is
+    // whether the daemon went along.
+    match tray.client.request(&request) {
+        Ok(_) => {}
+        Err(ClientError::Refused { message, .. }) => info!("{message}"),
+        Err(err) => warn!("{err}"),
+    }
+}
+
+/// Shows `status`, telling the host about whatever that changed.
+fn update(
+    tray: &Mutex<Tray>,
+    status: Status,
+    item: &InterfaceRef<Item>,
+    menu: &InterfaceRef<Menu>,
+) -> zbus::Result<()> {
+    let (icon_changed, tip_changed, revision) = {
+        let mut tray = lock(tray);
+        let old = icon::render(&tray.status);
+        let icon_changed = icon::render(&status) != old;
+        let tip_changed = tool_tip(&status) != tool_tip(&tray.status);
+        let entries = menu::build(&status, &tray.profiles, tray.step);
+        let revision = if entries == tray.menu {
+            None
+        } else {
+            tray.menu = entries;
+            tray.revision += 1;
+            Some(tray.revision)
+        };
+        tray.status = status;
+        (icon_changed, tip_changed, revision)
+    };
+    zbus::block_on(async {
+        if icon_changed {
+            Item::new_icon(item.signal_emitter()).await?;
+        }
+        if tip_changed {
+            Item::new_tool_tip(item.signal_emitter()).await?;
+        }
+        if let Some(revision) = revision {
+            Menu::layout_updated(menu.signal_emitter(), revision, 0).await?;
+        }
+        Ok(())
+    })
+}
+
+/// Registers the item with the tray now and whenever a tray starts later.
+fn register(connection: &Connection, name: &str) {
+    let changes = DBusProxy::new(connection)
+        .and_then(|proxy| proxy.receive_name_owner_changed_with_args(&[(0, WATCHER)]));
+    let register = || {
+        Proxy::new(connection, WATCHER, WATCHER_PATH, WATCHER)
+            .and_then(|watcher| watcher.call::<_, _, ()>("RegisterStatusNotifierItem", &name))
+    };
+    match register() {
+        Ok(()) => info!("in the tray"),
+        Err(err) => info!("waiting for a tray to appear: {err}"),
+    }
+    let changes = match changes {
+        Ok(changes) => changes,
+        Err(err) => {
+            warn!("will not notice trays that start later: {err}");
+            return;
+        }
+    };
+    for change in changes {
+        let Ok(args) = change.args() else { continue };
+        if args.new_owner().is_some() {
+            match register() {
+                Ok(()) => info!("in the tray"),
+                Err(err) => warn!("cannot get into the tray: {err}"),
+            }
+        }
+    }
+}
+
+/// The tooltip's title and text.
+fn tool_tip(status: &Status) -> (String, String) {
+    let details = display::details(status);
+    match details.split_once('\n') {
+        Some((summary, rest)) => (summary.to_owned(), rest.to_owned()),
+        None => (details, String::new()),
+    }
+}
+
+/// The item as served on the bus.
+struct Item {
+    tray: Arc<Mutex<Tray>>,
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    /// A click on the icon.
+    fn activate(&self, _x: i32, _y: i32) {
+        act(&self.tray, Action::Toggle);
+    }
+
+    fn scroll(&self, delta: i32, orientation: &str) {
+        if delta > 0 && orientation.eq_ignore_ascii_case("vertical") {
+            act(&self.tray, Action::Extend);
+        }
+    }
+
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "concentrato"
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> &str {
+        "concentrato"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn window_id(&self) -> i32 {
+        0
+    }
+
+    /// Empty, since the icon is drawn rather than taken from a theme.
+    #[zbus(property)]
+    fn icon_name(&self) -> &str {
+        ""
+    }
+
+    #[zbus(property)]
+    fn icon_pixmap(&self) -> Vec<Pixmap> {
+        icon::render(&lock(&self.tray).status)
+    }
+
+    #[zbus(property)]
+    fn tool_tip(&self) -> (String, Vec<Pixmap>, String, String) {
+        let (title, text) = tool_tip(&lock(&self.tray).status);
+        (String::new(), Vec::new(), title, text)
+    }
+
+    /// Whether clicking the icon only opens the menu, which it does not.
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> zbus::zvariant::ObjectPath<'_> {
+        zbus::zvariant::ObjectPath::from_static_str_unchecked(menu::PATH)
+    }
+
+    #[zbus(signal)]
+    async fn new_icon(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_tool_tip(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// Locks `mutex`, carrying on even if a thread panicked while holding it:
+/// the tray's state is replaced a field at a time, each whole.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,188 @@
+//! The tray icon: a ring that empties as the phase runs down.
+
+use std::f64::consts::TAU;
+
+use concentrato_core::{Phase, Status as State};
+
+use crate::protocol::Status;
+
+/// The sizes the icon is drawn at, so the host can pick the closest rather
+/// than scale one.
+const SIZES: [i32; 4] = [16, 22, 32, 48];
+
+/// An image as StatusNotifierItem takes it: width, height and ARGB32 pixels
+/// in network byte order.
+pub(super) type Pixmap = (i32, i32, Vec<u8>);
+
+/// The icon for `status`, at every size.
+pub(super) fn render(status: &Status) -> Vec<Pixmap> {
+    SIZES.into_iter().map(|size| ring(status, size)).collect()
+}
+
+fn ring(status: &Status, size: i32) -> Pixmap {
+    let color = color(status);
+    let left = match status.planned_ms {
+        0 => 0.0,
+        planned => status.remaining_ms as f64 / planned as f64,
+    };
+    let half = f64::from(size) / 2.0;
+    let width = (f64::from(size) / 6.0).max(2.0);
+    let middle = half - width / 2.0 - 0.5;
+    let paused = status.state == State::Paused;
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let (dx, dy) = (f64::from(x) + 0.5 - half, f64::from(y) + 0.5 - half);
+            // How much of the pixel the ring covers, for smooth edges.
+            let coverage = (width / 2.0 + 0.5 - (dx.hypot(dy) - middle).abs()).clamp(0.0, 1.0);
+            // Clockwise from the top, as on a clock face.
+            let turn = dx.atan2(-dy).rem_euclid(TAU) / TAU;
+            let (rgb, alpha) = if paused && pause_sign(size, x, y) {
+                (color, 1.0)
+            } else if turn < left {
+                (color, coverage)
+            } else {
+                (TRACK, coverage * 0.35)
+            };
+            pixels.push((alpha * 255.0).round() as u8);
+            pixels.extend_from_slice(&rgb);
+        }
+    }
+    (size, size, pixels)
+}
+
+/// Whether a pixel is part of the two bars of a pause sign inside the ring.
+fn pause_sign(size: i32, x: i32, y: i32) -> bool {
+    let unit = (size / 8).max(1);
+    let (middle, reach) = (size / 2, 3 * unit / 2);
+    let in_bar = |from: i32| (from..from + unit).contains(&x);
+    (middle - reach..middle + reach).contains(&y)
+        && (in_bar(middle - reach) || in_bar(middle + unit / 2))
+}
+
+/// The unfilled part of the ring.
+const TRACK: [u8; 3] = [0x88, 0x88, 0x88];
+
+/// The same colors status bars show.
+fn color(status: &Status) -> [u8; 3] {
+    match (status.state, status.phase) {
+        (State::Idle, _) => [0x88, 0x88, 0x88],
+        (State::Paused, _) => [0xe5, 0xc0, 0x7b],
+        (State::Running, Phase::Work) => [0xe0, 0x6c, 0x75],
+        (State::Running, Phase::ShortBreak | Phase::LongBreak) => [0x98, 0xc3, 0x79],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORK: [u8; 3] = [0xe0, 0x6c, 0x75];
+
+    fn status(state: State, left: u64) -> Status {
+        Status {
+            phase: Phase::Work,
+            state,
+            planned_ms: 100_000,
+            elapsed_ms: 100_000 - left * 1000,
+            remaining_ms: left * 1000,
+            position: 0,
+            cycle_length: 8,
+            completed: 0,
+            today: 0,
+            today_focus_ms: 0,
+            goal: None,
+            streak: 0,
+            profile: None,
+            task: None,
+        }
+    }
+
+    /// The alpha and color of the pixel at `x`, `y`.
+    fn pixel((size, _, pixels): &Pixmap, x: i32, y: i32) -> (u8, [u8; 3]) {
+        let at = ((y * size + x) * 4) as usize;
+        (pixels[at], [pixels[at + 1], pixels[at + 2], pixels[at + 3]])
+    }
+
+    /// The share of the ring's pixels in the phase's color.
+    fn filled(icon: &Pixmap) -> f64 {
+        let size = icon.0;
+        let (mut ring, mut colored) = (0_u32, 0_u32);
+        for y in 0..size {
+            for x in 0..size {
+                let (alpha, rgb) = pixel(icon, x, y);
+                if alpha > 0 {
+                    ring += 1;
+                    colored += u32::from(rgb == WORK);
+                }
+            }
+        }
+        f64::from(colored) / f64::from(ring)
+    }
+
+    #[test]
+    fn draws_every_size() {
+        let icons = render(&status(State::Running, 50));
+        let sizes: Vec<_> = icons.iter().map(|(w, h, _)| (*w, *h)).collect();
+        assert_eq!(sizes, [(16, 16), (22, 22), (32, 32), (48, 48)]);
+        for (size, _, pixels) in &icons {
+            assert_eq!(pixels.len(), (size * size * 4) as usize);
+        }
+    }
+
+    #[test]
+    fn fills_the_ring_with_the_time_left() {
+        let full = ring(&status(State::Running, 100), 32);
+        assert_eq!(filled(&full), 1.0);
+        let empty = ring(&status(State::Running, 0), 32);
+        assert_eq!(filled(&empty), 0.0);
+
+        let half = ring(&status(State::Running, 50), 32);
+        assert!((filled(&half) - 0.5).abs() < 0.02, "{}", filled(&half));
+        // Clockwise from the top: the right half is what is left.
+        assert_eq!(pixel(&half, 30, 16), (255, WORK));
+        assert_eq!(pixel(&half, 1, 16).1, TRACK);
+        assert!(pixel(&half, 1, 16).0 < 128);
+
+        // The middle stays clear, and an empty plan counts as run out.
+        assert_eq!(pixel(&half, 16, 16).0, 0);
+        let unplanned = Status {
+            planned_ms: 0,
+            ..status(State::Running, 0)
+        };
+        assert_eq!(filled(&ring(&unplanned, 32)), 0.0);
+    }
+
+    #[test]
+    fn shows_a_pause_sign_when_paused() {
+        let paused = ring(&status(State::Paused, 50), 32);
+        // One of the bars, the gap between them, and the other.
+        assert_eq!(pixel(&paused, 11, 16), (255, [0xe5, 0xc0, 0x7b]));
+        assert_eq!(pixel(&paused, 16, 16).0, 0);
+        assert_eq!(pixel(&paused, 18, 16), (255, [0xe5, 0xc0, 0x7b]));
+        assert_eq!(pixel(&ring(&status(State::Running, 50), 32), 11, 16).0, 0);
+    }
+
+    #[test]
+    fn draws_two_bars_for_the_pause_sign() {
+        // At 16 pixels, two bars two pixels wide and six tall.
+        let rows: Vec<String> = (0..16)
+            .map(|y| {
+                (0..16)
+                    .map(|x| if pause_sign(16, x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        let mut expected = vec!["................"; 5];
+        expected.extend([".....##..##....."; 6]);
+        expected.extend(["................"; 5]);
+        assert_eq!(rows, expected);
+        // At every size, with a gap between them.
+        for size in SIZES {
+            let row: Vec<bool> = (0..size).map(|x| pause_sign(size, x, size / 2)).collect();
+            let edges = row.windows(2).filter(|pair| pair[0] != pair[1]).count();
+            assert_eq!(edges, 4, "{size}");
+            assert!(!row[size as usize / 2], "{size}");
+        }
+    }
+}
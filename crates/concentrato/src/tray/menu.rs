@@ -0,0 +1,494 @@
+//! The tray's menu, served over the `com.canonical.dbusmenu` interface that
+//! StatusNotifierItem hosts read menus through.
+//!
+//! The menu is rebuilt from the timer's status whenever it changes, and hosts
+//! are told to fetch it again when the result differs from what they have.
+
+use std::collections::HashMap;
+use std::slice;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use concentrato_core::Status as State;
+use concentrato_core::format_duration;
+use log::debug;
+use serde::Serialize;
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedValue, StructureBuilder, Type, Value};
+
+use super::{Action, Tray};
+use crate::display;
+use crate::protocol::Status;
+
+pub(super) const PATH: &str = "/MenuBar";
+
+/// Ids of the entries, which stay the same as labels change. Profiles take
+/// the ids from [`PROFILES`] on.
+const ROOT: i32 = 0;
+const TOGGLE: i32 = 1;
+const SKIP: i32 = 2;
+const EXTEND: i32 = 3;
+const PROFILE_MENU: i32 = 4;
+const TODAY: i32 = 5;
+const CYCLE: i32 = 6;
+const QUIT: i32 = 7;
+const PROFILES: i32 = 100;
+
+/// One entry of the menu.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Entry {
+    id: i32,
+    kind: Kind,
+    label: String,
+    enabled: bool,
+    children: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Standard,
+    Separator,
+    /// One of a set of choices, and whether it is the one chosen.
+    Radio(bool),
+}
+
+impl Entry {
+    fn new(id: i32, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            kind: Kind::Standard,
+            label: label.into(),
+            enabled: true,
+            children: Vec::new(),
+        }
+    }
+
+    fn separator(id: i32) -> Self {
+        Self {
+            kind: Kind::Separator,
+            ..Self::new(id, "")
+        }
+    }
+
+    fn disabled(self) -> Self {
+        Self {
+            enabled: false,
+            ..self
+        }
+    }
+
+    fn find(&self, id: i32) -> Option<&Entry> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id))
+    }
+
+    /// The entry's properties, or those of them `names` asks for unless it
+    /// is empty.
+    fn properties(&self, names: &[String]) -> HashMap<String, OwnedValue> {
+        let mut properties: Vec<(&str, Value<'_>)> = Vec::new();
+        match self.kind {
+            Kind::Separator => properties.push(("type", "separator".into())),
+            Kind::Standard => {}
+            Kind::Radio(chosen) => {
+                properties.push(("toggle-type", "radio".into()));
+                properties.push(("toggle-state", i32::from(chosen).into()));
+            }
+        }
+        if self.kind != Kind::Separator {
+            properties.push(("label", self.label.as_str().into()));
+        }
+        properties.push(("enabled", self.enabled.into()));
+        if !self.children.is_empty() {
+            properties.push(("children-display", "submenu".into()));
+        }
+        properties
+            .into_iter()
+            .filter(|(name, _)| names.is_empty() || names.iter().any(|n| n == name))
+            .map(|(name, value)| {
+                let value = value.try_to_owned().expect("menu properties hold no fds");
+                (name.to_owned(), value)
+            })
+            .collect()
+    }
+
+    /// The entry and `depth` levels of its children, all of them if
+    /// `depth` is negative, in dbusmenu's recursive `(ia{sv}av)` shape.
+    fn layout(&self, depth: i32, names: &[String]) -> Layout {
+        let children = if depth == 0 {
+            Vec::new()
+        } else {
+            self.children
+                .iter()
+                .map(|child| child.layout(depth - 1, names).into_value())
+                .collect()
+        };
+        Layout {
+            id: self.id,
+            properties: self.properties(names),
+            children,
+        }
+    }
+}
+
+/// An entry as dbusmenu lays it out.
+#[derive(Debug, Serialize, Type)]
+pub(super) struct Layout {
+    id: i32,
+    properties: HashMap<String, OwnedValue>,
+    children: Vec<OwnedValue>,
+}
+
+impl Layout {
+    fn into_value(self) -> OwnedValue {
+        let structure = StructureBuilder::new()
+            .add_field(self.id)
+            .add_field(self.properties)
+            .add_field(self.children)
+            .build()
+            .expect("a layout has fields");
+        Value::from(structure)
+            .try_into()
+            .expect("menu layouts hold no fds")
+    }
+}
+
+/// The menu for `status`, offering `profiles` and extending by `step`.
+pub(super) fn build(status: &Status, profiles: &[String], step: Duration) -> Entry {
+    let toggle = match status.state {
+        State::Idle => format!("Start {}", status.phase.label().to_lowercase()),
+        State::Running => "Pause".to_owned(),
+        State::Paused => "Resume".to_owned(),
+    };
+    let mut root = Entry::new(ROOT, "");
+    root.children = vec![
+        Entry::new(TOGGLE, toggle),
+        Entry::new(SKIP, "Skip"),
+        Entry::new(EXTEND, format!("Extend by {}", format_duration(step))),
+    ];
+    if !profiles.is_empty() {
+        // Profiles only switch for a phase that has yet to start.
+        let mut menu = Entry::new(PROFILE_MENU, "Start with profile");
+        menu.enabled = status.state == State::Idle;
+        menu.children = (PROFILES..)
+            .zip(profiles)
+            .map(|(id, name)| Entry {
+                kind: Kind::Radio(status.profile.as_ref() == Some(name)),
+                ..Entry::new(id, name.as_str())
+            })
+            .collect();
+        root.children.push(Entry::separator(-1));
+        root.children.push(menu);
+    }
+    let today = match (status.goal, status.today) {
+        (Some(goal), _) => format!(
+            "{} today, {} in a row",
+            goal.describe(status.today_totals()),
+            display::days(status.streak)
+        ),
+        (None, n) => format!("{} today", display::plural(n as usize, "session")),
+    };
+    let cycle = format!(
+        "Phase {} of {} in the cycle",
+        status.position + 1,
+        status.cycle_length
+    );
+    root.children.extend([
+        Entry::separator(-2),
+        Entry::new(TODAY, today).disabled(),
+        Entry::new(CYCLE, cycle).disabled(),
+        Entry::separator(-3),
+        Entry::new(QUIT, "Quit tray"),
+    ]);
+    // Separators need ids of their own too.
+    let mut next = PROFILES + i32::try_from(profiles.len()).unwrap_or(i32::MAX - PROFILES);
+    for entry in &mut root.children {
+        if entry.id < 0 {
+            entry.id = next;
+            next += 1;
+        }
+    }
+    root
+}
+
+/// What clicking the entry with `id` does.
+fn action(id: i32, profiles: &[String]) -> Option<Action> {
+    Some(match id {
+        TOGGLE => Action::Toggle,
+        SKIP => Action::Skip,
+        EXTEND => Action::Extend,
+        QUIT => Action::Quit,
+        id if id >= PROFILES => {
+            let index = usize::try_from(id - PROFILES).ok()?;
+            Action::Profile(profiles.get(index)?.clone())
+        }
+        _ => return None,
+    })
+}
+
+/// The menu as served on the bus.
+pub(super) struct Menu {
+    pub(super) tray: Arc<Mutex<Tray>>,
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    #[zbus(out_args("revision", "layout"))]
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        property_names: Vec<String>,
+    ) -> zbus::fdo::Result<(u32, Layout)> {
+        let tray = super::lock(&self.tray);
+        let entry = tray
+            .menu
+            .find(parent_id)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("no menu entry {parent_id}")))?;
+        Ok((
+            tray.revision,
+            entry.layout(recursion_depth, &property_names),
+        ))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        let tray = super::lock(&self.tray);
+        ids.into_iter()
+            .filter_map(|id| tray.menu.find(id))
+            .map(|entry| (entry.id, entry.properties(&property_names)))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: String) -> zbus::fdo::Result<OwnedValue> {
+        let tray = super::lock(&self.tray);
+        tray.menu
+            .find(id)
+            .and_then(|entry| entry.properties(slice::from_ref(&name)).remove(&name))
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("no property {name} on {id}")))
+    }
+
+    fn event(&self, id: i32, event_id: String, _data: OwnedValue, _timestamp: u32) {
+        if event_id != "clicked" {
+            return;
+        }
+        let action = {
+            let tray = super::lock(&self.tray);
+            action(id, &tray.profiles)
+        };
+        match action {
+            Some(action) => super::act(&self.tray, action),
+            None => debug!("ignoring a click on menu entry {id}"),
+        }
+    }
+
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        for (id, event_id, data, timestamp) in events {
+            self.event(id, event_id, data, timestamp);
+        }
+        Vec::new()
+    }
+
+    /// The menu is always up to date, so there is never anything to do
+    /// before showing it.
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    #[zbus(out_args("updates_needed", "id_errors"))]
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(signal)]
+    pub(super) async fn layout_updated(
+        emitter: &SignalEmitter<'_>,
+        revision: u32,
+        parent: i32,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use concentrato_core::Phase;
+
+    use super::*;
+    use crate::goal::Goal;
+
+    fn status(state: State, profile: Option<&str>) -> Status {
+        Status {
+            phase: Phase::Work,
+            state,
+            planned_ms: 25 * 60_000,
+            elapsed_ms: 0,
+            remaining_ms: 25 * 60_000,
+            position: 2,
+            cycle_length: 8,
+            completed: 1,
+            today: 1,
+            today_focus_ms: 25 * 60_000,
+            goal: None,
+            streak: 0,
+            profile: profile.map(str::to_owned),
+            task: None,
+        }
+    }
+
+    fn profiles() -> Vec<String> {
+        vec!["classic".to_owned(), "deep-work".to_owned()]
+    }
+
+    /// The menu as text, an entry a line and its children indented below.
+    fn outline(entry: &Entry) -> String {
+        fn write(entry: &Entry, depth: usize, out: &mut String) {
+            let label = match entry.kind {
+                Kind::Separator => "---".to_owned(),
+                Kind::Standard => entry.label.clone(),
+                Kind::Radio(chosen) => {
+                    format!("({}) {}", if chosen { "x" } else { " " }, entry.label)
+                }
+            };
+            let disabled = if entry.enabled { "" } else { " [disabled]" };
+            out.push_str(&format!(
+                "{}{} {label}{disabled}\n",
+                "  ".repeat(depth),
+                entry.id
+            ));
+            for child in &entry.children {
+                write(child, depth + 1, out);
+            }
+        }
+        let mut out = String::new();
+        for child in &entry.children {
+            write(child, 0, &mut out);
+        }
+        out
+    }
+
+    const STEP: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn builds_the_menu_of_an_idle_timer() {
+        let menu = build(&status(State::Idle, Some("deep-work")), &profiles(), STEP);
+        assert_eq!(
+            outline(&menu),
+            "1 Start work\n\
+             2 Skip\n\
+             3 Extend by 1m\n\
+             102 ---\n\
+             4 Start with profile\n\
+             \x20 100 ( ) classic\n\
+             \x20 101 (x) deep-work\n\
+             103 ---\n\
+             5 1 session today [disabled]\n\
+             6 Phase 3 of 8 in the cycle [disabled]\n\
+             104 ---\n\
+             7 Quit tray\n"
+        );
+    }
+
+    #[test]
+    fn builds_the_menu_of_a_running_or_paused_timer() {
+        for (state, toggle) in [(State::Running, "Pause"), (State::Paused, "Resume")] {
+            let menu = build(&status(state, None), &profiles(), STEP);
+            assert_eq!(menu.find(TOGGLE).unwrap().label, toggle);
+            // A phase under way keeps its profile.
+            let profile_menu = menu.find(PROFILE_MENU).unwrap();
+            assert!(!profile_menu.enabled, "{state}");
+            assert!(
+                profile_menu
+                    .children
+                    .iter()
+                    .all(|entry| entry.kind == Kind::Radio(false))
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_out_profiles_when_there_are_none() {
+        let mut status = status(State::Idle, None);
+        status.goal = Some(Goal::Pomodoros(4));
+        status.streak = 3;
+        let menu = build(&status, &[], Duration::from_secs(90));
+        assert_eq!(
+            outline(&menu),
+            "1 Start work\n\
+             2 Skip\n\
+             3 Extend by 1m30s\n\
+             100 ---\n\
+             5 1/4 pomodoros today, 3 days in a row [disabled]\n\
+             6 Phase 3 of 8 in the cycle [disabled]\n\
+             101 ---\n\
+             7 Quit tray\n"
+        );
+    }
+
+    #[test]
+    fn gives_every_entry_an_id_of_its_own() {
+        for count in [0, 1, 2, 50] {
+            let profiles: Vec<String> = (0..count).map(|n| format!("p{n}")).collect();
+            let menu = build(&status(State::Idle, None), &profiles, STEP);
+            let mut ids = vec![menu.id];
+            let mut entries: Vec<&Entry> = menu.children.iter().collect();
+            while let Some(entry) = entries.pop() {
+                ids.push(entry.id);
+                entries.extend(&entry.children);
+            }
+            let total = ids.len();
+            ids.sort_unstable();
+            ids.dedup();
+            assert_eq!(ids.len(), total, "{count} profiles");
+            assert!(ids.iter().all(|&id| id >= 0), "{count} profiles");
+        }
+    }
+
+    #[test]
+    fn maps_clicks_back_to_actions() {
+        let profiles = profiles();
+        let menu = build(&status(State::Idle, None), &profiles, STEP);
+        assert_eq!(action(TOGGLE, &profiles), Some(Action::Toggle));
+        assert_eq!(action(SKIP, &profiles), Some(Action::Skip));
+        assert_eq!(action(EXTEND, &profiles), Some(Action::Extend));
+        assert_eq!(action(QUIT, &profiles), Some(Action::Quit));
+        assert_eq!(
+            action(PROFILES, &profiles),
+            Some(Action::Profile("classic".to_owned()))
+        );
+        assert_eq!(
+            action(PROFILES + 1, &profiles),
+            Some(Action::Profile("deep-work".to_owned()))
+        );
+        // Entries that only show something, separators included.
+        for id in [ROOT, PROFILE_MENU, TODAY, CYCLE, 102, 103, 104, -1] {
+            assert_eq!(action(id, &profiles), None, "{id}");
+        }
+        for id in [102, 103, 104] {
+            assert_eq!(menu.find(id).unwrap().kind, Kind::Separator);
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! A full-screen view of the timer for the terminal.
+//!
+//! The view subscribes to the daemon like any other client, so it follows
+//! the timer to the second whoever drives it, and sends requests of its own
+//! for the keys pressed. It sticks to what every terminal can do: the
+//! alternate screen, the sixteen named colors and block characters, which
+//! carry over SSH and through multiplexers alike. When the window is too
+//! small for the big digits, the countdown shrinks to a line of text.
+
+use std::cmp::Ordering;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use concentrato_core::{Phase, Status as State, format_duration};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+
+use crate::client::{Client, ClientError};
+use crate::display;
+use crate::protocol::{Request, Status};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TuiError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("cannot drive the terminal: {0}")]
+    Terminal(#[from] io::Error),
+}
+
+/// Shows the timer the daemon at `socket` keeps until the user quits;
+/// extending the phase adds `step` to it.
+pub fn run(socket: &Path, step: Duration) -> Result<(), TuiError> {
+    // Connect before taking over the terminal, so that a daemon that is not
+    // running is reported on a screen that stays.
+    let client = Client::connect(socket)?;
+    let (status, subscription) = Client::connect(socket)?.subscribe(true)?;
+    let (sender, inputs) = mpsc::channel();
+    {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for notification in subscription {
+                let input = match notification {
+                    Ok(notification) => Input::Status(notification.status().clone()),
+                    Err(err) => Input::Lost(err),
+                };
+                if sender.send(input).is_err() {
+                    return;
+                }
+            }
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            let _ = sender.send(Input::Lost(eof.into()));
+        });
+    }
+
+    let mut terminal = ratatui::try_init()?;
+    thread::spawn(move || read_terminal(&sender));
+    let mut app = App {
+        client,
+        status,
+        step,
+        editing: None,
+        message: None,
+    };
+    let result = app.run(&mut terminal, &inputs);
+    ratatui::restore();
+    result
+}
+
+/// What the view reacts to.
+enum Input {
+    Key(KeyEvent),
+    /// The terminal was resized or otherwise needs drawing again.
+    Redraw,
+    Status(Status),
+    /// The subscription ended.
+    Lost(ClientError),
+}
+
+/// Passes on the keys pressed and the resizes.
+fn read_terminal(sender: &Sender<Input>) {
+    loop {
+        let input = match event::read() {
+            // Some terminals report releases and repeats too.
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => Input::Key(key),
+            Ok(Event::Resize(..) | Event::FocusGained) => Input::Redraw,
+            Ok(_) => continue,
+            Err(_) => return,
+        };
+        if sender.send(input).is_err() {
+            return;
+        }
+    }
+}
+
+struct App {
+    /// The connection requests go over, next to the subscription.
+    client: Client,
+    status: Status,
+    step: Duration,
+    /// The new task label being typed, if it is.
+    editing: Option<String>,
+    /// Why the daemon turned the last key down, until the next one.
+    message: Option<String>,
+}
+
+impl App {
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        inputs: &Receiver<Input>,
+    ) -> Result<(), TuiError> {
+        terminal.draw(|frame| self.draw(frame))?;
+        for input in inputs {
+            match input {
+                Input::Key(key) => {
+                    if !self.key(key)? {
+                        return Ok(());
+                    }
+                }
+                Input::Redraw => {}
+                Input::Status(status) => self.status = status,
+                Input::Lost(err) => return Err(err.into()),
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+        }
+        Ok(())
+    }
+
+    /// Acts on a key, returning whether to carry on.
+    fn key(&mut self, key: KeyEvent) -> Result<bool, TuiError> {
+        // Raw mode turns Ctrl-C into a key like any other.
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(false);
+        }
+        self.message = None;
+        if let Some(task) = &mut self.editing {
+            match edit(task, key) {
+                Edit::Typing => {}
+                Edit::Save => {
+                    let task = task.trim().to_owned();
+                    self.editing = None;
+                    self.request(Request::SetTask { task })?;
+                }
+                Edit::Cancel => self.editing = None,
+            }
+            return Ok(true);
+        }
+        match command(key, self.step) {
+            Some(Command::Quit) => return Ok(false),
+            Some(Command::Send(request)) => self.request(request)?,
+            Some(Command::EditTask) => {
+                self.editing = Some(self.status.task.clone().unwrap_or_default());
+            }
+            None => {}
+        }
+        Ok(true)
+    }
+
+    /// Sends `request`, keeping the reason if the daemon turns it down.
+    fn request(&mut self, request: Request) -> Result<(), TuiError> {
+        match self.client.request(&request) {
+            Ok(status) => self.status = status,
+            Err(ClientError::Refused { message, .. }) => self.message = Some(message),
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame<'_>) {
+        let status = &self.status;
+        let color = color(status);
+        let block = Block::bordered()
+            .title(" concentrato ")
+            .title_bottom(self.footer())
+            .border_style(Style::new().fg(color));
+        let area = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        let clock = display::clock(match status.state {
+            State::Idle => status.planned(),
+            State::Running | State::Paused => status.remaining(),
+        });
+        let big = big_text(&clock);
+        let big_width = big[0].chars().count() as u16;
+        // The big digits take the place of one line among the seven others.
+        let fits = area.width >= big_width && area.height >= GLYPH_HEIGHT as u16 + 7;
+        let mut lines = vec![heading(status), Line::default()];
+        if fits {
+            lines.extend(big.into_iter().map(|row| Line::from(row).fg(color)));
+        } else {
+            lines.push(Line::from(clock).fg(color).bold());
+        }
+        lines.push(Line::default());
+        lines.push(dots(status, color));
+        lines.push(Line::default());
+        lines.push(match &status.task {
+            Some(task) => Line::from(task.as_str()),
+            None => Line::from("no task").dim(),
+        });
+        lines.push(tally(status).dim());
+
+        let [middle] = Layout::vertical([Constraint::Length(lines.len() as u16)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(Paragraph::new(lines).centered(), middle);
+    }
+
+    /// The line along the bottom of the frame: the keys, the task being
+    /// typed, or what went wrong.
+    fn footer(&self) -> Line<'_> {
+        if let Some(task) = &self.editing {
+            return Line::from(vec![
+                " task: ".bold(),
+                Span::raw(task.as_str()),
+                "█".slow_blink(),
+                "  enter to save, esc to cancel ".dim(),
+            ]);
+        }
+        if let Some(message) = &self.message {
+            return Line::from(format!(" {message} ")).yellow();
+        }
+        let step = format_duration(self.step);
+        Line::from(format!(
+            " space pause/resume · s skip · e +{step} · t task · q quit "
+        ))
+        .dim()
+    }
+}
+
+/// What a key asks for while no task is being typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Quit,
+    Send(Request),
+    /// Start typing a new task, from the current one.
+    EditTask,
+}
+
+/// The command `key` stands for, if any, extending by `step`.
+fn command(key: KeyEvent, step: Duration) -> Option<Command> {
+    Some(match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => Command::Quit,
+        KeyCode::Char(' ' | 'p') => Command::Send(Request::Toggle),
+        KeyCode::Char('s') => Command::Send(Request::Skip),
+        KeyCode::Char('e' | '+') => Command::Send(Request::extend(step)),
+        KeyCode::Char('t') => Command::EditTask,
+        _ => return None,
+    })
+}
+
+/// Where typing a task stands after a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Typing,
+    Save,
+    Cancel,
+}
+
+/// Applies `key` to the task being typed.
+fn edit(task: &mut String, key: KeyEvent) -> Edit {
+    match key.code {
+        KeyCode::Enter => return Edit::Save,
+        KeyCode::Esc => return Edit::Cancel,
+        KeyCode::Backspace => {
+            task.pop();
+        }
+        KeyCode::Char(c) => task.push(c),
+        _ => {}
+    }
+    Edit::Typing
+}
+
+/// The phase's name, with the state when it is not running.
+fn heading(status: &Status) -> Line<'static> {
+    let label = Span::styled(
+        status.phase.label(),
+        Style::new()
+            .fg(phase_color(status.phase))
+            .add_modifier(Modifier::BOLD),
+    );
+    let state = match status.state {
+        State::Idle => " · not started",
+        State::Running => "",
+        State::Paused => " · paused",
+    };
+    Line::from(vec![label, Span::raw(state).dim()])
+}
+
+/// A dot for each phase of the cycle, the current one in its color.
+fn dots(status: &Status, color: Color) -> Line<'static> {
+    let spans = (0..status.cycle_length).map(|i| {
+        let gap = if i == 0 { "" } else { " " };
+        match i.cmp(&status.position) {
+            Ordering::Less => Span::raw(format!("{gap}●")),
+            Ordering::Equal => Span::styled(format!("{gap}●"), Style::new().fg(color).bold()),
+            Ordering::Greater => Span::raw(format!("{gap}○")).dim(),
+        }
+    });
+    Line::from(spans.collect::<Vec<_>>())
+}
+
+/// How many work sessions today has seen, or how far it is towards the
+/// daily goal.
+fn tally(status: &Status) -> Line<'static> {
+    Line::from(match (status.goal, status.today) {
+        (Some(goal), _) => format!(
+            "{} today, {} in a row",
+            goal.describe(status.today_totals()),
+            display::days(status.streak)
+        ),
+        (None, 0) => "no sessions yet today".to_owned(),
+        (None, n) => format!("{} today", display::plural(n as usize, "session")),
+    })
+}
+
+fn phase_color(phase: Phase) -> Color {
+    match phase {
+        Phase::Work => Color::Red,
+        Phase::ShortBreak => Color::Green,
+        Phase::LongBreak => Color::Cyan,
+    }
+}
+
+/// The color of the countdown: the phase's while it runs.
+fn color(status: &Status) -> Color {
+    match status.state {
+        State::Idle => Color::DarkGray,
+        State::Running => phase_color(status.phase),
+        State::Paused => Color::Yellow,
+    }
+}
+
+/// The rows of `text` in big digits, which only has digits and colons.
+fn big_text(text: &str) -> [String; GLYPH_HEIGHT] {
+    let mut rows: [String; GLYPH_HEIGHT] = Default::default();
+    for (i, c) in text.chars().enumerate() {
+        let glyph = match c {
+            '0'..='9' => &DIGITS[c as usize - '0' as usize],
+            _ => &COLON,
+        };
+        for (row, part) in rows.iter_mut().zip(glyph) {
+            if i > 0 {
+                row.push(' ');
+            }
+            row.push_str(part);
+        }
+    }
+    rows
+}
+
+const GLYPH_HEIGHT: usize = 5;
+
+const DIGITS: [[&str; GLYPH_HEIGHT]; 10] = [
+    ["█████", "█   █", "█   █", "█   █", "█████"],
+    ["    █", "    █", "    █", "    █", "    █"],
+    ["█████", "    █", "█████", "█    ", "█████"],
+    ["█████", "    █", "█████", "    █", "█████"],
+    ["█   █", "█   █", "█████", "    █", "    █"],
+    ["█████", "█    ", "█████", "    █", "█████"],
+    ["█████", "█    ", "█████", "█   █", "█████"],
+    ["█████", "    █", "    █", "    █", "    █"],
+    ["█████", "█   █", "█████", "█   █", "█████"],
+    ["█████", "█   █", "█████", "    █", "█████"],
+];
+
+const COLON: [&str; GLYPH_HEIGHT] = [" ", "█", " ", "█", " "];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::goal::Goal;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    const STEP: Duration = Duration::from_secs(120);
+
+    #[test]
+    fn maps_keys_to_commands() {
+        let cases = [
+            (KeyCode::Char(' '), Some(Command::Send(Request::Toggle))),
+            (KeyCode::Char('p'), Some(Command::Send(Request::Toggle))),
+            (KeyCode::Char('s'), Some(Command::Send(Request::Skip))),
+            (
+                KeyCode::Char('e'),
+                Some(Command::Send(Request::Extend { by_ms: 120_000 })),
+            ),
+            (
+                KeyCode::Char('+'),
+                Some(Command::Send(Request::Extend { by_ms: 120_000 })),
+            ),
+            (KeyCode::Char('t'), Some(Command::EditTask)),
+            (KeyCode::Char('q'), Some(Command::Quit)),
+            (KeyCode::Esc, Some(Command::Quit)),
+            (KeyCode::Char('x'), None),
+            (KeyCode::Enter, None),
+            (KeyCode::Up, None),
+        ];
+        for (code, expected) in cases {
+            assert_eq!(command(press(code), STEP), expected, "{code:?}");
+        }
+    }
+
+    #[test]
+    fn types_a_task() {
+        let mut task = "writ".to_owned();
+        let keys = [
+            (KeyCode::Char('e'), Edit::Typing),
+            (KeyCode::Backspace, Edit::Typing),
+            (KeyCode::Backspace, Edit::Typing),
+            (KeyCode::Char('q'), Edit::Typing),
+            (KeyCode::Char(' '), Edit::Typing),
+            (KeyCode::Left, Edit::Typing),
+        ];
+        for (code, expected) in keys {
+            assert_eq!(edit(&mut task, press(code)), expected, "{code:?}");
+        }
+        // Keys that would do something else only type while editing.
+        assert_eq!(task, "wriq ");
+        assert_eq!(edit(&mut task, press(KeyCode::Enter)), Edit::Save);
+        assert_eq!(edit(&mut task, press(KeyCode::Esc)), Edit::Cancel);
+        assert_eq!(task, "wriq ");
+
+        let mut empty = String::new();
+        assert_eq!(edit(&mut empty, press(KeyCode::Backspace)), Edit::Typing);
+        assert_eq!(empty, "");
+    }
+
+    #[test]
+    fn draws_big_digits() {
+        assert_eq!(
+            big_text("25:09"),
+            [
+                "█████ █████   █████ █████",
+                "    █ █     █ █   █ █   █",
+                "█████ █████   █   █ █████",
+                "█         █ █ █   █     █",
+                "█████ █████   █████ █████",
+            ]
+            .map(str::to_owned)
+        );
+    }
+
+    #[test]
+    fn tallies_today() {
+        let mut status = Status {
+            phase: Phase::Work,
+            state: State::Idle,
+            planned_ms: 25 * 60_000,
+            elapsed_ms: 0,
+            remaining_ms: 25 * 60_000,
+            position: 0,
+            cycle_length: 8,
+            completed: 0,
+            today: 0,
+            today_focus_ms: 0,
+            goal: None,
+            streak: 0,
+            profile: None,
+            task: None,
+        };
+        let text = |status: &Status| tally(status).to_string();
+        assert_eq!(text(&status), "no sessions yet today");
+        status.today = 1;
+        assert_eq!(text(&status), "1 session today");
+        status.today = 3;
+        assert_eq!(text(&status), "3 sessions today");
+        status.goal = Some(Goal::Pomodoros(4));
+        status.streak = 1;
+        assert_eq!(text(&status), "3/4 pomodoros today, 1 day in a row");
+        status.goal = Some(Goal::Focus(Duration::from_secs(4 * 3600)));
+        status.today_focus_ms = 90 * 60_000 + 59_999;
+        status.streak = 0;
+        assert_eq!(text(&status), "1h30m/4h today, 0 days in a row");
+    }
+}